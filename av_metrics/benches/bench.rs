@@ -2,20 +2,25 @@ extern crate av_metrics;
 #[macro_use]
 extern crate criterion;
 
+use av_metrics::video::butteraugli::calculate_frame_butteraugli;
 use av_metrics::video::ciede::{calculate_frame_ciede, calculate_frame_ciede_nosimd};
+use av_metrics::video::deitp::{calculate_frame_deitp, calculate_frame_deitp_nosimd};
 use av_metrics::video::psnr::calculate_frame_psnr;
 use av_metrics::video::psnr_hvs::calculate_frame_psnr_hvs;
 use av_metrics::video::ssim::{calculate_frame_msssim, calculate_frame_ssim};
+use av_metrics::video::ssimulacra2::calculate_frame_ssimulacra2;
+use av_metrics::video::vif::calculate_frame_vif;
+use av_metrics::video::xpsnr::calculate_frame_xpsnr;
 use av_metrics::video::Decoder;
 use av_metrics::video::FrameInfo;
 use av_metrics::video::Pixel;
+use av_metrics::video::Y4mDecoder;
 use criterion::Criterion;
 use std::fs::File;
-use y4m::Decoder as Y4MDec;
 
 fn get_video_frame<T: Pixel>(filename: &str) -> FrameInfo<T> {
     let mut file = File::open(filename).unwrap();
-    let mut dec = Y4MDec::new(&mut file).unwrap();
+    let mut dec = Y4mDecoder::new(&mut file).unwrap();
     dec.read_video_frame().unwrap()
 }
 
@@ -79,6 +84,76 @@ pub fn ciede2000_simd_benchmark(c: &mut Criterion) {
     });
 }
 
+pub fn ciede2000_yuv444p8_simd_benchmark(c: &mut Criterion) {
+    let frame1 = get_video_frame::<u8>("./testfiles/yuv444p8_input.y4m");
+    let frame2 = get_video_frame::<u8>("./testfiles/yuv444p8_output.y4m");
+    c.bench_function("CIEDE2000 yuv444p8", |b| {
+        b.iter(|| {
+            calculate_frame_ciede(&frame1, &frame2).unwrap();
+        })
+    });
+}
+
+pub fn deitp_nosimd_benchmark(c: &mut Criterion) {
+    let frame1 = get_video_frame::<u8>("./testfiles/yuv420p8_input.y4m");
+    let frame2 = get_video_frame::<u8>("./testfiles/yuv420p8_output.y4m");
+    c.bench_function("DEITP", |b| {
+        b.iter(|| {
+            calculate_frame_deitp_nosimd(&frame1, &frame2).unwrap();
+        })
+    });
+}
+
+pub fn deitp_simd_benchmark(c: &mut Criterion) {
+    let frame1 = get_video_frame::<u8>("./testfiles/yuv420p8_input.y4m");
+    let frame2 = get_video_frame::<u8>("./testfiles/yuv420p8_output.y4m");
+    c.bench_function("DEITP", |b| {
+        b.iter(|| {
+            calculate_frame_deitp(&frame1, &frame2).unwrap();
+        })
+    });
+}
+
+pub fn vif_benchmark(c: &mut Criterion) {
+    let frame1 = get_video_frame::<u8>("./testfiles/yuv420p8_input.y4m");
+    let frame2 = get_video_frame::<u8>("./testfiles/yuv420p8_output.y4m");
+    c.bench_function("VIF", |b| {
+        b.iter(|| {
+            calculate_frame_vif(&frame1, &frame2).unwrap();
+        })
+    });
+}
+
+pub fn ssimulacra2_benchmark(c: &mut Criterion) {
+    let frame1 = get_video_frame::<u8>("./testfiles/yuv420p8_input.y4m");
+    let frame2 = get_video_frame::<u8>("./testfiles/yuv420p8_output.y4m");
+    c.bench_function("SSIMULACRA2", |b| {
+        b.iter(|| {
+            calculate_frame_ssimulacra2(&frame1, &frame2).unwrap();
+        })
+    });
+}
+
+pub fn butteraugli_benchmark(c: &mut Criterion) {
+    let frame1 = get_video_frame::<u8>("./testfiles/yuv420p8_input.y4m");
+    let frame2 = get_video_frame::<u8>("./testfiles/yuv420p8_output.y4m");
+    c.bench_function("Butteraugli", |b| {
+        b.iter(|| {
+            calculate_frame_butteraugli(&frame1, &frame2).unwrap();
+        })
+    });
+}
+
+pub fn xpsnr_benchmark(c: &mut Criterion) {
+    let frame1 = get_video_frame::<u8>("./testfiles/yuv420p8_input.y4m");
+    let frame2 = get_video_frame::<u8>("./testfiles/yuv420p8_output.y4m");
+    c.bench_function("XPSNR", |b| {
+        b.iter(|| {
+            calculate_frame_xpsnr(&frame1, &frame2).unwrap();
+        })
+    });
+}
+
 criterion_group!(
     benches,
     psnr_benchmark,
@@ -86,6 +161,13 @@ criterion_group!(
     ssim_benchmark,
     msssim_benchmark,
     ciede2000_nosimd_benchmark,
-    ciede2000_simd_benchmark
+    ciede2000_simd_benchmark,
+    ciede2000_yuv444p8_simd_benchmark,
+    deitp_nosimd_benchmark,
+    deitp_simd_benchmark,
+    vif_benchmark,
+    ssimulacra2_benchmark,
+    butteraugli_benchmark,
+    xpsnr_benchmark
 );
 criterion_main!(benches);
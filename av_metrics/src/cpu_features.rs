@@ -0,0 +1,115 @@
+//! Selects which CPU features are used by the SIMD implementations of metrics.
+//!
+//! By default, the highest level supported by the current CPU is used.
+//! Setting the environment variable `AV_METRICS_DISABLE_SIMD` to any value
+//! limits this to portable Rust code, as does calling
+//! [`set_cpu_feature_level`](fn.set_cpu_feature_level.html) with `CpuFeatureLevel::RUST`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A set of CPU features which metrics may use.
+///
+/// Levels are ordered, so each level includes the features of all lower levels.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CpuFeatureLevel {
+    /// Portable Rust code, without any SIMD.
+    RUST,
+    /// x86 AVX2 instructions.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    AVX2,
+}
+
+impl CpuFeatureLevel {
+    /// Every level which may be used on the current architecture, from lowest to highest.
+    pub const ALL: &'static [CpuFeatureLevel] = &[
+        CpuFeatureLevel::RUST,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        CpuFeatureLevel::AVX2,
+    ];
+
+    /// Returns the highest level supported by the current CPU,
+    /// ignoring any limit set by the environment or by `set_cpu_feature_level`.
+    pub fn detect() -> Self {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return CpuFeatureLevel::AVX2;
+            }
+        }
+        CpuFeatureLevel::RUST
+    }
+
+    fn index(self) -> usize {
+        CpuFeatureLevel::ALL
+            .iter()
+            .position(|&level| level == self)
+            .unwrap()
+    }
+}
+
+/// The level used by newly created metrics.
+///
+/// This is the level detected for the current CPU, limited to
+/// `CpuFeatureLevel::RUST` if `AV_METRICS_DISABLE_SIMD` is set,
+/// and to the level passed to `set_cpu_feature_level`, if any.
+impl Default for CpuFeatureLevel {
+    fn default() -> Self {
+        effective_level(
+            CpuFeatureLevel::detect(),
+            CpuFeatureLevel::ALL
+                .get(LIMIT.load(Ordering::Relaxed))
+                .copied(),
+            std::env::var_os("AV_METRICS_DISABLE_SIMD").is_some(),
+        )
+    }
+}
+
+/// Limits the detected level to `limit`, or to `CpuFeatureLevel::RUST` if SIMD is disabled.
+fn effective_level(
+    detected: CpuFeatureLevel,
+    limit: Option<CpuFeatureLevel>,
+    simd_disabled: bool,
+) -> CpuFeatureLevel {
+    if simd_disabled {
+        return CpuFeatureLevel::RUST;
+    }
+    limit.map_or(detected, |limit| limit.min(detected))
+}
+
+/// The index in `CpuFeatureLevel::ALL` of the highest level which may be used.
+static LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Limits the CPU features used by metrics to those in `level`.
+///
+/// This applies to every metric created after the call, across all threads.
+/// Features which the current CPU does not support are never used,
+/// regardless of this setting.
+pub fn set_cpu_feature_level(level: CpuFeatureLevel) {
+    LIMIT.store(level.index(), Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_feature_level_limit() {
+        for &detected in CpuFeatureLevel::ALL {
+            assert_eq!(detected, effective_level(detected, None, false));
+            assert_eq!(CpuFeatureLevel::RUST, effective_level(detected, None, true));
+            for &limit in CpuFeatureLevel::ALL {
+                // A limit above the detected level has no effect
+                assert_eq!(
+                    limit.min(detected),
+                    effective_level(detected, Some(limit), false)
+                );
+                assert_eq!(
+                    CpuFeatureLevel::RUST,
+                    effective_level(detected, Some(limit), true)
+                );
+            }
+        }
+        assert!(CpuFeatureLevel::default() <= CpuFeatureLevel::detect());
+    }
+}
@@ -3,7 +3,11 @@
 //! in the future.
 
 #![allow(clippy::cast_lossless)]
+// `usize::div_ceil`, `Option::is_none_or` and `is_some_and` are too new
+// for the versions of Rust we support
+#![allow(clippy::manual_div_ceil)]
 #![allow(clippy::needless_range_loop)]
+#![allow(clippy::unnecessary_map_or)]
 #![allow(clippy::unreadable_literal)]
 #![deny(missing_docs)]
 
@@ -12,6 +16,7 @@ extern crate err_derive;
 #[macro_use]
 extern crate itertools;
 
+pub mod cpu_features;
 pub mod video;
 
 /// Possible errors that may occur during processing of a metric.
@@ -37,6 +42,16 @@ pub enum MetricsError {
         #[doc(hidden)]
         reason: &'static str,
     },
+    /// Indicates the options for comparing two inputs are not valid.
+    #[error(display = "Invalid options: {}", reason)]
+    InvalidOptions {
+        #[doc(hidden)]
+        reason: &'static str,
+    },
+    /// Indicates an input video could not be decoded.
+    #[cfg(feature = "decode")]
+    #[error(display = "Could not decode input video: {}", _0)]
+    DecodeFailed(#[error(source)] video::DecodeError),
     /// Placeholder
     #[doc(hidden)]
     #[error(display = "Unreachable")]
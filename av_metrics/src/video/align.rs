@@ -0,0 +1,425 @@
+//! Detects a temporal offset between two videos.
+//!
+//! If an encoder or transcoder adds or drops frames at the start of a video,
+//! every later frame is compared against the wrong frame of the other video,
+//! and the resulting scores are meaningless.
+//! This module searches for the offset at which the two videos match best,
+//! which can then be applied through [`VideoOptions`](../struct.VideoOptions.html).
+//!
+//! Frames which are dropped or duplicated in the middle of a video are handled
+//! separately, by re-syncing the videos one frame at a time.
+//! See `VideoOptions::resync_lookahead`.
+
+use crate::video::decode::Decoder;
+use crate::video::pixel::Pixel;
+use crate::video::psnr::{calculate_frame_psnr, calculate_frame_psnr_avg};
+use crate::video::{read_frame, FrameInfo, FramePair, FrameRef, ResyncReport, VideoOptions};
+use crate::MetricsError;
+use std::collections::VecDeque;
+use std::error::Error;
+
+/// Options controlling the search for an alignment between two videos.
+#[derive(Debug, Clone, Copy)]
+pub struct AlignmentOptions {
+    /// The largest number of frames by which either video may be offset.
+    ///
+    /// Every offset from `max_offset` frames skipped in the first video
+    /// to `max_offset` frames skipped in the second video is tried.
+    pub max_offset: usize,
+    /// The number of frames compared at each offset.
+    /// At least one frame is always compared.
+    ///
+    /// Offsets which leave fewer than `frames` frames to compare are skipped,
+    /// so that every offset is scored on the same number of frames.
+    /// If either video is shorter than `frames`, its length is used instead.
+    pub frames: usize,
+}
+
+impl Default for AlignmentOptions {
+    fn default() -> Self {
+        AlignmentOptions {
+            max_offset: 10,
+            frames: 5,
+        }
+    }
+}
+
+/// The best alignment found between two videos.
+///
+/// At most one of `offset1` and `offset2` is non-zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Alignment {
+    /// The number of frames to skip at the start of the first video.
+    pub offset1: usize,
+    /// The number of frames to skip at the start of the second video.
+    pub offset2: usize,
+    /// The average PSNR of the compared frames at this alignment.
+    pub psnr: f64,
+}
+
+impl Alignment {
+    /// Sets the offsets of `options` to this alignment.
+    ///
+    /// The search reads frames from both decoders,
+    /// so new decoders must be opened for the comparison itself.
+    pub fn apply(&self, options: &mut VideoOptions) {
+        options.offset1 = self.offset1;
+        options.offset2 = self.offset2;
+    }
+}
+
+/// Finds the frame offset at which two videos match best.
+///
+/// Each offset within `options.max_offset` is tried by comparing the PSNR of
+/// the first `options.frames` frames of the two videos at that offset.
+/// Offsets near the end of a short video, which leave fewer frames to compare,
+/// are skipped. The offset with the highest average PSNR is returned.
+/// If several offsets score equally, the smallest is preferred.
+///
+/// Frames are read from the start of each decoder and kept in memory
+/// during the search.
+#[inline]
+pub fn find_alignment<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &AlignmentOptions,
+) -> Result<Alignment, Box<dyn Error + Send + Sync>> {
+    let details1 = decoder1.get_video_details();
+    let details2 = decoder2.get_video_details();
+    details1.can_compare(&details2)?;
+
+    if details1.bit_depth > 8 {
+        find_alignment_frames::<u16, _>(decoder1, decoder2, options)
+    } else {
+        find_alignment_frames::<u8, _>(decoder1, decoder2, options)
+    }
+}
+
+fn find_alignment_frames<T: Pixel, D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &AlignmentOptions,
+) -> Result<Alignment, Box<dyn Error + Send + Sync>> {
+    let frames = options.frames.max(1);
+    let frames1 = read_frames::<T, D>(decoder1, options.max_offset + frames)?;
+    let frames2 = read_frames::<T, D>(decoder2, options.max_offset + frames)?;
+    // Videos which are too short for the full window are compared over their whole length
+    let frames = frames.min(frames1.len()).min(frames2.len());
+
+    // Try the smallest offsets first, so they are preferred in case of a tie
+    let offsets = (0..=options.max_offset).flat_map(|offset| {
+        if offset == 0 {
+            vec![(0, 0)]
+        } else {
+            vec![(offset, 0), (0, offset)]
+        }
+    });
+    let mut best: Option<Alignment> = None;
+    for (offset1, offset2) in offsets {
+        let pairs = frames1
+            .iter()
+            .skip(offset1)
+            .zip(frames2.iter().skip(offset2))
+            .take(frames);
+        let mut total = 0.0;
+        let mut count = 0;
+        for (frame1, frame2) in pairs {
+            total += calculate_frame_psnr(frame1, frame2)?.avg;
+            count += 1;
+        }
+        // Scores from fewer frames are not comparable, and are more easily skewed
+        if count == 0 || count < frames {
+            continue;
+        }
+        let psnr = total / count as f64;
+        if best.map_or(true, |best| psnr > best.psnr) {
+            best = Some(Alignment {
+                offset1,
+                offset2,
+                psnr,
+            });
+        }
+    }
+
+    best.ok_or_else(|| {
+        MetricsError::UnsupportedInput {
+            reason: "No readable frames found in one or more input files",
+        }
+        .into()
+    })
+}
+
+/// Matches each frame of the second video to a frame of the first video,
+/// in order to compare videos where frames were dropped or duplicated.
+pub(crate) struct Resync<T: Pixel> {
+    lookahead: usize,
+    /// Upcoming frames of the first video. The front frame has the index `next1`.
+    pending: VecDeque<FrameInfo<T>>,
+    next1: usize,
+    next2: usize,
+    /// The most recently matched frame of the first video, and its index.
+    previous: Option<(usize, FrameInfo<T>)>,
+    pub(crate) report: ResyncReport,
+}
+
+/// A candidate match for a frame of the second video.
+#[derive(Debug, Clone, Copy)]
+enum ResyncMatch {
+    /// The previously matched frame, meaning the frame was duplicated.
+    Previous,
+    /// A pending frame, meaning this many frames before it were dropped.
+    Pending(usize),
+}
+
+impl<T: Pixel> Resync<T> {
+    /// `first1` and `first2` are the indices of the next frame in each decoder.
+    pub(crate) fn new(lookahead: usize, first1: usize, first2: usize) -> Self {
+        Resync {
+            lookahead,
+            pending: VecDeque::with_capacity(lookahead + 1),
+            next1: first1,
+            next2: first2,
+            previous: None,
+            report: ResyncReport::default(),
+        }
+    }
+
+    /// Reads the next frame of the second video, and pairs it with
+    /// the frame of the first video which it matches best.
+    /// Returns `None` once the end of either video is reached.
+    pub(crate) fn read_pair<D: Decoder>(
+        &mut self,
+        decoder1: &mut D,
+        decoder2: &mut D,
+    ) -> Result<Option<FramePair<T>>, MetricsError> {
+        while self.pending.len() <= self.lookahead {
+            match read_frame(decoder1)? {
+                Some(frame) => self.pending.push_back(frame),
+                None => break,
+            }
+        }
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let frame2 = match read_frame(decoder2)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+
+        // Prefer the next frame, then a duplicate, then the fewest dropped frames
+        let target = FrameRef::from(&frame2);
+        let score = |frame1: &FrameInfo<T>| calculate_frame_psnr_avg(&frame1.into(), &target);
+        let mut best = ResyncMatch::Pending(0);
+        let mut best_score = score(&self.pending[0])?;
+        if let Some((_, previous)) = self.previous.as_ref() {
+            let previous_score = score(previous)?;
+            if previous_score > best_score {
+                best = ResyncMatch::Previous;
+                best_score = previous_score;
+            }
+        }
+        for (dropped, frame1) in self.pending.iter().enumerate().skip(1) {
+            let frame_score = score(frame1)?;
+            if frame_score > best_score {
+                best = ResyncMatch::Pending(dropped);
+                best_score = frame_score;
+            }
+        }
+
+        let (index1, frame1) = match best {
+            ResyncMatch::Previous => {
+                let (index1, frame1) = self.previous.as_ref().unwrap();
+                self.report.duplicated.push(self.next2);
+                (*index1, frame1.clone())
+            }
+            ResyncMatch::Pending(dropped) => {
+                for _ in 0..dropped {
+                    self.pending.pop_front();
+                    self.report.dropped.push(self.next1);
+                    self.next1 += 1;
+                }
+                let frame1 = self.pending.pop_front().unwrap();
+                let index1 = self.next1;
+                self.next1 += 1;
+                self.previous = Some((index1, frame1.clone()));
+                (index1, frame1)
+            }
+        };
+        self.report.pairs.push((index1, self.next2));
+        self.next2 += 1;
+        Ok(Some((frame1, frame2)))
+    }
+}
+
+fn read_frames<T: Pixel, D: Decoder>(
+    decoder: &mut D,
+    limit: usize,
+) -> Result<Vec<FrameInfo<T>>, MetricsError> {
+    let mut frames = Vec::with_capacity(limit);
+    while frames.len() < limit {
+        match read_frame(decoder)? {
+            Some(frame) => frames.push(frame),
+            None => break,
+        }
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::psnr::calculate_video_psnr;
+    use std::fs::File;
+    use std::io::{Cursor, Read};
+
+    fn read_file(filename: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        File::open(filename)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        data
+    }
+
+    /// Removes the first frame from a y4m file in memory.
+    fn drop_first_frame(mut data: Vec<u8>) -> Vec<u8> {
+        let mut frames = data
+            .windows(6)
+            .enumerate()
+            .filter(|(_, window)| window == b"FRAME\n")
+            .map(|(i, _)| i);
+        let first = frames.next().unwrap();
+        let second = frames.next().unwrap();
+        data.drain(first..second);
+        data
+    }
+
+    /// Splits a y4m file into its header and the data for each frame.
+    fn split_frames(data: &[u8]) -> (&[u8], Vec<&[u8]>) {
+        let starts = data
+            .windows(6)
+            .enumerate()
+            .filter(|(_, window)| window == b"FRAME\n")
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let frames = starts
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| &data[start..starts.get(i + 1).copied().unwrap_or(data.len())])
+            .collect();
+        (&data[..starts[0]], frames)
+    }
+
+    fn align(input1: &[u8], input2: &[u8], options: &AlignmentOptions) -> Alignment {
+        let mut input1 = Cursor::new(input1);
+        let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+        let mut input2 = Cursor::new(input2);
+        let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+        find_alignment(&mut dec1, &mut dec2, options).unwrap()
+    }
+
+    #[test]
+    fn align_yuv420p8() {
+        let input = read_file("./testfiles/yuv420p8_input.y4m");
+        let output = read_file("./testfiles/yuv420p8_output.y4m");
+        let options = AlignmentOptions {
+            max_offset: 2,
+            frames: 1,
+        };
+
+        let alignment = align(&input, &output, &options);
+        assert_eq!((0, 0), (alignment.offset1, alignment.offset2));
+
+        let dropped = drop_first_frame(output.clone());
+        let alignment = align(&input, &dropped, &options);
+        assert_eq!((1, 0), (alignment.offset1, alignment.offset2));
+
+        let dropped = drop_first_frame(input.clone());
+        let alignment = align(&dropped, &output, &options);
+        assert_eq!((0, 1), (alignment.offset1, alignment.offset2));
+
+        // The aligned comparison matches the original one, minus the dropped frame
+        let mut video_options = VideoOptions::default();
+        alignment.apply(&mut video_options);
+        let mut input1 = Cursor::new(&dropped);
+        let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+        let mut input2 = Cursor::new(&output);
+        let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+        let aligned = calculate_video_psnr(&mut dec1, &mut dec2, &video_options).unwrap();
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let original =
+            calculate_video_psnr(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_eq!(2, aligned.frames.len());
+        assert_eq!(original.frames[1].avg, aligned.frames[0].avg);
+        assert_eq!(original.frames[2].avg, aligned.frames[1].avg);
+    }
+
+    #[test]
+    fn align_short_yuv420p8() {
+        let input = read_file("./testfiles/yuv420p8_input.y4m");
+        let output = read_file("./testfiles/yuv420p8_output.y4m");
+        // Replace the last frame of the first video with the first frame of the second,
+        // so the largest offset matches perfectly, but only on a single frame
+        let (header, frames1) = split_frames(&input);
+        let (_, frames2) = split_frames(&output);
+        let input = [header, frames1[0], frames1[1], frames2[0]].concat();
+
+        // The clips are shorter than `max_offset + frames`
+        let options = AlignmentOptions {
+            max_offset: 2,
+            frames: 2,
+        };
+        let alignment = align(&input, &output, &options);
+        assert_eq!((0, 0), (alignment.offset1, alignment.offset2));
+
+        // A window longer than the videos is reduced to their length
+        let options = AlignmentOptions {
+            max_offset: 2,
+            frames: 10,
+        };
+        let alignment = align(&input, &output, &options);
+        assert_eq!((0, 0), (alignment.offset1, alignment.offset2));
+    }
+
+    #[test]
+    fn resync_yuv420p8() {
+        let input = read_file("./testfiles/yuv420p8_input.y4m");
+        let output = read_file("./testfiles/yuv420p8_output.y4m");
+        // Duplicate the first frame in place of the second
+        let (header, frames) = split_frames(&output);
+        let resynced = [header, frames[0], frames[0], frames[2]].concat();
+
+        let mut input1 = Cursor::new(&input);
+        let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+        let mut input2 = Cursor::new(&resynced);
+        let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+        let options = VideoOptions {
+            resync_lookahead: Some(2),
+            ..Default::default()
+        };
+        let result = calculate_video_psnr(&mut dec1, &mut dec2, &options).unwrap();
+        assert_eq!(
+            Some(ResyncReport {
+                pairs: vec![(0, 0), (0, 1), (2, 2)],
+                dropped: vec![1],
+                duplicated: vec![1],
+            }),
+            result.resync
+        );
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let original =
+            calculate_video_psnr(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_eq!(original.frames[0].avg, result.frames[0].avg);
+        assert_eq!(original.frames[0].avg, result.frames[1].avg);
+        assert_eq!(original.frames[2].avg, result.frames[2].avg);
+    }
+}
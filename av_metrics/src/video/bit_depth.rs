@@ -0,0 +1,221 @@
+//! Converts frames between bit depths, so that videos with different bit depths
+//! can be compared.
+//!
+//! This is commonly needed when comparing a high bit depth source
+//! with a low bit depth encode.
+
+#[cfg(feature = "decode")]
+use crate::video::decode::{DecodeError, Decoder, VideoDetails};
+use crate::video::pixel::{CastFromPrimitive, Pixel};
+use crate::video::{FrameInfo, FrameRef, PlaneData, PlaneRef};
+
+/// How to bring two videos with different bit depths to a common bit depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitDepthNormalization {
+    /// Shift the video with the lower bit depth up to the higher bit depth.
+    ShiftUp,
+    /// Round the video with the higher bit depth down to the lower bit depth.
+    RoundDown,
+}
+
+impl BitDepthNormalization {
+    /// The common bit depth which videos with the given bit depths are converted to.
+    pub fn target_bit_depth(self, bit_depth1: usize, bit_depth2: usize) -> usize {
+        match self {
+            BitDepthNormalization::ShiftUp => bit_depth1.max(bit_depth2),
+            BitDepthNormalization::RoundDown => bit_depth1.min(bit_depth2),
+        }
+    }
+}
+
+/// Converts a frame to the given bit depth.
+///
+/// Increasing the bit depth shifts each sample up, and decreasing it rounds
+/// each sample to the nearest value at the new bit depth.
+/// `U` must be able to hold samples of the new bit depth,
+/// i.e. `u16` for bit depths above 8.
+pub fn convert_bit_depth<'a, T: Pixel, U: Pixel>(
+    frame: impl Into<FrameRef<'a, T>>,
+    bit_depth: usize,
+) -> FrameInfo<U> {
+    let frame = frame.into();
+    FrameInfo {
+        planes: [
+            convert_plane(&frame.planes[0], frame.bit_depth, bit_depth),
+            convert_plane(&frame.planes[1], frame.bit_depth, bit_depth),
+            convert_plane(&frame.planes[2], frame.bit_depth, bit_depth),
+        ],
+        bit_depth,
+        chroma_sampling: frame.chroma_sampling,
+    }
+}
+
+fn convert_plane<T: Pixel, U: Pixel>(
+    plane: &PlaneRef<T>,
+    from_bit_depth: usize,
+    to_bit_depth: usize,
+) -> PlaneData<U> {
+    PlaneData {
+        width: plane.width(),
+        height: plane.height(),
+        data: plane
+            .rows()
+            .flatten()
+            .map(|&sample| {
+                U::cast_from(convert_sample(
+                    u32::cast_from(sample),
+                    from_bit_depth,
+                    to_bit_depth,
+                ))
+            })
+            .collect(),
+    }
+}
+
+fn convert_sample(sample: u32, from_bit_depth: usize, to_bit_depth: usize) -> u32 {
+    if to_bit_depth >= from_bit_depth {
+        sample << (to_bit_depth - from_bit_depth)
+    } else {
+        let shift = from_bit_depth - to_bit_depth;
+        let max_value = (1 << to_bit_depth) - 1;
+        ((sample + (1 << (shift - 1))) >> shift).min(max_value)
+    }
+}
+
+/// A decoder which converts each frame to a given bit depth after decoding it.
+///
+/// Frames are read from the wrapped decoder using the pixel type for its own bit depth,
+/// and returned using the pixel type requested by the caller,
+/// so two videos read as `u8` and `u16` can be compared.
+/// This is used to implement `VideoOptions::bit_depth_normalization`.
+#[cfg(feature = "decode")]
+pub struct BitDepthDecoder<'a, D: Decoder> {
+    decoder: &'a mut D,
+    /// The bit depth to convert to, or `None` to leave frames unchanged.
+    bit_depth: Option<usize>,
+}
+
+#[cfg(feature = "decode")]
+impl<'a, D: Decoder> BitDepthDecoder<'a, D> {
+    /// Wraps a decoder, converting its frames to the given bit depth if `bit_depth` is set.
+    pub fn new(decoder: &'a mut D, bit_depth: Option<usize>) -> Self {
+        BitDepthDecoder { decoder, bit_depth }
+    }
+}
+
+#[cfg(feature = "decode")]
+impl<D: Decoder> Decoder for BitDepthDecoder<'_, D> {
+    fn read_video_frame<T: Pixel>(&mut self) -> Result<FrameInfo<T>, DecodeError> {
+        let source_bit_depth = self.decoder.get_bit_depth();
+        match self.bit_depth {
+            Some(bit_depth) if bit_depth != source_bit_depth => {
+                if source_bit_depth > 8 {
+                    let frame = self.decoder.read_video_frame::<u16>()?;
+                    Ok(convert_bit_depth(&frame, bit_depth))
+                } else {
+                    let frame = self.decoder.read_video_frame::<u8>()?;
+                    Ok(convert_bit_depth(&frame, bit_depth))
+                }
+            }
+            _ => self.decoder.read_video_frame(),
+        }
+    }
+
+    fn get_video_details(&self) -> VideoDetails {
+        let mut details = self.decoder.get_video_details();
+        if let Some(bit_depth) = self.bit_depth {
+            details.bit_depth = bit_depth;
+        }
+        details
+    }
+}
+
+/// Wraps two decoders so that their frames are converted to a common bit depth
+/// according to `normalization`.
+/// If `normalization` is `None`, frames are passed through unchanged.
+#[cfg(feature = "decode")]
+pub fn normalize_decoders<'a, D: Decoder>(
+    decoder1: &'a mut D,
+    decoder2: &'a mut D,
+    normalization: Option<BitDepthNormalization>,
+) -> (BitDepthDecoder<'a, D>, BitDepthDecoder<'a, D>) {
+    let bit_depth = normalization.map(|normalization| {
+        normalization.target_bit_depth(decoder1.get_bit_depth(), decoder2.get_bit_depth())
+    });
+    (
+        BitDepthDecoder::new(decoder1, bit_depth),
+        BitDepthDecoder::new(decoder2, bit_depth),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::psnr::calculate_video_psnr;
+    use crate::video::{ChromaSampling, VideoOptions};
+    use std::io::Cursor;
+
+    #[test]
+    fn convert_samples() {
+        let plane = |data: Vec<u16>| PlaneData {
+            width: data.len(),
+            height: 1,
+            data,
+        };
+        let frame = FrameInfo {
+            planes: [
+                plane(vec![0, 1, 2, 5, 6, 1021, 1022, 1023]),
+                plane(vec![]),
+                plane(vec![]),
+            ],
+            bit_depth: 10,
+            chroma_sampling: ChromaSampling::Cs400,
+        };
+        let rounded: FrameInfo<u8> = convert_bit_depth(&frame, 8);
+        assert_eq!(vec![0, 0, 1, 1, 2, 255, 255, 255], rounded.planes[0].data);
+        let shifted: FrameInfo<u16> = convert_bit_depth(&rounded, 10);
+        assert_eq!(
+            vec![0, 0, 4, 4, 8, 1020, 1020, 1020],
+            shifted.planes[0].data
+        );
+        assert_eq!(10, shifted.bit_depth);
+    }
+
+    #[test]
+    fn normalize_yuv420p10_to_yuv420p8() {
+        // Create an 8-bit copy of the 10-bit input
+        let input = std::fs::read("./testfiles/yuv420p10_input.y4m").unwrap();
+        let mut input1 = Cursor::new(&input);
+        let mut dec = Y4mDecoder::new(&mut input1).unwrap();
+        let mut converted = b"YUV4MPEG2 W640 H360 F25:1 Ip A1:1 C420jpeg\n".to_vec();
+        while let Ok(frame) = dec.read_video_frame::<u16>() {
+            let frame: FrameInfo<u8> = convert_bit_depth(&frame, 8);
+            converted.extend_from_slice(b"FRAME\n");
+            for plane in &frame.planes {
+                converted.extend_from_slice(&plane.data);
+            }
+        }
+
+        let run = |normalization| {
+            let mut input1 = Cursor::new(&input);
+            let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+            let mut input2 = Cursor::new(&converted);
+            let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+            let options = VideoOptions {
+                bit_depth_normalization: normalization,
+                ..Default::default()
+            };
+            calculate_video_psnr(&mut dec1, &mut dec2, &options)
+        };
+        assert!(run(None).is_err());
+
+        let result = run(Some(BitDepthNormalization::RoundDown)).unwrap();
+        assert_eq!(3, result.frames.len());
+        assert_eq!(100.0, result.aggregate.avg);
+
+        let result = run(Some(BitDepthNormalization::ShiftUp)).unwrap();
+        assert_eq!(3, result.frames.len());
+        assert!(result.aggregate.avg > 50.0);
+    }
+}
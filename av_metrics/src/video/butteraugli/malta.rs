@@ -0,0 +1,146 @@
+//! The Malta filter, which measures differences along lines through each pixel.
+//!
+//! Differences which line up, such as those along an edge, are summed before they
+//! are squared, so they are more visible than the same differences scattered as noise.
+
+use super::FloatPlane;
+
+/// The distance from the center to the end of each line, in pixels.
+const RADIUS: isize = 4;
+
+/// Lines through the center pixel in 16 orientations, as (dy, dx) offsets.
+///
+/// Horizontal, vertical and shallow lines have 9 samples,
+/// and diagonal lines have 7 samples.
+#[rustfmt::skip]
+const LINES: [&[(isize, isize)]; 16] = [
+    &[(0, -4), (0, -3), (0, -2), (0, -1), (0, 0), (0, 1), (0, 2), (0, 3), (0, 4)],
+    &[(-4, 0), (-3, 0), (-2, 0), (-1, 0), (0, 0), (1, 0), (2, 0), (3, 0), (4, 0)],
+    &[(-3, -3), (-2, -2), (-1, -1), (0, 0), (1, 1), (2, 2), (3, 3)],
+    &[(-3, 3), (-2, 2), (-1, 1), (0, 0), (1, -1), (2, -2), (3, -3)],
+    &[(-4, 1), (-3, 1), (-2, 1), (-1, 0), (0, 0), (1, 0), (2, -1), (3, -1), (4, -1)],
+    &[(-4, -1), (-3, -1), (-2, -1), (-1, 0), (0, 0), (1, 0), (2, 1), (3, 1), (4, 1)],
+    &[(-1, -4), (-1, -3), (-1, -2), (0, -1), (0, 0), (0, 1), (1, 2), (1, 3), (1, 4)],
+    &[(1, -4), (1, -3), (1, -2), (0, -1), (0, 0), (0, 1), (-1, 2), (-1, 3), (-1, 4)],
+    &[(-3, -2), (-2, -1), (-1, -1), (0, 0), (1, 1), (2, 1), (3, 2)],
+    &[(-3, 2), (-2, 1), (-1, 1), (0, 0), (1, -1), (2, -1), (3, -2)],
+    &[(-2, -3), (-1, -2), (-1, -1), (0, 0), (1, 1), (1, 2), (2, 3)],
+    &[(-2, 3), (-1, 2), (-1, 1), (0, 0), (1, -1), (1, -2), (2, -3)],
+    &[(-2, -4), (-2, -3), (-1, -2), (-1, -1), (0, 0), (1, 1), (1, 2), (2, 3), (2, 4)],
+    &[(2, -4), (2, -3), (1, -2), (1, -1), (0, 0), (-1, 1), (-1, 2), (-2, 3), (-2, 4)],
+    &[(-4, -2), (-3, -2), (-2, -1), (-1, -1), (0, 0), (1, 1), (2, 1), (3, 2), (4, 2)],
+    &[(-4, 2), (-3, 2), (-2, 1), (-1, 1), (0, 0), (1, -1), (2, -1), (3, -2), (4, -2)],
+];
+
+/// The lines of samples which a Malta filter sums.
+pub(super) struct MaltaKernel {
+    lines: Vec<Vec<(isize, isize)>>,
+    /// The weight of the differences, which is higher for the sparser
+    /// low frequency kernel.
+    mul: f64,
+}
+
+impl MaltaKernel {
+    /// A kernel which uses every sample along each line.
+    pub(super) fn high_frequency() -> Self {
+        MaltaKernel {
+            lines: LINES.iter().map(|line| line.to_vec()).collect(),
+            mul: 0.399_058_176_37,
+        }
+    }
+
+    /// A kernel which uses five samples spread along each line.
+    pub(super) fn low_frequency() -> Self {
+        MaltaKernel {
+            lines: LINES
+                .iter()
+                .map(|line| {
+                    let samples: &[usize] = if line.len() == 9 {
+                        &[0, 2, 4, 6, 8]
+                    } else {
+                        &[0, 2, 3, 4, 6]
+                    };
+                    samples.iter().map(|&i| line[i]).collect()
+                })
+                .collect(),
+            mul: 0.611_612_573_796,
+        }
+    }
+}
+
+/// Adds the Malta filtered difference between two planes to `dst`.
+///
+/// Differences are weighted by `w_0gt1` and compressed by `norm1`, with an
+/// additional penalty weighted by `w_0lt1` when the distorted plane is much
+/// smaller or larger than the reference.
+pub(super) fn malta_diff_map(
+    lum0: &FloatPlane,
+    lum1: &FloatPlane,
+    w_0gt1: f64,
+    w_0lt1: f64,
+    norm1: f64,
+    kernel: &MaltaKernel,
+    dst: &mut FloatPlane,
+) {
+    const LEN: f64 = 3.75;
+    const WEIGHT0: f64 = 0.5;
+    const WEIGHT1: f64 = 0.33;
+    let norm2_0gt1 = (kernel.mul * (WEIGHT0 * w_0gt1).sqrt() / (LEN * 2. + 1.) * norm1) as f32;
+    let norm2_0lt1 = (kernel.mul * (WEIGHT1 * w_0lt1).sqrt() / (LEN * 2. + 1.) * norm1) as f32;
+    let norm1 = norm1 as f32;
+
+    // The differences are padded with zeros, so the lines may extend past the edges
+    let (width, height) = (lum0.width, lum0.height);
+    let stride = width + 2 * RADIUS as usize;
+    let mut diffs = vec![0f32; stride * (height + 2 * RADIUS as usize)];
+    for y in 0..height {
+        let row = &mut diffs[(y + RADIUS as usize) * stride + RADIUS as usize..][..width];
+        for (x, diff) in row.iter_mut().enumerate() {
+            let v0 = lum0.data[y * width + x];
+            let v1 = lum1.data[y * width + x];
+            let absval = 0.5 * (v0.abs() + v1.abs());
+            *diff = norm2_0gt1 / (norm1 + absval) * (v0 - v1);
+
+            let scaler = norm2_0lt1 / (norm1 + absval);
+            let too_small = 0.55 * v0.abs();
+            let too_big = 1.05 * v0.abs();
+            if v0 < 0. {
+                if v1 > -too_small {
+                    *diff -= scaler * (v1 + too_small);
+                } else if v1 < -too_big {
+                    *diff += scaler * (-v1 - too_big);
+                }
+            } else if v1 < too_small {
+                *diff += scaler * (too_small - v1);
+            } else if v1 > too_big {
+                *diff -= scaler * (v1 - too_big);
+            }
+        }
+    }
+
+    let lines = kernel
+        .lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|&(dy, dx)| dy * stride as isize + dx)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for y in 0..height {
+        for x in 0..width {
+            let center = ((y + RADIUS as usize) * stride + x + RADIUS as usize) as isize;
+            let sum = lines
+                .iter()
+                .map(|line| {
+                    let sum = line
+                        .iter()
+                        .map(|&offset| diffs[(center + offset) as usize])
+                        .sum::<f32>();
+                    sum * sum
+                })
+                .sum::<f32>();
+            dst.data[y * width + x] += sum;
+        }
+    }
+}
@@ -0,0 +1,802 @@
+//! Butteraugli, a psychovisual image distance from the JPEG XL project.
+//!
+//! Each frame is converted to linear RGB, then to a model of the responses of the
+//! cones of the eye, which adapts to the local intensity of the image. The responses
+//! are split into frequency bands, and the difference in each band is weighted by
+//! how visible it is, with masking from nearby high frequency detail. The result is
+//! a map of the perceived distance at each pixel.
+//!
+//! The distance between two frames is reported both as the maximum of this map,
+//! which measures the worst artifact in the frame, and as its 3-norm, which measures
+//! the overall distortion. Lower is better, and 0 means the frames are identical.
+//! A max-norm distance of 1 is roughly the threshold of visible differences.
+//!
+//! See https://github.com/google/butteraugli for more details.
+
+mod malta;
+
+#[cfg(feature = "decode")]
+use crate::video::decode::Decoder;
+use crate::video::pixel::Pixel;
+use crate::video::ssimulacra2::LinearRgb;
+#[cfg(feature = "decode")]
+use crate::video::{decode_and_process_video, VideoOptions};
+use crate::video::{
+    ColorDescription, ComparisonInfo, FrameRef, TransferCharacteristics, VideoMetric,
+    VideoMetricResults,
+};
+use crate::MetricsError;
+use malta::{malta_diff_map, MaltaKernel};
+use std::error::Error;
+
+/// The luminance of peak white, in nits.
+const INTENSITY_TARGET: f32 = 80.0;
+/// The weight of overshoot relative to undershoot in high frequency differences.
+const HF_ASYMMETRY: f64 = 0.8;
+/// The distance which is roughly the threshold of visible differences,
+/// in the internal units of the model.
+const GOOD_QUALITY_THRESHOLD: f64 = 17.83;
+
+/// Calculate the Butteraugli distance between two video clips. Lower is better.
+///
+/// Frames are converted to RGB using the color description reported by each decoder,
+/// which may be overridden through [`VideoOptions`](../struct.VideoOptions.html).
+/// The aggregate max-norm distance is the largest distance of any frame,
+/// and the aggregate 3-norm distance is the 3-norm of the per-frame 3-norm distances.
+/// By default, this will return at the end of the shorter of the two clips,
+/// comparing any frames up to that point.
+#[cfg(feature = "decode")]
+#[inline]
+pub fn calculate_video_butteraugli<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<ButteraugliScore>, Box<dyn Error + Send + Sync>> {
+    Butteraugli::default().process_video(decoder1, decoder2, options)
+}
+
+/// Calculate the Butteraugli distance between two video frames. Lower is better.
+///
+/// The frames are assumed to use the default `ColorDescription`.
+/// Use [`Butteraugli::with_color_descriptions`](struct.Butteraugli.html#method.with_color_descriptions)
+/// for other inputs.
+#[inline]
+pub fn calculate_frame_butteraugli<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<ButteraugliScore, Box<dyn Error + Send + Sync>> {
+    Butteraugli::default().process_frame(&frame1.into(), &frame2.into())
+}
+
+/// Calculate the Butteraugli distance between two video frames,
+/// along with the distance at each pixel. Lower is better.
+///
+/// The frames are assumed to use the default `ColorDescription`.
+#[inline]
+pub fn calculate_frame_butteraugli_with_distance_map<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<(ButteraugliScore, DistanceMap), Box<dyn Error + Send + Sync>> {
+    Butteraugli::default().process_frame_with_distance_map(&frame1.into(), &frame2.into())
+}
+
+/// The Butteraugli distance of a frame or video.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ButteraugliScore {
+    /// The largest distance at any pixel, which measures the most visible artifact.
+    pub max_norm: f64,
+    /// The 3-norm of the distance at each pixel, which measures the overall distortion.
+    pub three_norm: f64,
+}
+
+/// The Butteraugli distance at each pixel of a frame.
+///
+/// The map has the same resolution as the luma plane of the frames.
+#[derive(Clone, Debug)]
+pub struct DistanceMap {
+    /// The width, in pixels, of this map.
+    pub width: usize,
+    /// The height, in pixels, of this map.
+    pub height: usize,
+    /// The distance at each pixel, in row-major order.
+    pub data: Vec<f32>,
+}
+
+/// The Butteraugli metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+///
+/// Frames are converted to RGB according to the matrix coefficients and range
+/// in the color description of each video. Only the BT.1886 transfer function
+/// is supported, because Butteraugli was designed for standard dynamic range images.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Butteraugli {
+    color_descriptions: [ColorDescription; 2],
+}
+
+impl Butteraugli {
+    /// Creates a Butteraugli metric which converts the frames of the first and second
+    /// video to RGB according to the given color descriptions.
+    ///
+    /// When processing a video, the color descriptions reported by the decoders
+    /// are used instead, unless they are overridden by `VideoOptions`.
+    pub fn with_color_descriptions(
+        color_description1: ColorDescription,
+        color_description2: ColorDescription,
+    ) -> Self {
+        Butteraugli {
+            color_descriptions: [color_description1, color_description2],
+        }
+    }
+
+    /// Returns a copy of this metric using the color descriptions of the given videos.
+    #[cfg(feature = "decode")]
+    pub(crate) fn for_videos<D: Decoder>(
+        self,
+        decoder1: &D,
+        decoder2: &D,
+        options: &VideoOptions,
+    ) -> Self {
+        Butteraugli {
+            color_descriptions: [
+                options.color_description(&decoder1.get_video_details()),
+                options.color_description(&decoder2.get_video_details()),
+            ],
+        }
+    }
+
+    /// Calculates the distance between two frames, along with the distance at each pixel.
+    pub fn process_frame_with_distance_map<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<(ButteraugliScore, DistanceMap), Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
+        if frame1.bit_depth < 8 {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Bit depths below 8 are not supported",
+            }
+            .into());
+        }
+        if self
+            .color_descriptions
+            .iter()
+            .any(|desc| desc.transfer_characteristics != TransferCharacteristics::BT1886)
+        {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Butteraugli only supports standard dynamic range video",
+            }
+            .into());
+        }
+        if frame1.planes[0].width() < 8 || frame1.planes[0].height() < 8 {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Frames must be at least 8x8 to calculate Butteraugli",
+            }
+            .into());
+        }
+
+        let reference = LinearRgb::new(frame1, self.color_descriptions[0]);
+        let distorted = LinearRgb::new(frame2, self.color_descriptions[1]);
+        let mut diffmap = diffmap_at_scale(&reference, &distorted);
+
+        // Lower resolutions are compared as well, for artifacts spanning larger areas
+        let reference = reference.downsample();
+        let distorted = distorted.downsample();
+        if reference.width >= 8 && reference.height >= 8 {
+            add_supersampled(&diffmap_at_scale(&reference, &distorted), &mut diffmap);
+        }
+
+        let score = ButteraugliScore {
+            max_norm: diffmap.data.iter().copied().fold(0., f32::max) as f64,
+            three_norm: three_norm(&diffmap.data),
+        };
+        Ok((
+            score,
+            DistanceMap {
+                width: diffmap.width,
+                height: diffmap.height,
+                data: diffmap.data,
+            },
+        ))
+    }
+}
+
+impl VideoMetric for Butteraugli {
+    type FrameResult = ButteraugliScore;
+    type VideoResult = VideoMetricResults<ButteraugliScore>;
+
+    #[cfg(feature = "decode")]
+    fn process_video<D: Decoder>(
+        &self,
+        decoder1: &mut D,
+        decoder2: &mut D,
+        options: &VideoOptions,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let metric = self.for_videos(decoder1, decoder2, options);
+        decode_and_process_video(&metric, decoder1, decoder2, options)
+    }
+
+    fn process_frame<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        Ok(self.process_frame_with_distance_map(frame1, frame2)?.0)
+    }
+
+    fn aggregate_frame_results(
+        &self,
+        metrics: &[Self::FrameResult],
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let mean_cube =
+            metrics.iter().map(|m| m.three_norm.powi(3)).sum::<f64>() / metrics.len() as f64;
+        Ok(VideoMetricResults {
+            aggregate: ButteraugliScore {
+                max_norm: metrics.iter().map(|m| m.max_norm).fold(0., f64::max),
+                three_norm: mean_cube.cbrt(),
+            },
+            frames: metrics.to_vec(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+        })
+    }
+}
+
+/// The mean of the 3-, 6- and 12-norms of the distance map.
+fn three_norm(diffmap: &[f32]) -> f64 {
+    let mut sums = [0f64; 3];
+    for &d in diffmap {
+        let mut v = (d as f64).powi(3);
+        for sum in sums.iter_mut() {
+            *sum += v;
+            v *= v;
+        }
+    }
+    let norms = sums.iter().enumerate().map(|(i, &sum)| {
+        let p = 3. * (1 << i) as f64;
+        (sum / diffmap.len() as f64).powf(1. / p)
+    });
+    norms.sum::<f64>() / 3.
+}
+
+/// One plane of floating point samples.
+#[derive(Clone)]
+struct FloatPlane {
+    data: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+impl FloatPlane {
+    fn new(data: Vec<f32>, width: usize, height: usize) -> Self {
+        FloatPlane {
+            data,
+            width,
+            height,
+        }
+    }
+
+    fn zeros(width: usize, height: usize) -> Self {
+        FloatPlane::new(vec![0.; width * height], width, height)
+    }
+
+    fn map(&self, f: impl Fn(f32) -> f32) -> FloatPlane {
+        let data = self.data.iter().map(|&x| f(x)).collect();
+        FloatPlane::new(data, self.width, self.height)
+    }
+
+    fn zip_map(&self, other: &FloatPlane, f: impl Fn(f32, f32) -> f32) -> FloatPlane {
+        let data = self
+            .data
+            .iter()
+            .zip(other.data.iter())
+            .map(|(&a, &b)| f(a, b))
+            .collect();
+        FloatPlane::new(data, self.width, self.height)
+    }
+
+    /// Applies a Gaussian blur, renormalizing the kernel where it overlaps the edges.
+    fn blur(&self, sigma: f32) -> FloatPlane {
+        // The kernel is truncated at 2.25 standard deviations
+        let radius = ((2.25 * sigma) as usize).max(1) as isize;
+        let scaler = -1. / (2. * sigma * sigma);
+        let kernel = (-radius..=radius)
+            .map(|i| (scaler * (i * i) as f32).exp())
+            .collect::<Vec<_>>();
+        self.convolve_transposed(&kernel)
+            .convolve_transposed(&kernel)
+    }
+
+    /// Filters each row with the kernel, and returns the transposed result.
+    fn convolve_transposed(&self, kernel: &[f32]) -> FloatPlane {
+        let radius = kernel.len() / 2;
+        let (width, height) = (self.width, self.height);
+        let ranges = (0..width)
+            .map(|x| {
+                let start = x.saturating_sub(radius);
+                let end = (x + radius + 1).min(width);
+                let taps = &kernel[start + radius - x..end + radius - x];
+                (start, taps, 1. / taps.iter().sum::<f32>())
+            })
+            .collect::<Vec<_>>();
+
+        let mut dst = FloatPlane::zeros(height, width);
+        for (y, row) in self.data.chunks(width).enumerate() {
+            for (x, &(start, taps, scale)) in ranges.iter().enumerate() {
+                let sum = row[start..]
+                    .iter()
+                    .zip(taps)
+                    .map(|(&sample, &weight)| sample * weight)
+                    .sum::<f32>();
+                dst.data[x * height + y] = sum * scale;
+            }
+        }
+        dst
+    }
+}
+
+/// Calculates the distance map at the resolution of the given images.
+fn diffmap_at_scale(reference: &LinearRgb, distorted: &LinearRgb) -> FloatPlane {
+    let reference = PsychoImage::new(&opsin_dynamics(reference));
+    let distorted = PsychoImage::new(&opsin_dynamics(distorted));
+    reference.diffmap(&distorted)
+}
+
+/// Blends a distance map at half resolution into the one at full resolution.
+fn add_supersampled(src: &FloatPlane, dst: &mut FloatPlane) {
+    const WEIGHT: f32 = 0.5;
+    const MIXING: f32 = 0.3;
+    for y in 0..dst.height {
+        for x in 0..dst.width {
+            let value = &mut dst.data[y * dst.width + x];
+            *value = *value * (1. - MIXING * WEIGHT) + WEIGHT * src.data[y / 2 * src.width + x / 2];
+        }
+    }
+}
+
+/// Converts linear RGB to the XYB responses of the cones, adapted to the
+/// blurred intensity of the surrounding area.
+fn opsin_dynamics(rgb: &LinearRgb) -> [FloatPlane; 3] {
+    // The bias of the absorbance of each cone, below which responses are clamped
+    const BIAS: [f32; 3] = [1.755_748_4, 1.755_748_4, 12.226_455];
+    const MIX: [[f32; 3]; 3] = [
+        [0.299_565_5, 0.633_730_9, 0.077_705_62],
+        [0.221_586_9, 0.693_913_9, 0.098_731_36],
+        [0.02, 0.02, 0.204_801_3],
+    ];
+    let absorbance = |rgb: [f32; 3]| {
+        [0, 1, 2].map(|i| {
+            INTENSITY_TARGET * (MIX[i][0] * rgb[0] + MIX[i][1] * rgb[1] + MIX[i][2] * rgb[2])
+                + BIAS[i]
+        })
+    };
+    // The nonlinear response of the cones to their absorbance
+    let gamma = |v: f32| 19.245_013 * (v + 9.971_064).ln() - 23.160_463;
+
+    let planes = &rgb.planes;
+    let blurred =
+        [0, 1, 2].map(|i| FloatPlane::new(planes[i].clone(), rgb.width, rgb.height).blur(1.2));
+    let mut xyb = [0, 1, 2].map(|_| FloatPlane::zeros(rgb.width, rgb.height));
+    for i in 0..planes[0].len() {
+        let pre = absorbance([blurred[0].data[i], blurred[1].data[i], blurred[2].data[i]]);
+        let cur = absorbance([planes[0][i], planes[1][i], planes[2][i]]);
+        let [c0, c1, c2] = [0, 1, 2].map(|c| {
+            let pre = pre[c].max(1e-4);
+            let sensitivity = (gamma(pre) / pre).max(1e-4);
+            (cur[c] * sensitivity).max(BIAS[c])
+        });
+        xyb[0].data[i] = c0 - c1;
+        xyb[1].data[i] = c0 + c1;
+        xyb[2].data[i] = c2;
+    }
+    xyb
+}
+
+/// The XYB responses separated into frequency bands.
+///
+/// The ultra high and high frequency bands only contain the X and Y channels,
+/// because the eye is not sensitive to high frequency changes in blue.
+struct PsychoImage {
+    uhf: [FloatPlane; 2],
+    hf: [FloatPlane; 2],
+    mf: [FloatPlane; 3],
+    lf: [FloatPlane; 3],
+}
+
+impl PsychoImage {
+    fn new(xyb: &[FloatPlane; 3]) -> Self {
+        const SIGMA_LF: f32 = 7.155_933;
+        const SIGMA_HF: f32 = 3.224_899;
+        const SIGMA_UHF: f32 = 1.564_163_3;
+
+        let lf = [0, 1, 2].map(|c| xyb[c].blur(SIGMA_LF));
+        let mf = [0, 1, 2].map(|c| xyb[c].zip_map(&lf[c], |a, b| a - b));
+        let lf = low_freq_to_vals(lf);
+
+        let blurred = [0, 1, 2].map(|c| mf[c].blur(SIGMA_HF));
+        let hf = [0, 1].map(|c| mf[c].zip_map(&blurred[c], |a, b| a - b));
+        let [mf_x, mf_y, mf_b] = blurred;
+        let mf = [
+            mf_x.map(|v| remove_range(v, 0.29)),
+            mf_y.map(|v| amplify_range(v, 0.1)),
+            mf_b,
+        ];
+        // Red-green changes are less visible next to changes in intensity
+        let [hf_x, hf_y] = hf;
+        let hf_x = hf_x.zip_map(&hf_y, |x, y| {
+            const S: f32 = 0.653_020_56;
+            const SUPPRESS: f32 = 46.;
+            x * (S + (1. - S) * SUPPRESS / (y * y + SUPPRESS))
+        });
+
+        let blurred_x = hf_x.blur(SIGMA_UHF);
+        let uhf_x = hf_x.zip_map(&blurred_x, |a, b| remove_range(a - b, 0.04));
+        let hf_x = blurred_x.map(|v| remove_range(v, 1.5));
+
+        let blurred_y = hf_y.blur(SIGMA_UHF).map(|v| maximum_clamp(v, 28.469_18));
+        let uhf_y = hf_y.zip_map(&blurred_y, |a, b| {
+            maximum_clamp(a - b, 5.191_753) * 2.693_137_6
+        });
+        let hf_y = blurred_y.map(|v| amplify_range(v * 2.155, 0.132));
+
+        PsychoImage {
+            uhf: [uhf_x, uhf_y],
+            hf: [hf_x, hf_y],
+            mf,
+            lf,
+        }
+    }
+
+    /// Calculates the distance map between this image and another.
+    fn diffmap(&self, other: &PsychoImage) -> FloatPlane {
+        // The weight of each band and channel, for undershoot and overshoot,
+        // and the scale of each band, used to compress large differences
+        const UHF_Y: (f64, f64) = (1.100_390_325_55, 71.780_027_516_9);
+        const UHF_X: (f64, f64) = (173.5, 5.0);
+        const HF_Y: (f64, f64) = (18.723_741_438_7, 4_498_534.452_32);
+        const HF_X: (f64, f64) = (6_923.994_761_09, 8_051.158_332_47);
+        const MF_Y: (f64, f64) = (37.081_987_039_9, 130_262_059.556);
+        const MF_X: (f64, f64) = (8_246.753_213_53, 1_009_002.705_82);
+        // The weight of the squared difference of each channel, in the
+        // high, medium and low frequency bands
+        const L2_WEIGHTS: [[f64; 3]; 3] = [
+            [400., 1.508_157_031_18, 0.],
+            [2_150., 10.619_543_323_9, 16.217_604_315_2],
+            [29.235_379_799_4, 0.844_626_970_982, 0.703_646_627_719],
+        ];
+
+        let (width, height) = (self.mf[0].width, self.mf[0].height);
+        let mut ac = [0, 1, 2].map(|_| FloatPlane::zeros(width, height));
+        let hf_kernel = MaltaKernel::high_frequency();
+        let lf_kernel = MaltaKernel::low_frequency();
+        let asymmetry = HF_ASYMMETRY.sqrt();
+        let malta = [
+            (
+                &self.uhf,
+                &other.uhf,
+                [UHF_X, UHF_Y],
+                &hf_kernel,
+                HF_ASYMMETRY,
+            ),
+            (&self.hf, &other.hf, [HF_X, HF_Y], &lf_kernel, asymmetry),
+        ];
+        for (band0, band1, weights, kernel, asymmetry) in malta {
+            for c in 0..2 {
+                let (weight, norm) = weights[c];
+                let (w_0gt1, w_0lt1) = (weight * asymmetry, weight / asymmetry);
+                malta_diff_map(
+                    &band0[c], &band1[c], w_0gt1, w_0lt1, norm, kernel, &mut ac[c],
+                );
+            }
+        }
+        for (c, &(weight, norm)) in [MF_X, MF_Y].iter().enumerate() {
+            malta_diff_map(
+                &self.mf[c],
+                &other.mf[c],
+                weight,
+                weight,
+                norm,
+                &lf_kernel,
+                &mut ac[c],
+            );
+        }
+
+        let mut dc = [0, 1, 2].map(|_| FloatPlane::zeros(width, height));
+        for c in 0..3 {
+            if c < 2 {
+                let weight = L2_WEIGHTS[0][c];
+                l2_diff_asymmetric(
+                    &self.hf[c],
+                    &other.hf[c],
+                    weight * HF_ASYMMETRY,
+                    weight / HF_ASYMMETRY,
+                    &mut ac[c],
+                );
+            }
+            l2_diff(&self.mf[c], &other.mf[c], L2_WEIGHTS[1][c], &mut ac[c]);
+            l2_diff(&self.lf[c], &other.lf[c], L2_WEIGHTS[2][c], &mut dc[c]);
+        }
+
+        let mask = self.mask(other, &mut ac[1]);
+        let mut diffmap = FloatPlane::zeros(width, height);
+        for i in 0..diffmap.data.len() {
+            let ac = (ac[0].data[i] + ac[1].data[i] + ac[2].data[i]) as f64;
+            let dc = (dc[0].data[i] + dc[1].data[i] + dc[2].data[i]) as f64;
+            let mask = mask.data[i] as f64;
+            diffmap.data[i] = (mask_y(mask) * ac + mask_dc_y(mask) * dc).sqrt() as f32;
+        }
+        diffmap
+    }
+
+    /// Calculates how much the high frequency detail of the reference masks
+    /// differences, and adds the difference in masking to `ac`.
+    fn mask(&self, other: &PsychoImage, ac: &mut FloatPlane) -> FloatPlane {
+        const MUL: f32 = 6.194_240_8;
+        const BIAS: f32 = 12.610_506;
+        const RADIUS: f32 = 2.7;
+        const MASK_TO_ERROR_MUL: f32 = 10.;
+
+        let precompute = |image: &PsychoImage| {
+            let bias_sqrt = BIAS.sqrt();
+            image
+                .masking_activity()
+                .map(|v| (MUL * v.abs() + BIAS).sqrt() - bias_sqrt)
+        };
+        let blurred0 = precompute(self).blur(RADIUS);
+        let blurred1 = precompute(other).blur(RADIUS);
+        for ((ac, &b0), &b1) in ac.data.iter_mut().zip(&blurred0.data).zip(&blurred1.data) {
+            *ac += MASK_TO_ERROR_MUL * (b0 - b1) * (b0 - b1);
+        }
+        fuzzy_erosion(&blurred0)
+    }
+
+    /// Combines the high frequency X and Y channels, which mask differences.
+    fn masking_activity(&self) -> FloatPlane {
+        const MULS: [f32; 3] = [2.5, 0.4, 0.4];
+        let mut activity = FloatPlane::zeros(self.hf[0].width, self.hf[0].height);
+        for (i, value) in activity.data.iter_mut().enumerate() {
+            let x = (self.uhf[0].data[i] + self.hf[0].data[i]) * MULS[0];
+            let y = self.uhf[1].data[i] * MULS[1] + self.hf[1].data[i] * MULS[2];
+            *value = (x * x + y * y).sqrt();
+        }
+        activity
+    }
+}
+
+/// Scales the low frequency XYB responses so that differences in each
+/// channel are similarly visible.
+fn low_freq_to_vals(lf: [FloatPlane; 3]) -> [FloatPlane; 3] {
+    const X_MUL: f32 = 33.832_837;
+    const Y_MUL: f32 = 14.458_268;
+    const B_MUL: f32 = 49.879_845;
+    const Y_TO_B_MUL: f32 = -0.362_267_05;
+    let [x, y, b] = lf;
+    let b = b.zip_map(&y, |b, y| (b + Y_TO_B_MUL * y) * B_MUL);
+    [x.map(|v| v * X_MUL), y.map(|v| v * Y_MUL), b]
+}
+
+/// Shrinks values towards zero by `w`, clamping those within `w` of zero.
+fn remove_range(v: f32, w: f32) -> f32 {
+    if v > w {
+        v - w
+    } else if v < -w {
+        v + w
+    } else {
+        0.
+    }
+}
+
+/// Pushes values away from zero by `w`, doubling those within `w` of zero.
+fn amplify_range(v: f32, w: f32) -> f32 {
+    if v > w {
+        v + w
+    } else if v < -w {
+        v - w
+    } else {
+        2. * v
+    }
+}
+
+/// Compresses values whose magnitude is above `max`.
+fn maximum_clamp(v: f32, max: f32) -> f32 {
+    const MUL: f32 = 0.688_059_6;
+    if v >= max {
+        (v - max) * MUL + max
+    } else if v < -max {
+        (v + max) * MUL - max
+    } else {
+        v
+    }
+}
+
+/// Adds the weighted squared difference of two planes to `dst`.
+fn l2_diff(i0: &FloatPlane, i1: &FloatPlane, weight: f64, dst: &mut FloatPlane) {
+    let weight = weight as f32;
+    for ((dst, &v0), &v1) in dst.data.iter_mut().zip(&i0.data).zip(&i1.data) {
+        *dst += weight * (v0 - v1) * (v0 - v1);
+    }
+}
+
+/// Adds the weighted squared difference of two planes to `dst`, with an additional
+/// penalty when the distorted plane is much smaller or larger than the reference.
+fn l2_diff_asymmetric(
+    i0: &FloatPlane,
+    i1: &FloatPlane,
+    w_0gt1: f64,
+    w_0lt1: f64,
+    dst: &mut FloatPlane,
+) {
+    let w_0gt1 = (w_0gt1 * 0.8) as f32;
+    let w_0lt1 = (w_0lt1 * 0.8) as f32;
+    for ((dst, &v0), &v1) in dst.data.iter_mut().zip(&i0.data).zip(&i1.data) {
+        *dst += w_0gt1 * (v0 - v1) * (v0 - v1);
+
+        let too_small = 0.4 * v0.abs();
+        let too_big = v0.abs();
+        let v = if v0 < 0. {
+            if v1 > -too_small {
+                v1 + too_small
+            } else if v1 < -too_big {
+                -v1 - too_big
+            } else {
+                0.
+            }
+        } else if v1 < too_small {
+            too_small - v1
+        } else if v1 > too_big {
+            v1 - too_big
+        } else {
+            0.
+        };
+        *dst += w_0lt1 * v * v;
+    }
+}
+
+/// Lowers the masking in areas near smooth regions, by mixing in
+/// the smallest values within a few pixels.
+fn fuzzy_erosion(src: &FloatPlane) -> FloatPlane {
+    const STEP: isize = 3;
+    let (width, height) = (src.width as isize, src.height as isize);
+    let mut dst = FloatPlane::zeros(src.width, src.height);
+    for y in 0..height {
+        for x in 0..width {
+            let center = src.data[(y * width + x) as usize];
+            let mut min = [center, 2. * center, 2. * center];
+            for (dy, dx) in [
+                (-STEP, -STEP),
+                (0, -STEP),
+                (STEP, -STEP),
+                (-STEP, STEP),
+                (0, STEP),
+                (STEP, STEP),
+                (-STEP, 0),
+                (STEP, 0),
+            ] {
+                let (sy, sx) = (y + dy, x + dx);
+                if sy < 0 || sy >= height || sx < 0 || sx >= width {
+                    continue;
+                }
+                let v = src.data[(sy * width + sx) as usize];
+                if v < min[2] {
+                    if v < min[0] {
+                        min = [v, min[0], min[1]];
+                    } else if v < min[1] {
+                        min = [min[0], v, min[1]];
+                    } else {
+                        min[2] = v;
+                    }
+                }
+            }
+            dst.data[(y * width + x) as usize] = 0.45 * min[0] + 0.3 * min[1] + 0.25 * min[2];
+        }
+    }
+    dst
+}
+
+/// The weight of high and medium frequency differences for the given masking activity.
+fn mask_y(delta: f64) -> f64 {
+    const OFFSET: f64 = 0.829_591_754_942;
+    const SCALER: f64 = 0.451_936_922_203;
+    const MUL: f64 = 2.548_594_479_3;
+    let c = MUL / (SCALER * delta + OFFSET);
+    let value = (1. + c) / GOOD_QUALITY_THRESHOLD;
+    value * value
+}
+
+/// The weight of low frequency differences for the given masking activity.
+fn mask_dc_y(delta: f64) -> f64 {
+    const OFFSET: f64 = 0.200_255_785_22;
+    const SCALER: f64 = 3.874_494_188_04;
+    const MUL: f64 = 0.505_054_525_019;
+    let c = MUL / (SCALER * delta + OFFSET);
+    let value = (1. + c) / GOOD_QUALITY_THRESHOLD;
+    value * value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use std::fs::File;
+
+    fn run(name: &str) -> VideoMetricResults<ButteraugliScore> {
+        let mut file1 = File::open(format!("./testfiles/{}_input.y4m", name)).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open(format!("./testfiles/{}_output.y4m", name)).unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        calculate_video_butteraugli(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn butteraugli_identical_frames() {
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        let (score, map) = calculate_frame_butteraugli_with_distance_map(&frame, &frame).unwrap();
+        assert_metric_eq(0.0, score.max_norm);
+        assert_metric_eq(0.0, score.three_norm);
+        assert_eq!(frame.planes[0].width, map.width);
+        assert_eq!(frame.planes[0].height, map.height);
+        assert!(map.data.iter().all(|&d| d == 0.));
+    }
+
+    #[test]
+    fn butteraugli_blur_is_normalized() {
+        let plane = FloatPlane::new(vec![1.; 24 * 16], 24, 16);
+        let blurred = plane.blur(7.155_933);
+        assert!(blurred.data.iter().all(|&v| (v - 1.).abs() < 1e-5));
+    }
+
+    #[test]
+    fn butteraugli_yuv420p8() {
+        let result = run("yuv420p8");
+        assert_eq!(3, result.frames.len());
+        assert_metric_eq(8.9863, result.aggregate.max_norm);
+        assert_metric_eq(2.1416, result.aggregate.three_norm);
+    }
+
+    #[test]
+    fn butteraugli_yuv420p10() {
+        let result = run("yuv420p10");
+        assert_metric_eq(8.9707, result.aggregate.max_norm);
+        assert_metric_eq(2.1536, result.aggregate.three_norm);
+    }
+
+    #[test]
+    fn butteraugli_yuv444p8() {
+        let result = run("yuv444p8");
+        assert_metric_eq(9.0397, result.aggregate.max_norm);
+        assert_metric_eq(2.2120, result.aggregate.three_norm);
+    }
+
+    #[test]
+    fn butteraugli_distance_map() {
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let frame1 = dec1.read_video_frame::<u8>().unwrap();
+        let frame2 = dec2.read_video_frame::<u8>().unwrap();
+        let (score, map) = calculate_frame_butteraugli_with_distance_map(&frame1, &frame2).unwrap();
+        let max = map.data.iter().copied().fold(0., f32::max);
+        assert_metric_eq(score.max_norm, max as f64);
+        assert_metric_eq(score.three_norm, three_norm(&map.data));
+    }
+
+    #[test]
+    fn butteraugli_rejects_hdr() {
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        let pq = ColorDescription {
+            transfer_characteristics: TransferCharacteristics::PQ,
+            ..Default::default()
+        };
+        let metric = Butteraugli::with_color_descriptions(pq, pq);
+        assert!(metric
+            .process_frame(&FrameRef::from(&frame), &FrameRef::from(&frame))
+            .is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! Resamples chroma planes, so that videos with different chroma sampling
+//! can be compared.
+//!
+//! This allows e.g. a 4:4:4 master to be compared with a 4:2:0 delivery encode,
+//! so that metrics include the chroma loss caused by subsampling.
+
+#[cfg(feature = "decode")]
+use crate::video::decode::{DecodeError, Decoder, VideoDetails};
+use crate::video::pixel::Pixel;
+use crate::video::scale::{resample_plane, SampleMapping, ScaleFilter};
+use crate::video::{ChromaSamplePosition, ChromaSampling, FrameInfo, FrameRef, PlaneData};
+
+/// How to bring two videos with different chroma sampling to a common chroma sampling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromaResampling {
+    /// Upsample the chroma planes of the video with less chroma resolution.
+    ///
+    /// A 4:0:0 video is given neutral chroma planes.
+    Upsample,
+    /// Downsample the chroma planes of the video with more chroma resolution.
+    Downsample,
+}
+
+impl ChromaResampling {
+    /// The common chroma sampling which videos with the given chroma sampling
+    /// are converted to.
+    pub fn target_sampling(
+        self,
+        chroma_sampling1: ChromaSampling,
+        chroma_sampling2: ChromaSampling,
+    ) -> ChromaSampling {
+        let (decimation1, decimation2) = match (
+            chroma_sampling1.get_decimation(),
+            chroma_sampling2.get_decimation(),
+        ) {
+            (Some(decimation1), Some(decimation2)) => (decimation1, decimation2),
+            // Only one video has chroma planes
+            (Some(_), None) | (None, Some(_)) => {
+                return match self {
+                    ChromaResampling::Upsample if chroma_sampling1 == ChromaSampling::Cs400 => {
+                        chroma_sampling2
+                    }
+                    ChromaResampling::Upsample => chroma_sampling1,
+                    ChromaResampling::Downsample => ChromaSampling::Cs400,
+                };
+            }
+            (None, None) => return ChromaSampling::Cs400,
+        };
+        let decimation = match self {
+            ChromaResampling::Upsample => (
+                decimation1.0.min(decimation2.0),
+                decimation1.1.min(decimation2.1),
+            ),
+            ChromaResampling::Downsample => (
+                decimation1.0.max(decimation2.0),
+                decimation1.1.max(decimation2.1),
+            ),
+        };
+        match decimation {
+            (0, 0) => ChromaSampling::Cs444,
+            (1, 0) => ChromaSampling::Cs422,
+            _ => ChromaSampling::Cs420,
+        }
+    }
+}
+
+/// Whether chroma samples are co-located with the top-left luma sample,
+/// rather than centered between the luma samples they cover.
+///
+/// Vertical siting is horizontally co-located in the input, but the y4m decoder
+/// realigns it to be centered before the frame is returned.
+fn is_cosited(position: ChromaSamplePosition) -> bool {
+    position == ChromaSamplePosition::Colocated
+}
+
+/// Maps the chroma samples of the output to positions in the source chroma plane,
+/// along one dimension with the given decimation.
+fn chroma_mapping(
+    src_decimation: usize,
+    dst_decimation: usize,
+    src_cosited: bool,
+    dst_cosited: bool,
+) -> SampleMapping {
+    let src_step = (1 << src_decimation) as f64;
+    let dst_step = (1 << dst_decimation) as f64;
+    // The position of the first output sample, in luma samples
+    let position = if dst_cosited {
+        0.0
+    } else {
+        0.5 * dst_step - 0.5
+    };
+    // The same position in source chroma samples, measured from the edge of the plane
+    let src_position = if src_cosited {
+        position / src_step + 0.5
+    } else {
+        (position + 0.5) / src_step
+    };
+    let ratio = dst_step / src_step;
+    SampleMapping {
+        ratio,
+        offset: src_position - 0.5 * ratio,
+    }
+}
+
+/// Resamples the chroma planes of a frame to the given chroma sampling.
+///
+/// `position` is the siting of the frame's chroma samples,
+/// and `target_position` is the siting of the resampled chroma samples.
+/// The luma plane is unchanged.
+pub fn resample_chroma<'a, T: Pixel>(
+    frame: impl Into<FrameRef<'a, T>>,
+    position: ChromaSamplePosition,
+    chroma_sampling: ChromaSampling,
+    target_position: ChromaSamplePosition,
+) -> FrameInfo<T> {
+    let frame = frame.into();
+    let luma = &frame.planes[0];
+    let bit_depth = frame.bit_depth;
+    let (width, height) = chroma_sampling.get_chroma_dimensions(luma.width(), luma.height());
+    let chroma_plane = |plane| match (
+        frame.chroma_sampling.get_decimation(),
+        chroma_sampling.get_decimation(),
+    ) {
+        (Some((src_x, src_y)), Some((dst_x, dst_y))) => {
+            let src_cosited = is_cosited(position);
+            let dst_cosited = is_cosited(target_position);
+            resample_plane(
+                &frame.planes[plane],
+                width,
+                height,
+                ScaleFilter::default(),
+                bit_depth,
+                chroma_mapping(src_x, dst_x, src_cosited, dst_cosited),
+                chroma_mapping(src_y, dst_y, src_cosited, dst_cosited),
+            )
+        }
+        // Without chroma planes in the source, use neutral chroma
+        (None, _) => PlaneData {
+            width,
+            height,
+            data: vec![T::cast_from(1u32 << (bit_depth - 1)); width * height],
+        },
+        (_, None) => PlaneData {
+            width: 0,
+            height: 0,
+            data: Vec::new(),
+        },
+    };
+    FrameInfo {
+        planes: [
+            PlaneData {
+                width: luma.width(),
+                height: luma.height(),
+                data: luma.rows().flatten().copied().collect(),
+            },
+            chroma_plane(1),
+            chroma_plane(2),
+        ],
+        bit_depth,
+        chroma_sampling,
+    }
+}
+
+/// A decoder which resamples the chroma planes of each frame after decoding it.
+///
+/// This is used to implement `VideoOptions::chroma_resampling`.
+#[cfg(feature = "decode")]
+pub struct ChromaDecoder<'a, D: Decoder> {
+    decoder: &'a mut D,
+    /// The chroma sampling and siting to convert to, or `None` to leave frames unchanged.
+    target: Option<(ChromaSampling, ChromaSamplePosition)>,
+}
+
+#[cfg(feature = "decode")]
+impl<'a, D: Decoder> ChromaDecoder<'a, D> {
+    /// Wraps a decoder, resampling the chroma planes of its frames to the given
+    /// chroma sampling and siting if `target` is set.
+    pub fn new(decoder: &'a mut D, target: Option<(ChromaSampling, ChromaSamplePosition)>) -> Self {
+        ChromaDecoder { decoder, target }
+    }
+}
+
+#[cfg(feature = "decode")]
+impl<D: Decoder> Decoder for ChromaDecoder<'_, D> {
+    fn read_video_frame<T: Pixel>(&mut self) -> Result<FrameInfo<T>, DecodeError> {
+        let frame = self.decoder.read_video_frame()?;
+        match self.target {
+            Some((chroma_sampling, target_position))
+                if chroma_sampling != frame.chroma_sampling =>
+            {
+                let position = self.decoder.get_video_details().chroma_sample_position;
+                Ok(resample_chroma(
+                    &frame,
+                    position,
+                    chroma_sampling,
+                    target_position,
+                ))
+            }
+            _ => Ok(frame),
+        }
+    }
+
+    fn get_video_details(&self) -> VideoDetails {
+        let mut details = self.decoder.get_video_details();
+        if let Some((chroma_sampling, position)) = self.target {
+            if chroma_sampling != details.chroma_sampling {
+                details.chroma_sampling = chroma_sampling;
+                details.chroma_sample_position = position;
+            }
+        }
+        details
+    }
+}
+
+/// Wraps two decoders so that their frames are resampled to a common chroma sampling
+/// according to `resampling`.
+/// If `resampling` is `None`, frames are passed through unchanged.
+///
+/// Resampled chroma planes use the siting of the video which already has
+/// the common chroma sampling.
+#[cfg(feature = "decode")]
+pub fn resample_chroma_decoders<'a, D: Decoder>(
+    decoder1: &'a mut D,
+    decoder2: &'a mut D,
+    resampling: Option<ChromaResampling>,
+) -> (ChromaDecoder<'a, D>, ChromaDecoder<'a, D>) {
+    let target = resampling.map(|resampling| {
+        let details1 = decoder1.get_video_details();
+        let details2 = decoder2.get_video_details();
+        let chroma_sampling =
+            resampling.target_sampling(details1.chroma_sampling, details2.chroma_sampling);
+        let position = if chroma_sampling == details1.chroma_sampling {
+            details1.chroma_sample_position
+        } else {
+            details2.chroma_sample_position
+        };
+        (chroma_sampling, position)
+    });
+    (
+        ChromaDecoder::new(decoder1, target),
+        ChromaDecoder::new(decoder2, target),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::ciede::calculate_video_ciede;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::psnr::calculate_video_psnr;
+    use crate::video::VideoOptions;
+    use std::io::Cursor;
+
+    #[test]
+    fn target_sampling() {
+        use ChromaSampling::*;
+        let upsample = |a, b| ChromaResampling::Upsample.target_sampling(a, b);
+        let downsample = |a, b| ChromaResampling::Downsample.target_sampling(a, b);
+        assert_eq!(Cs444, upsample(Cs420, Cs444));
+        assert_eq!(Cs422, upsample(Cs422, Cs420));
+        assert_eq!(Cs420, upsample(Cs400, Cs420));
+        assert_eq!(Cs420, downsample(Cs444, Cs420));
+        assert_eq!(Cs420, downsample(Cs422, Cs420));
+        assert_eq!(Cs400, downsample(Cs444, Cs400));
+    }
+
+    #[test]
+    fn resample_flat_chroma() {
+        let plane = |width, height| PlaneData {
+            width,
+            height,
+            data: vec![100u8; width * height],
+        };
+        let frame = FrameInfo {
+            planes: [plane(9, 7), plane(5, 4), plane(5, 4)],
+            bit_depth: 8,
+            chroma_sampling: ChromaSampling::Cs420,
+        };
+        for &position in &[
+            ChromaSamplePosition::Colocated,
+            ChromaSamplePosition::Bilateral,
+        ] {
+            let upsampled = resample_chroma(&frame, position, ChromaSampling::Cs444, position);
+            assert_eq!(
+                (9, 7),
+                (upsampled.planes[1].width, upsampled.planes[1].height)
+            );
+            assert!(upsampled.planes[2].data.iter().all(|&pixel| pixel == 100));
+            let restored = resample_chroma(&upsampled, position, ChromaSampling::Cs420, position);
+            assert_eq!(frame.planes[1].data, restored.planes[1].data);
+        }
+    }
+
+    #[test]
+    fn resample_yuv444p8_to_yuv420p8() {
+        // Create a 4:2:0 copy of the 4:4:4 input
+        let input = std::fs::read("./testfiles/yuv444p8_input.y4m").unwrap();
+        let mut input1 = Cursor::new(&input);
+        let mut dec = Y4mDecoder::new(&mut input1).unwrap();
+        let position = dec.get_video_details().chroma_sample_position;
+        let mut converted = b"YUV4MPEG2 W640 H360 F25:1 Ip A1:1 C420jpeg\n".to_vec();
+        while let Ok(frame) = dec.read_video_frame::<u8>() {
+            let frame = resample_chroma(
+                &frame,
+                position,
+                ChromaSampling::Cs420,
+                ChromaSamplePosition::Bilateral,
+            );
+            converted.extend_from_slice(b"FRAME\n");
+            for plane in &frame.planes {
+                converted.extend_from_slice(&plane.data);
+            }
+        }
+
+        let options = |resampling| VideoOptions {
+            chroma_resampling: resampling,
+            ..Default::default()
+        };
+        let run_psnr = |resampling| {
+            let mut input1 = Cursor::new(&input);
+            let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+            let mut input2 = Cursor::new(&converted);
+            let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+            calculate_video_psnr(&mut dec1, &mut dec2, &options(resampling))
+        };
+        assert!(run_psnr(None).is_err());
+
+        // Downsampling the master reproduces the converted frames exactly
+        let result = run_psnr(Some(ChromaResampling::Downsample)).unwrap();
+        assert_eq!(100.0, result.aggregate.avg);
+
+        // Upsampling the encode measures the chroma loss, but leaves luma intact
+        let result = run_psnr(Some(ChromaResampling::Upsample)).unwrap();
+        assert_eq!(100.0, result.aggregate.y);
+        assert!(result.aggregate.u < 100.0 && result.aggregate.u > 30.0);
+
+        let mut input1 = Cursor::new(&input);
+        let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+        let mut input2 = Cursor::new(&converted);
+        let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+        let result = calculate_video_ciede(
+            &mut dec1,
+            &mut dec2,
+            &options(Some(ChromaResampling::Upsample)),
+        )
+        .unwrap();
+        assert!(result.aggregate > 30.0);
+    }
+}
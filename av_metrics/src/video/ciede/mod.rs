@@ -5,10 +5,19 @@
 //! CIEDE2000 implementation adapted from
 //! [Kyle Siefring's](https://github.com/KyleSiefring/dump_ciede2000).
 
+use crate::cpu_features::CpuFeatureLevel;
 #[cfg(feature = "decode")]
 use crate::video::decode::Decoder;
+#[cfg(feature = "decode")]
+use crate::video::decode_and_process_video;
 use crate::video::pixel::{CastFromPrimitive, Pixel};
-use crate::video::{FrameInfo, VideoMetric};
+#[cfg(feature = "decode")]
+use crate::video::VideoOptions;
+use crate::video::{
+    ChromaSampling, ColorDescription, ColorRange, ComparisonInfo, FrameRef, VideoMetric,
+    VideoMetricResults,
+};
+use crate::MetricsError;
 use std::f64;
 
 mod rgbtolab;
@@ -19,19 +28,17 @@ use delta_e::*;
 
 /// Calculate the CIEDE2000 metric between two video clips. Higher is better.
 ///
-/// This will return at the end of the shorter of the two clips,
+/// By default, this will return at the end of the shorter of the two clips,
 /// comparing any frames up to that point.
-///
-/// Optionally, `frame_limit` can be set to only compare the first
-/// `frame_limit` frames in each video.
+/// See [`VideoOptions`](../struct.VideoOptions.html) for other options.
 #[cfg(feature = "decode")]
 #[inline]
 pub fn calculate_video_ciede<D: Decoder>(
     decoder1: &mut D,
     decoder2: &mut D,
-    frame_limit: Option<usize>,
-) -> Result<f64, Box<dyn Error>> {
-    Ciede2000::default().process_video(decoder1, decoder2, frame_limit)
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<f64>, Box<dyn Error + Send + Sync>> {
+    Ciede2000::default().process_video(decoder1, decoder2, options)
 }
 
 /// Calculate the CIEDE2000 metric between two video clips. Higher is better.
@@ -43,18 +50,22 @@ pub fn calculate_video_ciede<D: Decoder>(
 pub fn calculate_video_ciede_nosimd<D: Decoder>(
     decoder1: &mut D,
     decoder2: &mut D,
-    frame_limit: Option<usize>,
-) -> Result<f64, Box<dyn Error>> {
-    (Ciede2000 { use_simd: false }).process_video(decoder1, decoder2, frame_limit)
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<f64>, Box<dyn Error + Send + Sync>> {
+    (Ciede2000 {
+        cpu: CpuFeatureLevel::RUST,
+        ..Default::default()
+    })
+    .process_video(decoder1, decoder2, options)
 }
 
 /// Calculate the CIEDE2000 metric between two video frames. Higher is better.
 #[inline]
-pub fn calculate_frame_ciede<T: Pixel>(
-    frame1: &FrameInfo<T>,
-    frame2: &FrameInfo<T>,
-) -> Result<f64, Box<dyn Error>> {
-    Ciede2000::default().process_frame(frame1, frame2)
+pub fn calculate_frame_ciede<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    Ciede2000::default().process_frame(&frame1.into(), &frame2.into())
 }
 
 /// Calculate the CIEDE2000 metric between two video frames. Higher is better.
@@ -63,56 +74,121 @@ pub fn calculate_frame_ciede<T: Pixel>(
 /// by tests and benchmarks.
 #[cfg(any(test, feature = "bench"))]
 #[inline]
-pub fn calculate_frame_ciede_nosimd<T: Pixel>(
-    frame1: &FrameInfo<T>,
-    frame2: &FrameInfo<T>,
-) -> Result<f64, Box<dyn Error>> {
-    (Ciede2000 { use_simd: false }).process_frame(frame1, frame2)
+pub fn calculate_frame_ciede_nosimd<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    (Ciede2000 {
+        cpu: CpuFeatureLevel::RUST,
+        ..Default::default()
+    })
+    .process_frame(&frame1.into(), &frame2.into())
 }
 
-struct Ciede2000 {
-    use_simd: bool,
+/// The CIEDE2000 metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+///
+/// Frames are converted to RGB according to the color description of each video,
+/// which defaults to BT.709 limited range.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ciede2000 {
+    cpu: CpuFeatureLevel,
+    color_descriptions: [ColorDescription; 2],
 }
 
-impl Default for Ciede2000 {
-    fn default() -> Self {
-        Ciede2000 { use_simd: true }
+impl Ciede2000 {
+    /// Creates a CIEDE2000 metric which converts the frames of the first and second
+    /// video to RGB according to the given color descriptions.
+    ///
+    /// When processing a video, the color descriptions reported by the decoders
+    /// are used instead, unless they are overridden by `VideoOptions`.
+    pub fn with_color_descriptions(
+        color_description1: ColorDescription,
+        color_description2: ColorDescription,
+    ) -> Self {
+        Ciede2000 {
+            color_descriptions: [color_description1, color_description2],
+            ..Default::default()
+        }
+    }
+
+    /// Returns a copy of this metric using the color descriptions of the given videos.
+    #[cfg(feature = "decode")]
+    pub(crate) fn for_videos<D: Decoder>(
+        self,
+        decoder1: &D,
+        decoder2: &D,
+        options: &VideoOptions,
+    ) -> Self {
+        Ciede2000 {
+            color_descriptions: [
+                options.color_description(&decoder1.get_video_details()),
+                options.color_description(&decoder2.get_video_details()),
+            ],
+            ..self
+        }
     }
 }
 
 impl VideoMetric for Ciede2000 {
     type FrameResult = f64;
-    type VideoResult = f64;
+    type VideoResult = VideoMetricResults<f64>;
+
+    #[cfg(feature = "decode")]
+    fn process_video<D: Decoder>(
+        &self,
+        decoder1: &mut D,
+        decoder2: &mut D,
+        options: &VideoOptions,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let metric = self.for_videos(decoder1, decoder2, options);
+        decode_and_process_video(&metric, decoder1, decoder2, options)
+    }
 
     fn process_frame<T: Pixel>(
-        &mut self,
-        frame1: &FrameInfo<T>,
-        frame2: &FrameInfo<T>,
-    ) -> Result<Self::FrameResult, Box<dyn Error>> {
-        frame1.can_compare(&frame2)?;
-
-        let dec = frame1.chroma_sampling.get_decimation().unwrap_or((1, 1));
-        let y_width = frame1.planes[0].width;
-        let y_height = frame1.planes[0].height;
-        let c_width = frame1.planes[1].width;
-        let delta_e_row_fn = get_delta_e_row_fn(frame1.bit_depth, dec.0, self.use_simd);
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
+        if frame1.bit_depth < 8 {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Bit depths below 8 are not supported",
+            }
+            .into());
+        }
+
+        let y_width = frame1.planes[0].width();
+        let y_height = frame1.planes[0].height();
+        // 4:0:0 video is compared as if it had neutral chroma at full resolution,
+        // which leaves only the lightness difference.
+        let dec = frame1.chroma_sampling.get_decimation().unwrap_or((0, 0));
+        let neutral_row = if frame1.chroma_sampling == ChromaSampling::Cs400 {
+            Some(vec![T::cast_from(1u32 << (frame1.bit_depth - 1)); y_width])
+        } else {
+            None
+        };
+        let yuv_to_rgb1 = YuvToRgb::new(self.color_descriptions[0], frame1.bit_depth);
+        let yuv_to_rgb2 = YuvToRgb::new(self.color_descriptions[1], frame1.bit_depth);
+        let delta_e_row_fn = get_delta_e_row_fn(frame1.bit_depth, dec.0, self.cpu);
         let mut delta_e_vec: Vec<f32> = vec![0.0; y_width * y_height];
         for i in 0..y_height {
             let y_start = i * y_width;
             let y_end = y_start + y_width;
-            let c_start = (i >> dec.1) * c_width;
-            let c_end = c_start + c_width;
+            let c_row = i >> dec.1;
             unsafe {
                 delta_e_row_fn(
                     FrameRow {
-                        y: &frame1.planes[0].data[y_start..y_end],
-                        u: &frame1.planes[1].data[c_start..c_end],
-                        v: &frame1.planes[2].data[c_start..c_end],
+                        y: frame1.planes[0].row(i),
+                        u: chroma_row(frame1, 1, c_row, neutral_row.as_deref()),
+                        v: chroma_row(frame1, 2, c_row, neutral_row.as_deref()),
+                        yuv_to_rgb: &yuv_to_rgb1,
                     },
                     FrameRow {
-                        y: &frame2.planes[0].data[y_start..y_end],
-                        u: &frame2.planes[1].data[c_start..c_end],
-                        v: &frame2.planes[2].data[c_start..c_end],
+                        y: frame2.planes[0].row(i),
+                        u: chroma_row(frame2, 1, c_row, neutral_row.as_deref()),
+                        v: chroma_row(frame2, 2, c_row, neutral_row.as_deref()),
+                        yuv_to_rgb: &yuv_to_rgb2,
                     },
                     &mut delta_e_vec[y_start..y_end],
                 );
@@ -126,12 +202,17 @@ impl VideoMetric for Ciede2000 {
         Ok(score.min(100.))
     }
 
-    #[cfg(feature = "decode")]
     fn aggregate_frame_results(
         &self,
         metrics: &[Self::FrameResult],
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        Ok(metrics.iter().copied().sum::<f64>() / metrics.len() as f64)
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        Ok(VideoMetricResults {
+            aggregate: metrics.iter().copied().sum::<f64>() / metrics.len() as f64,
+            frames: metrics.to_vec(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+        })
     }
 }
 
@@ -149,29 +230,127 @@ pub(crate) struct FrameRow<'a, T: Pixel> {
     y: &'a [T],
     u: &'a [T],
     v: &'a [T],
+    yuv_to_rgb: &'a YuvToRgb,
+}
+
+/// Coefficients for converting the samples of one video to RGB.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct YuvToRgb {
+    y_offset: f32,
+    y_scale: f32,
+    uv_offset: f32,
+    uv_scale: f32,
+    r_v: f32,
+    g_u: f32,
+    g_v: f32,
+    b_u: f32,
+}
+
+impl YuvToRgb {
+    fn new(color_description: ColorDescription, bit_depth: usize) -> Self {
+        // The maximum values of the analog U and V components
+        const U_MAX: f32 = 0.436;
+        const V_MAX: f32 = 0.615;
+
+        let (kr, kb) = color_description.matrix_coefficients.luma_weights();
+        let kg = 1. - kr - kb;
+        let (y_offset, y_scale, uv_scale) = match color_description.range {
+            ColorRange::Limited => {
+                let scale = (1 << (bit_depth - 8)) as f32;
+                (16. * scale, 1. / (219. * scale), 1. / (224. * scale))
+            }
+            ColorRange::Full => {
+                let max = ((1 << bit_depth) - 1) as f32;
+                (0., 1. / max, 1. / max)
+            }
+        };
+        YuvToRgb {
+            y_offset,
+            y_scale,
+            uv_offset: (1 << (bit_depth - 1)) as f32,
+            uv_scale,
+            r_v: (1. - kr) / V_MAX,
+            g_u: -kb * (1. - kb) / (U_MAX * kg),
+            g_v: -kr * (1. - kr) / (V_MAX * kg),
+            b_u: (1. - kb) / U_MAX,
+        }
+    }
+
+    fn convert(&self, yuv: (u16, u16, u16)) -> [f32; 3] {
+        let y = (yuv.0 as f32 - self.y_offset) * self.y_scale;
+        let u = (yuv.1 as f32 - self.uv_offset) * self.uv_scale;
+        let v = (yuv.2 as f32 - self.uv_offset) * self.uv_scale;
+
+        let r = y + self.r_v * v;
+        let g = y + self.g_u * u + self.g_v * v;
+        let b = y + self.b_u * u;
+
+        [r, g, b]
+    }
+}
+
+/// Returns a row of a chroma plane, or `neutral_row` in place of missing chroma planes.
+fn chroma_row<'a, T: Pixel>(
+    frame: &'a FrameRef<T>,
+    plane: usize,
+    row: usize,
+    neutral_row: Option<&'a [T]>,
+) -> &'a [T] {
+    neutral_row.unwrap_or_else(|| frame.planes[plane].row(row))
 }
 
 type DeltaERowFn<T> = unsafe fn(FrameRow<T>, FrameRow<T>, &mut [f32]);
 
-fn get_delta_e_row_fn<T: Pixel>(bit_depth: usize, xdec: usize, simd: bool) -> DeltaERowFn<T> {
+fn get_delta_e_row_fn<T: Pixel>(
+    bit_depth: usize,
+    xdec: usize,
+    cpu: CpuFeatureLevel,
+) -> DeltaERowFn<T> {
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     {
-        if is_x86_feature_detected!("avx2") && xdec == 1 && simd {
-            return match bit_depth {
-                8 => BD8::delta_e_row_avx2,
-                10 => BD10::delta_e_row_avx2,
-                12 => BD12::delta_e_row_avx2,
+        if cpu >= CpuFeatureLevel::AVX2 {
+            return match (bit_depth, xdec) {
+                (8, 1) => BD8::delta_e_row_avx2,
+                (9, 1) => BD9::delta_e_row_avx2,
+                (10, 1) => BD10::delta_e_row_avx2,
+                (11, 1) => BD11::delta_e_row_avx2,
+                (12, 1) => BD12::delta_e_row_avx2,
+                (13, 1) => BD13::delta_e_row_avx2,
+                (14, 1) => BD14::delta_e_row_avx2,
+                (15, 1) => BD15::delta_e_row_avx2,
+                (16, 1) => BD16::delta_e_row_avx2,
+                (8, 0) => BD8_444::delta_e_row_avx2,
+                (9, 0) => BD9_444::delta_e_row_avx2,
+                (10, 0) => BD10_444::delta_e_row_avx2,
+                (11, 0) => BD11_444::delta_e_row_avx2,
+                (12, 0) => BD12_444::delta_e_row_avx2,
+                (13, 0) => BD13_444::delta_e_row_avx2,
+                (14, 0) => BD14_444::delta_e_row_avx2,
+                (15, 0) => BD15_444::delta_e_row_avx2,
+                (16, 0) => BD16_444::delta_e_row_avx2,
                 _ => unreachable!(),
             };
         }
     }
     match (bit_depth, xdec) {
         (8, 1) => BD8::delta_e_row_scalar,
+        (9, 1) => BD9::delta_e_row_scalar,
         (10, 1) => BD10::delta_e_row_scalar,
+        (11, 1) => BD11::delta_e_row_scalar,
         (12, 1) => BD12::delta_e_row_scalar,
+        (13, 1) => BD13::delta_e_row_scalar,
+        (14, 1) => BD14::delta_e_row_scalar,
+        (15, 1) => BD15::delta_e_row_scalar,
+        (16, 1) => BD16::delta_e_row_scalar,
         (8, 0) => BD8_444::delta_e_row_scalar,
+        (9, 0) => BD9_444::delta_e_row_scalar,
         (10, 0) => BD10_444::delta_e_row_scalar,
+        (11, 0) => BD11_444::delta_e_row_scalar,
         (12, 0) => BD12_444::delta_e_row_scalar,
+        (13, 0) => BD13_444::delta_e_row_scalar,
+        (14, 0) => BD14_444::delta_e_row_scalar,
+        (15, 0) => BD15_444::delta_e_row_scalar,
+        (16, 0) => BD16_444::delta_e_row_scalar,
         _ => unreachable!(),
     }
 }
@@ -181,37 +360,42 @@ pub(crate) trait Colorspace {
     const X_DECIMATION: u32;
 }
 
-struct BD8;
-struct BD10;
-struct BD12;
+/// Declares a `Colorspace` for each combination of bit depth and horizontal
+/// chroma decimation supported by the scalar implementation.
+macro_rules! colorspaces {
+    ($($name:ident: $bit_depth:expr, $x_decimation:expr;)*) => {
+        $(
+            struct $name;
 
-struct BD8_444;
-struct BD10_444;
-struct BD12_444;
+            impl Colorspace for $name {
+                const BIT_DEPTH: u32 = $bit_depth;
+                const X_DECIMATION: u32 = $x_decimation;
+            }
 
-impl Colorspace for BD8 {
-    const BIT_DEPTH: u32 = 8;
-    const X_DECIMATION: u32 = 1;
-}
-impl Colorspace for BD10 {
-    const BIT_DEPTH: u32 = 10;
-    const X_DECIMATION: u32 = 1;
-}
-impl Colorspace for BD12 {
-    const BIT_DEPTH: u32 = 12;
-    const X_DECIMATION: u32 = 1;
-}
-impl Colorspace for BD8_444 {
-    const BIT_DEPTH: u32 = 8;
-    const X_DECIMATION: u32 = 0;
+            impl DeltaEScalar for $name {}
+        )*
+    };
 }
-impl Colorspace for BD10_444 {
-    const BIT_DEPTH: u32 = 10;
-    const X_DECIMATION: u32 = 0;
-}
-impl Colorspace for BD12_444 {
-    const BIT_DEPTH: u32 = 12;
-    const X_DECIMATION: u32 = 0;
+
+colorspaces! {
+    BD8: 8, 1;
+    BD9: 9, 1;
+    BD10: 10, 1;
+    BD11: 11, 1;
+    BD12: 12, 1;
+    BD13: 13, 1;
+    BD14: 14, 1;
+    BD15: 15, 1;
+    BD16: 16, 1;
+    BD8_444: 8, 0;
+    BD9_444: 9, 0;
+    BD10_444: 10, 0;
+    BD11_444: 11, 0;
+    BD12_444: 12, 0;
+    BD13_444: 13, 0;
+    BD14_444: 14, 0;
+    BD15_444: 15, 0;
+    BD16_444: 16, 0;
 }
 
 fn twice<T>(
@@ -224,27 +408,17 @@ where
 }
 
 pub(crate) trait DeltaEScalar: Colorspace {
-    fn delta_e_scalar(yuv1: (u16, u16, u16), yuv2: (u16, u16, u16)) -> f32 {
-        let scale = (1 << (Self::BIT_DEPTH - 8)) as f32;
-        let yuv_to_rgb = |yuv: (u16, u16, u16)| {
-            // Assumes BT.709
-            let y = (yuv.0 as f32 - 16. * scale) * (1. / (219. * scale));
-            let u = (yuv.1 as f32 - 128. * scale) * (1. / (224. * scale));
-            let v = (yuv.2 as f32 - 128. * scale) * (1. / (224. * scale));
-
-            // [-0.804677, 1.81723]
-            let r = y + 1.28033 * v;
-            // [−0.316650, 1.09589]
-            let g = y - 0.21482 * u - 0.38059 * v;
-            // [-1.28905, 2.29781]
-            let b = y + 2.12798 * u;
-
-            (r, g, b)
-        };
-
-        let (r1, g1, b1) = yuv_to_rgb(yuv1);
-        let (r2, g2, b2) = yuv_to_rgb(yuv2);
-        DE2000::new(rgb_to_lab(&[r1, g1, b1]), rgb_to_lab(&[r2, g2, b2]), K_SUB)
+    fn delta_e_scalar(
+        yuv1: (u16, u16, u16),
+        yuv2: (u16, u16, u16),
+        yuv_to_rgb1: &YuvToRgb,
+        yuv_to_rgb2: &YuvToRgb,
+    ) -> f32 {
+        DE2000::new(
+            rgb_to_lab(&yuv_to_rgb1.convert(yuv1)),
+            rgb_to_lab(&yuv_to_rgb2.convert(yuv2)),
+            K_SUB,
+        )
     }
 
     unsafe fn delta_e_row_scalar<T: Pixel>(
@@ -273,6 +447,8 @@ pub(crate) trait DeltaEScalar: Colorspace {
                         u16::cast_from(*u2),
                         u16::cast_from(*v2),
                     ),
+                    row1.yuv_to_rgb,
+                    row2.yuv_to_rgb,
                 );
             }
         } else {
@@ -290,19 +466,14 @@ pub(crate) trait DeltaEScalar: Colorspace {
                         u16::cast_from(*u2),
                         u16::cast_from(*v2),
                     ),
+                    row1.yuv_to_rgb,
+                    row2.yuv_to_rgb,
                 );
             }
         }
     }
 }
 
-impl DeltaEScalar for BD8 {}
-impl DeltaEScalar for BD10 {}
-impl DeltaEScalar for BD12 {}
-impl DeltaEScalar for BD8_444 {}
-impl DeltaEScalar for BD10_444 {}
-impl DeltaEScalar for BD12_444 {}
-
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 use self::avx2::*;
 use std::error::Error;
@@ -318,31 +489,33 @@ mod avx2 {
 
     pub(crate) trait DeltaEAVX2: Colorspace + DeltaEScalar {
         #[target_feature(enable = "avx2")]
-        unsafe fn yuv_to_rgb(yuv: (__m256, __m256, __m256)) -> (__m256, __m256, __m256) {
-            let scale: f32 = (1 << (Self::BIT_DEPTH - 8)) as f32;
+        unsafe fn yuv_to_rgb(
+            yuv: (__m256, __m256, __m256),
+            yuv_to_rgb: &YuvToRgb,
+        ) -> (__m256, __m256, __m256) {
             #[target_feature(enable = "avx2")]
             unsafe fn set1(val: f32) -> __m256 {
                 _mm256_set1_ps(val)
             };
             let y = _mm256_mul_ps(
-                _mm256_sub_ps(yuv.0, set1(16. * scale)),
-                set1(1. / (219. * scale)),
+                _mm256_sub_ps(yuv.0, set1(yuv_to_rgb.y_offset)),
+                set1(yuv_to_rgb.y_scale),
             );
             let u = _mm256_mul_ps(
-                _mm256_sub_ps(yuv.1, set1(128. * scale)),
-                set1(1. / (224. * scale)),
+                _mm256_sub_ps(yuv.1, set1(yuv_to_rgb.uv_offset)),
+                set1(yuv_to_rgb.uv_scale),
             );
             let v = _mm256_mul_ps(
-                _mm256_sub_ps(yuv.2, set1(128. * scale)),
-                set1(1. / (224. * scale)),
+                _mm256_sub_ps(yuv.2, set1(yuv_to_rgb.uv_offset)),
+                set1(yuv_to_rgb.uv_scale),
             );
 
-            let r = _mm256_add_ps(y, _mm256_mul_ps(v, set1(1.28033)));
+            let r = _mm256_add_ps(y, _mm256_mul_ps(v, set1(yuv_to_rgb.r_v)));
             let g = _mm256_add_ps(
-                _mm256_add_ps(y, _mm256_mul_ps(u, set1(-0.21482))),
-                _mm256_mul_ps(v, set1(-0.38059)),
+                _mm256_add_ps(y, _mm256_mul_ps(u, set1(yuv_to_rgb.g_u))),
+                _mm256_mul_ps(v, set1(yuv_to_rgb.g_v)),
             );
-            let b = _mm256_add_ps(y, _mm256_mul_ps(u, set1(2.12798)));
+            let b = _mm256_add_ps(y, _mm256_mul_ps(u, set1(yuv_to_rgb.b_u)));
 
             (r, g, b)
         }
@@ -351,10 +524,12 @@ mod avx2 {
         unsafe fn delta_e_avx2(
             yuv1: (__m256, __m256, __m256),
             yuv2: (__m256, __m256, __m256),
+            yuv_to_rgb1: &YuvToRgb,
+            yuv_to_rgb2: &YuvToRgb,
             res_chunk: &mut [f32],
         ) {
-            let (r1, g1, b1) = Self::yuv_to_rgb(yuv1);
-            let (r2, g2, b2) = Self::yuv_to_rgb(yuv2);
+            let (r1, g1, b1) = Self::yuv_to_rgb(yuv1, yuv_to_rgb1);
+            let (r2, g2, b2) = Self::yuv_to_rgb(yuv2, yuv_to_rgb2);
 
             let lab1 = rgb_to_lab_avx2(&[r1, g1, b1]);
             let lab2 = rgb_to_lab_avx2(&[r2, g2, b2]);
@@ -369,15 +544,18 @@ mod avx2 {
             row2: FrameRow<T>,
             res_row: &mut [f32],
         ) {
+            // Each chunk holds 8 luma samples, and the chroma samples covering them
+            let chroma_chunk_size = 8 >> Self::X_DECIMATION;
+
             // Only one version should be compiled for each trait
             if Self::BIT_DEPTH == 8 {
                 for (chunk1_y, chunk1_u, chunk1_v, chunk2_y, chunk2_u, chunk2_v, res_chunk) in izip!(
                     row1.y.chunks(8),
-                    row1.u.chunks(4),
-                    row1.v.chunks(4),
+                    row1.u.chunks(chroma_chunk_size),
+                    row1.v.chunks(chroma_chunk_size),
                     row2.y.chunks(8),
-                    row2.u.chunks(4),
-                    row2.v.chunks(4),
+                    row2.u.chunks(chroma_chunk_size),
+                    row2.v.chunks(chroma_chunk_size),
                     res_row.chunks_mut(8)
                 ) {
                     if chunk1_y.len() == 8 {
@@ -388,11 +566,17 @@ mod avx2 {
                         };
 
                         #[inline(always)]
-                        unsafe fn load_chroma(chunk: &[u8]) -> __m256 {
+                        unsafe fn load_subsampled_chroma(chunk: &[u8]) -> __m256 {
                             let tmp = _mm_cvtsi32_si128(*(chunk.as_ptr() as *const i32));
                             _mm256_cvtepi32_ps(_mm256_cvtepu8_epi32(_mm_unpacklo_epi8(tmp, tmp)))
                         };
 
+                        let load_chroma: unsafe fn(&[u8]) -> __m256 = if Self::X_DECIMATION == 1 {
+                            load_subsampled_chroma
+                        } else {
+                            load_luma
+                        };
+
                         Self::delta_e_avx2(
                             (
                                 load_luma(
@@ -434,6 +618,8 @@ mod avx2 {
                                         .collect::<Vec<_>>(),
                                 ),
                             ),
+                            row1.yuv_to_rgb,
+                            row2.yuv_to_rgb,
                             res_chunk,
                         );
                     } else {
@@ -442,11 +628,13 @@ mod avx2 {
                                 y: chunk1_y,
                                 u: chunk1_u,
                                 v: chunk1_v,
+                                yuv_to_rgb: row1.yuv_to_rgb,
                             },
                             FrameRow {
                                 y: chunk2_y,
                                 u: chunk2_u,
                                 v: chunk2_v,
+                                yuv_to_rgb: row2.yuv_to_rgb,
                             },
                             res_chunk,
                         );
@@ -455,11 +643,11 @@ mod avx2 {
             } else {
                 for (chunk1_y, chunk1_u, chunk1_v, chunk2_y, chunk2_u, chunk2_v, res_chunk) in izip!(
                     row1.y.chunks(8),
-                    row1.u.chunks(4),
-                    row1.v.chunks(4),
+                    row1.u.chunks(chroma_chunk_size),
+                    row1.v.chunks(chroma_chunk_size),
                     row2.y.chunks(8),
-                    row2.u.chunks(4),
-                    row2.v.chunks(4),
+                    row2.u.chunks(chroma_chunk_size),
+                    row2.v.chunks(chroma_chunk_size),
                     res_row.chunks_mut(8)
                 ) {
                     if chunk1_y.len() == 8 {
@@ -470,11 +658,17 @@ mod avx2 {
                         };
 
                         #[inline(always)]
-                        unsafe fn load_chroma(chunk: &[u16]) -> __m256 {
+                        unsafe fn load_subsampled_chroma(chunk: &[u16]) -> __m256 {
                             let tmp = _mm_loadl_epi64(chunk.as_ptr() as *const _);
                             _mm256_cvtepi32_ps(_mm256_cvtepu16_epi32(_mm_unpacklo_epi16(tmp, tmp)))
                         };
 
+                        let load_chroma: unsafe fn(&[u16]) -> __m256 = if Self::X_DECIMATION == 1 {
+                            load_subsampled_chroma
+                        } else {
+                            load_luma
+                        };
+
                         Self::delta_e_avx2(
                             (
                                 load_luma(
@@ -516,6 +710,8 @@ mod avx2 {
                                         .collect::<Vec<_>>(),
                                 ),
                             ),
+                            row1.yuv_to_rgb,
+                            row2.yuv_to_rgb,
                             res_chunk,
                         );
                     } else {
@@ -524,11 +720,13 @@ mod avx2 {
                                 y: chunk1_y,
                                 u: chunk1_u,
                                 v: chunk1_v,
+                                yuv_to_rgb: row1.yuv_to_rgb,
                             },
                             FrameRow {
                                 y: chunk2_y,
                                 u: chunk2_u,
                                 v: chunk2_v,
+                                yuv_to_rgb: row2.yuv_to_rgb,
                             },
                             res_chunk,
                         );
@@ -539,94 +737,229 @@ mod avx2 {
     }
 
     impl DeltaEAVX2 for BD8 {}
+    impl DeltaEAVX2 for BD9 {}
     impl DeltaEAVX2 for BD10 {}
+    impl DeltaEAVX2 for BD11 {}
     impl DeltaEAVX2 for BD12 {}
+    impl DeltaEAVX2 for BD13 {}
+    impl DeltaEAVX2 for BD14 {}
+    impl DeltaEAVX2 for BD15 {}
+    impl DeltaEAVX2 for BD16 {}
+    impl DeltaEAVX2 for BD8_444 {}
+    impl DeltaEAVX2 for BD9_444 {}
+    impl DeltaEAVX2 for BD10_444 {}
+    impl DeltaEAVX2 for BD11_444 {}
+    impl DeltaEAVX2 for BD12_444 {}
+    impl DeltaEAVX2 for BD13_444 {}
+    impl DeltaEAVX2 for BD14_444 {}
+    impl DeltaEAVX2 for BD15_444 {}
+    impl DeltaEAVX2 for BD16_444 {}
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::MatrixCoefficients;
     use std::fs::File;
-    use y4m::Decoder;
 
     #[test]
     fn ciede2000_yuv420p8_nosimd() {
         let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(36.2821, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, &VideoOptions::default())
+                .unwrap();
+        assert_metric_eq(36.2821, result.aggregate);
     }
 
     #[test]
     fn ciede2000_yuv420p8() {
         let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(36.2821, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(36.2821, result.aggregate);
+    }
+
+    #[test]
+    fn ciede2000_color_description() {
+        let run = |matrix_coefficients, color_range| {
+            let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+            let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+            let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+            let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+            let options = VideoOptions {
+                matrix_coefficients,
+                color_range,
+                ..Default::default()
+            };
+            calculate_video_ciede(&mut dec1, &mut dec2, &options)
+                .unwrap()
+                .aggregate
+        };
+        // The inputs are limited range unless overridden
+        assert_metric_eq(36.2821, run(None, None));
+        assert_metric_eq(37.2020, run(None, Some(ColorRange::Full)));
+        assert_metric_eq(36.1577, run(Some(MatrixCoefficients::BT601), None));
+        assert_metric_eq(36.2946, run(Some(MatrixCoefficients::BT2020NCL), None));
+    }
+
+    #[test]
+    fn ciede2000_yuv400p8() {
+        let mut file1 = File::open("./testfiles/yuv400p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv400p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(39.7586, result.aggregate);
     }
 
     #[test]
     fn ciede2000_yuv422p8_nosimd() {
         let mut file1 = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv422p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(43.9618, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, &VideoOptions::default())
+                .unwrap();
+        assert_metric_eq(43.9618, result.aggregate);
     }
 
     #[test]
     fn ciede2000_yuv422p8() {
         let mut file1 = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv422p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(43.9618, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(43.9618, result.aggregate);
     }
 
     #[test]
     fn ciede2000_yuv444p8_nosimd() {
         let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(37.5106, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, &VideoOptions::default())
+                .unwrap();
+        assert_metric_eq(37.5106, result.aggregate);
     }
 
     #[test]
     fn ciede2000_yuv444p8() {
         let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(37.5106, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(37.5106, result.aggregate);
     }
 
     #[test]
     fn ciede2000_yuv420p10_nosimd() {
         let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(36.3691, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede_nosimd::<_>(&mut dec1, &mut dec2, &VideoOptions::default())
+                .unwrap();
+        assert_metric_eq(36.3691, result.aggregate);
     }
 
     #[test]
     fn ciede2000_yuv420p10() {
         let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ciede::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(36.3691, result);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ciede::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(36.3691, result.aggregate);
+    }
+
+    #[test]
+    fn ciede2000_high_bit_depths() {
+        use crate::video::bit_depth::convert_bit_depth;
+        use crate::video::decode::Decoder as _;
+        use crate::video::FrameInfo;
+
+        // Shifting both inputs up to a higher bit depth must not change the score
+        for name in &["yuv420p8", "yuv444p8"] {
+            let mut file1 = File::open(format!("./testfiles/{}_input.y4m", name)).unwrap();
+            let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+            let mut file2 = File::open(format!("./testfiles/{}_output.y4m", name)).unwrap();
+            let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+            let frame1 = dec1.read_video_frame::<u8>().unwrap();
+            let frame2 = dec2.read_video_frame::<u8>().unwrap();
+            let expected = calculate_frame_ciede_nosimd(&frame1, &frame2).unwrap();
+            for bit_depth in 9..=16 {
+                let frame1: FrameInfo<u16> = convert_bit_depth(&frame1, bit_depth);
+                let frame2: FrameInfo<u16> = convert_bit_depth(&frame2, bit_depth);
+                assert_metric_eq(
+                    expected,
+                    calculate_frame_ciede_nosimd(&frame1, &frame2).unwrap(),
+                );
+                assert_metric_eq(expected, calculate_frame_ciede(&frame1, &frame2).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn ciede2000_yuv444_simd_matches_scalar() {
+        use crate::video::bit_depth::convert_bit_depth;
+        use crate::video::decode::Decoder as _;
+        use crate::video::{FrameInfo, PlaneRef};
+
+        // Crop to a width which is not a multiple of 8, so the remainder of each row
+        // falls back to the scalar path
+        fn cropped<T: Pixel>(frame: &FrameInfo<T>) -> FrameRef<'_, T> {
+            let plane_ref = |i: usize| {
+                let plane = &frame.planes[i];
+                PlaneRef::new(&plane.data, plane.width - 3, plane.height, plane.width).unwrap()
+            };
+            FrameRef {
+                planes: [plane_ref(0), plane_ref(1), plane_ref(2)],
+                bit_depth: frame.bit_depth,
+                chroma_sampling: frame.chroma_sampling,
+            }
+        }
+
+        let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<u8>(), dec2.read_video_frame::<u8>())
+        {
+            assert_metric_eq(
+                calculate_frame_ciede_nosimd(&frame1, &frame2).unwrap(),
+                calculate_frame_ciede(&frame1, &frame2).unwrap(),
+            );
+            assert_metric_eq(
+                calculate_frame_ciede_nosimd(cropped(&frame1), cropped(&frame2)).unwrap(),
+                calculate_frame_ciede(cropped(&frame1), cropped(&frame2)).unwrap(),
+            );
+            for &bit_depth in &[10, 12, 16] {
+                let frame1: FrameInfo<u16> = convert_bit_depth(&frame1, bit_depth);
+                let frame2: FrameInfo<u16> = convert_bit_depth(&frame2, bit_depth);
+                assert_metric_eq(
+                    calculate_frame_ciede_nosimd(cropped(&frame1), cropped(&frame2)).unwrap(),
+                    calculate_frame_ciede(cropped(&frame1), cropped(&frame2)).unwrap(),
+                );
+            }
+        }
     }
 }
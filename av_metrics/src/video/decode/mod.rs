@@ -1,5 +1,7 @@
 use crate::video::pixel::Pixel;
-use crate::video::FrameInfo;
+use crate::video::{ChromaSamplePosition, ChromaSampling, ColorDescription, FrameInfo};
+use crate::MetricsError;
+use std::io;
 
 #[cfg(feature = "y4m-decode")]
 mod y4m;
@@ -9,14 +11,115 @@ pub use self::y4m::*;
 
 /// A trait for allowing metrics to decode generic video formats.
 ///
-/// Currently, y4m decoding support using the `y4m` crate is built-in
-/// to this crate. This trait is extensible so users may implement
-/// their own decoders.
+/// Currently, y4m decoding support is built-in to this crate as
+/// [`Y4mDecoder`](struct.Y4mDecoder.html). This trait is extensible
+/// so users may implement their own decoders.
 pub trait Decoder {
     /// Read the next frame from the input video.
     ///
-    /// Expected to return `Err` if the end of the video is reached.
-    fn read_video_frame<T: Pixel>(&mut self) -> Result<FrameInfo<T>, ()>;
+    /// Expected to return `Err(DecodeError::EndOfStream)` if the end of the video is reached.
+    fn read_video_frame<T: Pixel>(&mut self) -> Result<FrameInfo<T>, DecodeError>;
     /// Get the bit depth of the video.
-    fn get_bit_depth(&self) -> usize;
+    fn get_bit_depth(&self) -> usize {
+        self.get_video_details().bit_depth
+    }
+    /// Get the format of the video and other properties
+    /// which are known before any frames are decoded.
+    fn get_video_details(&self) -> VideoDetails;
+}
+
+/// The format and other properties of a video, as reported by a [`Decoder`](trait.Decoder.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoDetails {
+    /// The width, in pixels, of the luma plane.
+    pub width: usize,
+    /// The height, in pixels, of the luma plane.
+    pub height: usize,
+    /// The number of bits per pixel.
+    pub bit_depth: usize,
+    /// The chroma sampling format of the video.
+    pub chroma_sampling: ChromaSampling,
+    /// The position of subsampled chroma samples, as stored in the input.
+    pub chroma_sample_position: ChromaSamplePosition,
+    /// How the samples of the video map to colors.
+    /// Properties which the input does not specify are reported with their defaults.
+    pub color_description: ColorDescription,
+    /// The number of frames per second.
+    pub frame_rate: Rational,
+    /// The total number of frames in the video, if the decoder knows it
+    /// without reading the entire input.
+    pub frame_count: Option<usize>,
+}
+
+impl VideoDetails {
+    /// The time, in seconds, at which the frame with the given index is displayed.
+    ///
+    /// Returns `None` if the frame rate of the video is unknown.
+    pub fn frame_timestamp(&self, frame_index: usize) -> Option<f64> {
+        if self.frame_rate.num == 0 || self.frame_rate.den == 0 {
+            return None;
+        }
+        Some(frame_index as f64 * self.frame_rate.den as f64 / self.frame_rate.num as f64)
+    }
+
+    pub(crate) fn can_compare(&self, other: &Self) -> Result<(), MetricsError> {
+        if self.bit_depth != other.bit_depth {
+            return Err(MetricsError::InputMismatch {
+                reason: "Bit depths do not match",
+            });
+        }
+        if self.width != other.width || self.height != other.height {
+            return Err(MetricsError::InputMismatch {
+                reason: "Video resolution does not match",
+            });
+        }
+        if self.chroma_sampling != other.chroma_sampling {
+            return Err(MetricsError::InputMismatch {
+                reason: "Chroma subsampling offsets do not match",
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// A rational number, such as a frame rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rational {
+    /// The numerator.
+    pub num: u64,
+    /// The denominator.
+    pub den: u64,
+}
+
+impl Rational {
+    /// Creates a new rational number.
+    pub fn new(num: u64, den: u64) -> Self {
+        Rational { num, den }
+    }
+}
+
+/// Possible errors that may occur while reading a frame from a [`Decoder`](trait.Decoder.html).
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    /// The end of the video was reached. This is not a failure,
+    /// but indicates that there are no more frames to read.
+    #[error(display = "End of stream")]
+    EndOfStream,
+    /// The input could not be read.
+    #[error(display = "I/O error: {}", _0)]
+    Io(#[error(source)] io::Error),
+    /// The input could be read, but does not contain valid video data.
+    #[error(display = "Malformed input: {}", reason)]
+    Malformed {
+        #[doc(hidden)]
+        reason: &'static str,
+    },
+    /// The input is valid, but uses a format which is not supported by this decoder.
+    #[error(display = "Unsupported format: {}", reason)]
+    UnsupportedFormat {
+        #[doc(hidden)]
+        reason: &'static str,
+    },
 }
@@ -1,8 +1,10 @@
-use crate::video::decode::Decoder;
+use crate::video::decode::{DecodeError, Decoder, Rational, VideoDetails};
 use crate::video::pixel::CastFromPrimitive;
 use crate::video::pixel::Pixel;
-use crate::video::{ChromaSamplePosition, ChromaSampling, FrameInfo, PlaneData};
-use std::io::Read;
+use crate::video::{
+    ChromaSamplePosition, ChromaSampling, ColorDescription, ColorRange, FrameInfo, PlaneData,
+};
+use std::io::{self, Read};
 use std::{cmp, mem};
 
 fn get_chroma_sampling<R: Read>(
@@ -22,6 +24,43 @@ fn get_chroma_sampling<R: Read>(
     }
 }
 
+/// Y4M headers do not include matrix coefficients or the transfer function,
+/// but may include the color range.
+fn get_color_description<R: Read>(dec: &y4m::Decoder<'_, R>) -> ColorDescription {
+    // FFmpeg signals full range with an `XCOLORRANGE` parameter.
+    // The `C420jpeg` colorspace only describes the chroma sample position,
+    // and FFmpeg also uses it for limited range video.
+    let full_range = dec
+        .get_raw_params()
+        .split(|&b| b == b' ')
+        .any(|param| param == b"XCOLORRANGE=FULL");
+    let range = if full_range {
+        ColorRange::Full
+    } else {
+        ColorRange::Limited
+    };
+    ColorDescription {
+        range,
+        ..Default::default()
+    }
+}
+
+fn get_video_details<R: Read>(dec: &y4m::Decoder<'_, R>) -> VideoDetails {
+    let (chroma_sampling, chroma_sample_position) = get_chroma_sampling(dec);
+    let frame_rate = dec.get_framerate();
+    VideoDetails {
+        width: dec.get_width(),
+        height: dec.get_height(),
+        bit_depth: dec.get_bit_depth(),
+        chroma_sampling,
+        chroma_sample_position,
+        color_description: get_color_description(dec),
+        frame_rate: Rational::new(frame_rate.num as u64, frame_rate.den as u64),
+        // Y4M headers do not include the length of the video
+        frame_count: None,
+    }
+}
+
 fn copy_from_raw_u8<T: Pixel>(source: &[u8]) -> Vec<T> {
     match mem::size_of::<T>() {
         1 => source.iter().map(|byte| T::cast_from(*byte)).collect(),
@@ -38,50 +77,158 @@ fn copy_from_raw_u8<T: Pixel>(source: &[u8]) -> Vec<T> {
     }
 }
 
-impl<R: Read> Decoder for y4m::Decoder<'_, R> {
-    fn read_video_frame<T: Pixel>(&mut self) -> Result<FrameInfo<T>, ()> {
-        let bit_depth = self.get_bit_depth();
-        let (chroma_sampling, chroma_sample_pos) = get_chroma_sampling(self);
-        let luma_width = self.get_width();
-        let luma_height = self.get_height();
+/// The maximum length of a frame header, as accepted by the `y4m` crate.
+const MAX_FRAME_HEADER_LEN: usize = 1024;
+
+/// A decoder for YUV4MPEG2 (y4m) video.
+///
+/// The stream header is parsed by the `y4m` crate, but frames are read here,
+/// so that a frame which is cut off by the end of the input is reported
+/// as malformed, rather than as the end of the video.
+pub struct Y4mDecoder<R: Read> {
+    reader: R,
+    details: VideoDetails,
+    /// The size in bytes of the luma plane and of each chroma plane.
+    plane_sizes: (usize, usize),
+    frame_buf: Vec<u8>,
+}
+
+impl<R: Read> Y4mDecoder<R> {
+    /// Reads the stream header of a y4m video from `reader`.
+    pub fn new(mut reader: R) -> Result<Self, DecodeError> {
+        let details = get_video_details(&y4m::Decoder::new(&mut reader).map_err(convert_error)?);
+        let bytes_per_sample = if details.bit_depth > 8 { 2 } else { 1 };
+        let (chroma_width, chroma_height) = details
+            .chroma_sampling
+            .get_chroma_dimensions(details.width, details.height);
+        let plane_sizes = (
+            details.width * details.height * bytes_per_sample,
+            chroma_width * chroma_height * bytes_per_sample,
+        );
+        Ok(Y4mDecoder {
+            reader,
+            details,
+            plane_sizes,
+            frame_buf: vec![0; plane_sizes.0 + 2 * plane_sizes.1],
+        })
+    }
+
+    /// Reads the header of the next frame, returning `DecodeError::EndOfStream`
+    /// only if the input ends before the first byte of the header.
+    fn read_frame_header(&mut self) -> Result<(), DecodeError> {
+        let mut header = Vec::new();
+        loop {
+            let mut byte = [0; 1];
+            match self.reader.read(&mut byte) {
+                Ok(0) if header.is_empty() => return Err(DecodeError::EndOfStream),
+                Ok(0) => {
+                    return Err(DecodeError::Malformed {
+                        reason: "Truncated y4m frame header",
+                    })
+                }
+                Ok(_) if byte[0] == b'\n' => break,
+                Ok(_) if header.len() == MAX_FRAME_HEADER_LEN => {
+                    return Err(DecodeError::Malformed {
+                        reason: "y4m frame header is too long",
+                    })
+                }
+                Ok(_) => header.push(byte[0]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(DecodeError::Io(e)),
+            }
+        }
+        // Frame parameters are allowed, but ignored
+        if header.starts_with(b"FRAME") && (header.len() == 5 || header[5] == b' ') {
+            Ok(())
+        } else {
+            Err(DecodeError::Malformed {
+                reason: "Could not parse y4m frame header",
+            })
+        }
+    }
+}
+
+impl<R: Read> Decoder for Y4mDecoder<R> {
+    fn read_video_frame<T: Pixel>(&mut self) -> Result<FrameInfo<T>, DecodeError> {
+        let VideoDetails {
+            width: luma_width,
+            height: luma_height,
+            bit_depth,
+            chroma_sampling,
+            chroma_sample_position: chroma_sample_pos,
+            ..
+        } = self.details;
         let (chroma_width, chroma_height) =
             chroma_sampling.get_chroma_dimensions(luma_width, luma_height);
 
-        self.read_frame()
-            .map(|frame| FrameInfo {
-                bit_depth,
-                chroma_sampling,
-                planes: [
+        self.read_frame_header()?;
+        // Once the header has been read, the end of the input means the frame is incomplete
+        self.reader
+            .read_exact(&mut self.frame_buf)
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::UnexpectedEof => DecodeError::Malformed {
+                    reason: "Truncated y4m frame",
+                },
+                _ => DecodeError::Io(error),
+            })?;
+        let (y_plane, chroma_planes) = self.frame_buf.split_at(self.plane_sizes.0);
+        let (u_plane, v_plane) = chroma_planes.split_at(self.plane_sizes.1);
+
+        Ok(FrameInfo {
+            bit_depth,
+            chroma_sampling,
+            planes: [
+                PlaneData {
+                    width: luma_width,
+                    height: luma_height,
+                    data: copy_from_raw_u8(y_plane),
+                },
+                convert_chroma_data(
                     PlaneData {
-                        width: luma_width,
-                        height: luma_height,
-                        data: copy_from_raw_u8(frame.get_y_plane()),
+                        width: chroma_width,
+                        height: chroma_height,
+                        data: copy_from_raw_u8(u_plane),
                     },
-                    convert_chroma_data(
-                        PlaneData {
-                            width: chroma_width,
-                            height: chroma_height,
-                            data: copy_from_raw_u8(frame.get_u_plane()),
-                        },
-                        chroma_sample_pos,
-                        bit_depth,
-                    ),
-                    convert_chroma_data(
-                        PlaneData {
-                            width: chroma_width,
-                            height: chroma_height,
-                            data: copy_from_raw_u8(frame.get_v_plane()),
-                        },
-                        chroma_sample_pos,
-                        bit_depth,
-                    ),
-                ],
-            })
-            .map_err(|_| ())
+                    chroma_sample_pos,
+                    bit_depth,
+                ),
+                convert_chroma_data(
+                    PlaneData {
+                        width: chroma_width,
+                        height: chroma_height,
+                        data: copy_from_raw_u8(v_plane),
+                    },
+                    chroma_sample_pos,
+                    bit_depth,
+                ),
+            ],
+        })
     }
 
-    fn get_bit_depth(&self) -> usize {
-        self.get_bit_depth()
+    fn get_video_details(&self) -> VideoDetails {
+        self.details
+    }
+}
+
+/// Converts an error from parsing a y4m stream header into a `DecodeError`.
+fn convert_error(error: y4m::Error) -> DecodeError {
+    match error {
+        y4m::Error::EOF => DecodeError::Malformed {
+            reason: "Missing or truncated y4m header",
+        },
+        y4m::Error::IoError(e) => DecodeError::Io(e),
+        y4m::Error::ParseError => DecodeError::Malformed {
+            reason: "Could not parse y4m header",
+        },
+        y4m::Error::BadInput => DecodeError::Malformed {
+            reason: "Invalid y4m parameters",
+        },
+        y4m::Error::OutOfMemory => DecodeError::Malformed {
+            reason: "y4m frame exceeds the decoder's memory limits",
+        },
+        y4m::Error::UnknownColorspace => DecodeError::UnsupportedFormat {
+            reason: "Unknown y4m colorspace",
+        },
     }
 }
 
@@ -163,3 +310,92 @@ fn clamp<T: PartialOrd>(input: T, min: T, max: T) -> T {
         input
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+
+    #[test]
+    fn y4m_video_details_yuv422p8() {
+        let mut file = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
+        let dec = Y4mDecoder::new(&mut file).unwrap();
+        let details = dec.get_video_details();
+        assert_eq!(640, details.width);
+        assert_eq!(360, details.height);
+        assert_eq!(8, details.bit_depth);
+        assert_eq!(ChromaSampling::Cs422, details.chroma_sampling);
+        assert_eq!(
+            ChromaSamplePosition::Vertical,
+            details.chroma_sample_position
+        );
+        assert_eq!(Rational::new(25, 1), details.frame_rate);
+        assert_eq!(None, details.frame_count);
+        assert_eq!(Some(0.08), details.frame_timestamp(2));
+    }
+
+    #[test]
+    fn y4m_truncated_frame_yuv420p8() {
+        let mut data = Vec::new();
+        File::open("./testfiles/yuv420p8_input.y4m")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        let mut dec = Y4mDecoder::new(&data[..]).unwrap();
+        while dec.read_video_frame::<u8>().is_ok() {}
+        match dec.read_video_frame::<u8>() {
+            Err(DecodeError::EndOfStream) => (),
+            _ => panic!("Expected the end of the stream"),
+        }
+
+        // Cut the file off in the middle of the last frame
+        let mut dec = Y4mDecoder::new(&data[..data.len() - 1000]).unwrap();
+        let error = loop {
+            if let Err(error) = dec.read_video_frame::<u8>() {
+                break error;
+            }
+        };
+        match error {
+            DecodeError::Malformed { .. } => (),
+            _ => panic!("Unexpected error: {}", error),
+        }
+
+        // Cut the file off in the middle of a frame header
+        let last_frame = data
+            .windows(6)
+            .rposition(|window| window == b"FRAME\n")
+            .unwrap();
+        let mut dec = Y4mDecoder::new(&data[..last_frame + 3]).unwrap();
+        let error = loop {
+            if let Err(error) = dec.read_video_frame::<u8>() {
+                break error;
+            }
+        };
+        match error {
+            DecodeError::Malformed { .. } => (),
+            _ => panic!("Unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn y4m_color_range() {
+        let range = |header: &[u8]| {
+            let dec = Y4mDecoder::new(header).unwrap();
+            dec.get_video_details().color_description.range
+        };
+        assert_eq!(ColorRange::Limited, range(b"YUV4MPEG2 W2 H2 F25:1 C420\n"));
+        assert_eq!(
+            ColorRange::Limited,
+            range(b"YUV4MPEG2 W2 H2 F25:1 C420jpeg\n")
+        );
+        assert_eq!(
+            ColorRange::Limited,
+            range(b"YUV4MPEG2 W2 H2 F25:1 C420jpeg XCOLORRANGE=LIMITED\n")
+        );
+        assert_eq!(
+            ColorRange::Full,
+            range(b"YUV4MPEG2 W2 H2 F25:1 C444 XCOLORRANGE=FULL\n")
+        );
+    }
+}
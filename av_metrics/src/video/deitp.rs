@@ -0,0 +1,763 @@
+//! The ΔE ITP color difference formula from ITU-R BT.2124.
+//!
+//! Unlike CIEDE2000, which assumes a standard dynamic range display,
+//! ΔE ITP is designed for high dynamic range and wide color gamut video.
+//! Each frame is converted from BT.2020 YUV to linear light using the transfer function
+//! of the video (PQ, HLG or BT.1886), then to the ICtCp color space,
+//! in which distances closely match perceived color differences.
+//!
+//! Scores are the mean ΔE ITP over all pixels, so lower is better.
+//! A difference of 1 corresponds roughly to one just noticeable difference.
+
+use crate::cpu_features::CpuFeatureLevel;
+#[cfg(feature = "decode")]
+use crate::video::decode::Decoder;
+use crate::video::pixel::{CastFromPrimitive, Pixel};
+#[cfg(feature = "decode")]
+use crate::video::{decode_and_process_video, VideoOptions};
+use crate::video::{
+    ChromaSampling, ColorDescription, ColorRange, ComparisonInfo, FrameRef, MatrixCoefficients,
+    TransferCharacteristics, VideoMetric, VideoMetricResults,
+};
+use std::error::Error;
+
+/// Calculate the ΔE ITP metric between two video clips. Lower is better.
+///
+/// Frames are converted using the color description reported by each decoder,
+/// which may be overridden through [`VideoOptions`](../struct.VideoOptions.html).
+/// By default, this will return at the end of the shorter of the two clips,
+/// comparing any frames up to that point.
+#[cfg(feature = "decode")]
+#[inline]
+pub fn calculate_video_deitp<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<f64>, Box<dyn Error + Send + Sync>> {
+    DeltaEItp::default().process_video(decoder1, decoder2, options)
+}
+
+/// Calculate the ΔE ITP metric between two video clips. Lower is better.
+///
+/// This version disables SIMD. It is intended to only be used
+/// by tests and benchmarks.
+#[cfg(all(feature = "decode", any(test, feature = "bench")))]
+#[inline]
+pub fn calculate_video_deitp_nosimd<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<f64>, Box<dyn Error + Send + Sync>> {
+    DeltaEItp {
+        cpu: CpuFeatureLevel::RUST,
+        ..Default::default()
+    }
+    .process_video(decoder1, decoder2, options)
+}
+
+/// Calculate the ΔE ITP metric between two video frames. Lower is better.
+///
+/// The frames are assumed to use the default `ColorDescription`.
+/// Use [`DeltaEItp::with_color_descriptions`](struct.DeltaEItp.html#method.with_color_descriptions)
+/// for other inputs, such as PQ or HLG video.
+#[inline]
+pub fn calculate_frame_deitp<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    DeltaEItp::default().process_frame(&frame1.into(), &frame2.into())
+}
+
+/// Calculate the ΔE ITP metric between two video frames. Lower is better.
+///
+/// This version disables SIMD. It is intended to only be used
+/// by tests and benchmarks.
+#[cfg(any(test, feature = "bench"))]
+#[inline]
+pub fn calculate_frame_deitp_nosimd<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    DeltaEItp {
+        cpu: CpuFeatureLevel::RUST,
+        ..Default::default()
+    }
+    .process_frame(&frame1.into(), &frame2.into())
+}
+
+/// The ΔE ITP metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+///
+/// Frames are converted according to the range and transfer function
+/// in the color description of each video.
+/// BT.2020 primaries and matrix coefficients are always assumed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeltaEItp {
+    cpu: CpuFeatureLevel,
+    color_descriptions: [ColorDescription; 2],
+}
+
+impl DeltaEItp {
+    /// Creates a ΔE ITP metric which converts the frames of the first and second
+    /// video according to the given color descriptions.
+    ///
+    /// When processing a video, the color descriptions reported by the decoders
+    /// are used instead, unless they are overridden by `VideoOptions`.
+    pub fn with_color_descriptions(
+        color_description1: ColorDescription,
+        color_description2: ColorDescription,
+    ) -> Self {
+        DeltaEItp {
+            color_descriptions: [color_description1, color_description2],
+            ..Default::default()
+        }
+    }
+
+    /// Returns a copy of this metric using the color descriptions of the given videos.
+    #[cfg(feature = "decode")]
+    pub(crate) fn for_videos<D: Decoder>(
+        self,
+        decoder1: &D,
+        decoder2: &D,
+        options: &VideoOptions,
+    ) -> Self {
+        DeltaEItp {
+            color_descriptions: [
+                options.color_description(&decoder1.get_video_details()),
+                options.color_description(&decoder2.get_video_details()),
+            ],
+            ..self
+        }
+    }
+}
+
+impl VideoMetric for DeltaEItp {
+    type FrameResult = f64;
+    type VideoResult = VideoMetricResults<f64>;
+
+    #[cfg(feature = "decode")]
+    fn process_video<D: Decoder>(
+        &self,
+        decoder1: &mut D,
+        decoder2: &mut D,
+        options: &VideoOptions,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let metric = self.for_videos(decoder1, decoder2, options);
+        decode_and_process_video(&metric, decoder1, decoder2, options)
+    }
+
+    fn process_frame<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
+
+        let width = frame1.planes[0].width();
+        let height = frame1.planes[0].height();
+        let bit_depth = frame1.bit_depth;
+        let converter1 = ItpConverter::new(self.color_descriptions[0], bit_depth);
+        let converter2 = ItpConverter::new(self.color_descriptions[1], bit_depth);
+        let delta_e_row_fn = get_delta_e_row_fn(self.cpu);
+
+        let mut row1 = RowBuffer::new(width);
+        let mut row2 = RowBuffer::new(width);
+        let mut sum = 0.;
+        for i in 0..height {
+            row1.fill(frame1, i);
+            row2.fill(frame2, i);
+            sum += unsafe { delta_e_row_fn(&row1, &row2, &converter1, &converter2) };
+        }
+        Ok(sum / (width * height) as f64)
+    }
+
+    fn aggregate_frame_results(
+        &self,
+        metrics: &[Self::FrameResult],
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        Ok(VideoMetricResults {
+            aggregate: metrics.iter().copied().sum::<f64>() / metrics.len() as f64,
+            frames: metrics.to_vec(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+        })
+    }
+}
+
+/// The samples of one row of a frame, with chroma upsampled to the luma resolution.
+pub(crate) struct RowBuffer {
+    pub(crate) y: Vec<f32>,
+    pub(crate) u: Vec<f32>,
+    pub(crate) v: Vec<f32>,
+}
+
+impl RowBuffer {
+    pub(crate) fn new(width: usize) -> Self {
+        RowBuffer {
+            y: vec![0.; width],
+            u: vec![0.; width],
+            v: vec![0.; width],
+        }
+    }
+
+    pub(crate) fn fill<T: Pixel>(&mut self, frame: &FrameRef<T>, row: usize) {
+        for (y, &sample) in self.y.iter_mut().zip(frame.planes[0].row(row)) {
+            *y = u16::cast_from(sample) as f32;
+        }
+        match frame.chroma_sampling.get_decimation() {
+            Some((xdec, ydec)) => {
+                let u_row = frame.planes[1].row(row >> ydec);
+                let v_row = frame.planes[2].row(row >> ydec);
+                for (x, (u, v)) in self.u.iter_mut().zip(self.v.iter_mut()).enumerate() {
+                    *u = u16::cast_from(u_row[x >> xdec]) as f32;
+                    *v = u16::cast_from(v_row[x >> xdec]) as f32;
+                }
+            }
+            // 4:0:0 video is compared as if it had neutral chroma
+            None => {
+                let neutral = (1u32 << (frame.bit_depth - 1)) as f32;
+                debug_assert_eq!(ChromaSampling::Cs400, frame.chroma_sampling);
+                self.u.iter_mut().for_each(|u| *u = neutral);
+                self.v.iter_mut().for_each(|v| *v = neutral);
+            }
+        }
+    }
+}
+
+/// Converts the samples of one video to ICtCp.
+#[derive(Debug, Clone, Copy)]
+struct ItpConverter {
+    y_offset: f32,
+    y_scale: f32,
+    uv_offset: f32,
+    uv_scale: f32,
+    r_v: f32,
+    g_u: f32,
+    g_v: f32,
+    b_u: f32,
+    transfer_characteristics: TransferCharacteristics,
+}
+
+impl ItpConverter {
+    fn new(color_description: ColorDescription, bit_depth: usize) -> Self {
+        let (kr, kb) = MatrixCoefficients::BT2020NCL.luma_weights();
+        let kg = 1. - kr - kb;
+        let (y_offset, y_scale, uv_scale) = match color_description.range {
+            ColorRange::Limited => {
+                let scale = (1 << (bit_depth - 8)) as f32;
+                (16. * scale, 1. / (219. * scale), 1. / (224. * scale))
+            }
+            ColorRange::Full => {
+                let max = ((1 << bit_depth) - 1) as f32;
+                (0., 1. / max, 1. / max)
+            }
+        };
+        ItpConverter {
+            y_offset,
+            y_scale,
+            uv_offset: (1 << (bit_depth - 1)) as f32,
+            uv_scale,
+            r_v: 2. * (1. - kr),
+            g_u: 2. * kb * (1. - kb) / kg,
+            g_v: 2. * kr * (1. - kr) / kg,
+            b_u: 2. * (1. - kb),
+            transfer_characteristics: color_description.transfer_characteristics,
+        }
+    }
+
+    fn convert(&self, y: f32, u: f32, v: f32) -> [f32; 3] {
+        let y = (y - self.y_offset) * self.y_scale;
+        let u = (u - self.uv_offset) * self.uv_scale;
+        let v = (v - self.uv_offset) * self.uv_scale;
+
+        let r = (y + self.r_v * v).clamp(0., 1.);
+        let g = (y - self.g_u * u - self.g_v * v).clamp(0., 1.);
+        let b = (y + self.b_u * u).clamp(0., 1.);
+
+        let [r, g, b] = self.linearize([r, g, b]);
+        let l = pq_inverse_eotf((1688. * r + 2146. * g + 262. * b) * (1. / 4096.));
+        let m = pq_inverse_eotf((683. * r + 2951. * g + 462. * b) * (1. / 4096.));
+        let s = pq_inverse_eotf((99. * r + 309. * g + 3688. * b) * (1. / 4096.));
+
+        let i = 0.5 * l + 0.5 * m;
+        let ct = (6610. * l - 13613. * m + 7003. * s) * (1. / 4096.);
+        let cp = (17933. * l - 17390. * m - 543. * s) * (1. / 4096.);
+        // T is scaled by 0.5 to correct for the hue shifts of Ct
+        [i, 0.5 * ct, cp]
+    }
+
+    /// Converts non-linear RGB to display light, where 1.0 is 10000 cd/m².
+    fn linearize(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self.transfer_characteristics {
+            // A reference display with a peak of 100 cd/m²
+            TransferCharacteristics::BT1886 => {
+                [rgb[0].powf(2.4), rgb[1].powf(2.4), rgb[2].powf(2.4)].map_scale(0.01)
+            }
+            TransferCharacteristics::PQ => [pq_eotf(rgb[0]), pq_eotf(rgb[1]), pq_eotf(rgb[2])],
+            // A reference display with a peak of 1000 cd/m²
+            TransferCharacteristics::HLG => {
+                let rgb = [
+                    hlg_inverse_oetf(rgb[0]),
+                    hlg_inverse_oetf(rgb[1]),
+                    hlg_inverse_oetf(rgb[2]),
+                ];
+                let luma = HLG_LUMA[0] * rgb[0] + HLG_LUMA[1] * rgb[1] + HLG_LUMA[2] * rgb[2];
+                let gain = if luma > 0. {
+                    0.1 * luma.powf(HLG_GAMMA - 1.)
+                } else {
+                    0.
+                };
+                rgb.map_scale(gain)
+            }
+        }
+    }
+}
+
+trait MapScale {
+    fn map_scale(self, scale: f32) -> Self;
+}
+
+impl MapScale for [f32; 3] {
+    fn map_scale(self, scale: f32) -> Self {
+        [self[0] * scale, self[1] * scale, self[2] * scale]
+    }
+}
+
+// Constants for the PQ transfer function, from SMPTE ST 2084
+const PQ_M1: f32 = 2610. / 16384.;
+const PQ_M2: f32 = 2523. / 4096. * 128.;
+const PQ_C1: f32 = 3424. / 4096.;
+const PQ_C2: f32 = 2413. / 4096. * 32.;
+const PQ_C3: f32 = 2392. / 4096. * 32.;
+
+// Constants for the HLG transfer function, from ITU-R BT.2100
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 1. - 4. * HLG_A;
+const HLG_C: f32 = 0.559_910_7;
+const HLG_GAMMA: f32 = 1.2;
+const HLG_LUMA: [f32; 3] = [0.2627, 0.6780, 0.0593];
+
+fn pq_eotf(x: f32) -> f32 {
+    let p = x.powf(1. / PQ_M2);
+    ((p - PQ_C1).max(0.) / (PQ_C2 - PQ_C3 * p)).powf(1. / PQ_M1)
+}
+
+fn pq_inverse_eotf(x: f32) -> f32 {
+    let p = x.powf(PQ_M1);
+    ((PQ_C1 + PQ_C2 * p) / (1. + PQ_C3 * p)).powf(PQ_M2)
+}
+
+fn hlg_inverse_oetf(x: f32) -> f32 {
+    if x <= 0.5 {
+        x * x / 3.
+    } else {
+        (((x - HLG_C) / HLG_A).exp() + HLG_B) / 12.
+    }
+}
+
+type DeltaERowFn = unsafe fn(&RowBuffer, &RowBuffer, &ItpConverter, &ItpConverter) -> f64;
+
+fn get_delta_e_row_fn(cpu: CpuFeatureLevel) -> DeltaERowFn {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if cpu >= CpuFeatureLevel::AVX2 {
+            return avx2::delta_e_row_avx2;
+        }
+    }
+    delta_e_row_scalar
+}
+
+fn delta_e(itp1: [f32; 3], itp2: [f32; 3]) -> f32 {
+    let di = itp1[0] - itp2[0];
+    let dt = itp1[1] - itp2[1];
+    let dp = itp1[2] - itp2[2];
+    720. * (di * di + dt * dt + dp * dp).sqrt()
+}
+
+unsafe fn delta_e_row_scalar(
+    row1: &RowBuffer,
+    row2: &RowBuffer,
+    converter1: &ItpConverter,
+    converter2: &ItpConverter,
+) -> f64 {
+    let mut sum = 0.;
+    for x in 0..row1.y.len() {
+        let itp1 = converter1.convert(row1.y[x], row1.u[x], row1.v[x]);
+        let itp2 = converter2.convert(row2.y[x], row2.u[x], row2.v[x]);
+        sum += delta_e(itp1, itp2) as f64;
+    }
+    sum
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2 {
+    use super::*;
+
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn delta_e_row_avx2(
+        row1: &RowBuffer,
+        row2: &RowBuffer,
+        converter1: &ItpConverter,
+        converter2: &ItpConverter,
+    ) -> f64 {
+        let width = row1.y.len();
+        let simd_width = width & !7;
+        let mut sum = 0.;
+        for x in (0..simd_width).step_by(8) {
+            let load = |row: &RowBuffer| {
+                (
+                    _mm256_loadu_ps(row.y[x..].as_ptr()),
+                    _mm256_loadu_ps(row.u[x..].as_ptr()),
+                    _mm256_loadu_ps(row.v[x..].as_ptr()),
+                )
+            };
+            let itp1 = convert_avx2(load(row1), converter1);
+            let itp2 = convert_avx2(load(row2), converter2);
+
+            let di = _mm256_sub_ps(itp1[0], itp2[0]);
+            let dt = _mm256_sub_ps(itp1[1], itp2[1]);
+            let dp = _mm256_sub_ps(itp1[2], itp2[2]);
+            let distance = _mm256_mul_ps(
+                _mm256_set1_ps(720.),
+                _mm256_sqrt_ps(_mm256_add_ps(
+                    _mm256_add_ps(_mm256_mul_ps(di, di), _mm256_mul_ps(dt, dt)),
+                    _mm256_mul_ps(dp, dp),
+                )),
+            );
+            let mut lanes = [0f32; 8];
+            _mm256_storeu_ps(lanes.as_mut_ptr(), distance);
+            sum += lanes.iter().map(|&d| d as f64).sum::<f64>();
+        }
+        for x in simd_width..width {
+            let itp1 = converter1.convert(row1.y[x], row1.u[x], row1.v[x]);
+            let itp2 = converter2.convert(row2.y[x], row2.u[x], row2.v[x]);
+            sum += delta_e(itp1, itp2) as f64;
+        }
+        sum
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn convert_avx2(yuv: (__m256, __m256, __m256), converter: &ItpConverter) -> [__m256; 3] {
+        let set1 = |x: f32| _mm256_set1_ps(x);
+        let y = _mm256_mul_ps(
+            _mm256_sub_ps(yuv.0, set1(converter.y_offset)),
+            set1(converter.y_scale),
+        );
+        let u = _mm256_mul_ps(
+            _mm256_sub_ps(yuv.1, set1(converter.uv_offset)),
+            set1(converter.uv_scale),
+        );
+        let v = _mm256_mul_ps(
+            _mm256_sub_ps(yuv.2, set1(converter.uv_offset)),
+            set1(converter.uv_scale),
+        );
+
+        let clamp_unit = |x: __m256| _mm256_min_ps(_mm256_max_ps(x, set1(0.)), set1(1.));
+        let r = clamp_unit(_mm256_add_ps(y, _mm256_mul_ps(v, set1(converter.r_v))));
+        let g = clamp_unit(_mm256_sub_ps(
+            _mm256_sub_ps(y, _mm256_mul_ps(u, set1(converter.g_u))),
+            _mm256_mul_ps(v, set1(converter.g_v)),
+        ));
+        let b = clamp_unit(_mm256_add_ps(y, _mm256_mul_ps(u, set1(converter.b_u))));
+
+        let [r, g, b] = linearize_avx2([r, g, b], converter.transfer_characteristics);
+        let mix = |cr: f32, cg: f32, cb: f32| {
+            _mm256_add_ps(
+                _mm256_add_ps(
+                    _mm256_mul_ps(r, set1(cr / 4096.)),
+                    _mm256_mul_ps(g, set1(cg / 4096.)),
+                ),
+                _mm256_mul_ps(b, set1(cb / 4096.)),
+            )
+        };
+        let l = pq_inverse_eotf_avx2(mix(1688., 2146., 262.));
+        let m = pq_inverse_eotf_avx2(mix(683., 2951., 462.));
+        let s = pq_inverse_eotf_avx2(mix(99., 309., 3688.));
+
+        let mix = |cl: f32, cm: f32, cs: f32| {
+            _mm256_add_ps(
+                _mm256_add_ps(_mm256_mul_ps(l, set1(cl)), _mm256_mul_ps(m, set1(cm))),
+                _mm256_mul_ps(s, set1(cs)),
+            )
+        };
+        [
+            mix(0.5, 0.5, 0.),
+            mix(
+                0.5 * 6610. / 4096.,
+                0.5 * -13613. / 4096.,
+                0.5 * 7003. / 4096.,
+            ),
+            mix(17933. / 4096., -17390. / 4096., -543. / 4096.),
+        ]
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn linearize_avx2(
+        rgb: [__m256; 3],
+        transfer_characteristics: TransferCharacteristics,
+    ) -> [__m256; 3] {
+        let scale = |rgb: [__m256; 3], scale: __m256| {
+            [
+                _mm256_mul_ps(rgb[0], scale),
+                _mm256_mul_ps(rgb[1], scale),
+                _mm256_mul_ps(rgb[2], scale),
+            ]
+        };
+        match transfer_characteristics {
+            TransferCharacteristics::BT1886 => scale(
+                [
+                    pow_avx2(rgb[0], 2.4),
+                    pow_avx2(rgb[1], 2.4),
+                    pow_avx2(rgb[2], 2.4),
+                ],
+                _mm256_set1_ps(0.01),
+            ),
+            TransferCharacteristics::PQ => [
+                pq_eotf_avx2(rgb[0]),
+                pq_eotf_avx2(rgb[1]),
+                pq_eotf_avx2(rgb[2]),
+            ],
+            TransferCharacteristics::HLG => {
+                let rgb = [
+                    hlg_inverse_oetf_avx2(rgb[0]),
+                    hlg_inverse_oetf_avx2(rgb[1]),
+                    hlg_inverse_oetf_avx2(rgb[2]),
+                ];
+                let luma = _mm256_add_ps(
+                    _mm256_add_ps(
+                        _mm256_mul_ps(rgb[0], _mm256_set1_ps(HLG_LUMA[0])),
+                        _mm256_mul_ps(rgb[1], _mm256_set1_ps(HLG_LUMA[1])),
+                    ),
+                    _mm256_mul_ps(rgb[2], _mm256_set1_ps(HLG_LUMA[2])),
+                );
+                let gain = _mm256_mul_ps(_mm256_set1_ps(0.1), pow_avx2(luma, HLG_GAMMA - 1.));
+                scale(rgb, gain)
+            }
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn pq_eotf_avx2(x: __m256) -> __m256 {
+        let p = pow_avx2(x, 1. / PQ_M2);
+        let numerator = _mm256_max_ps(_mm256_sub_ps(p, _mm256_set1_ps(PQ_C1)), _mm256_setzero_ps());
+        let denominator = _mm256_sub_ps(
+            _mm256_set1_ps(PQ_C2),
+            _mm256_mul_ps(_mm256_set1_ps(PQ_C3), p),
+        );
+        pow_avx2(_mm256_div_ps(numerator, denominator), 1. / PQ_M1)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn pq_inverse_eotf_avx2(x: __m256) -> __m256 {
+        let p = pow_avx2(x, PQ_M1);
+        let numerator = _mm256_add_ps(
+            _mm256_set1_ps(PQ_C1),
+            _mm256_mul_ps(_mm256_set1_ps(PQ_C2), p),
+        );
+        let denominator =
+            _mm256_add_ps(_mm256_set1_ps(1.), _mm256_mul_ps(_mm256_set1_ps(PQ_C3), p));
+        pow_avx2(_mm256_div_ps(numerator, denominator), PQ_M2)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn hlg_inverse_oetf_avx2(x: __m256) -> __m256 {
+        let low = _mm256_mul_ps(_mm256_mul_ps(x, x), _mm256_set1_ps(1. / 3.));
+        let high = _mm256_mul_ps(
+            _mm256_add_ps(
+                exp_avx2(_mm256_mul_ps(
+                    _mm256_sub_ps(x, _mm256_set1_ps(HLG_C)),
+                    _mm256_set1_ps(1. / HLG_A),
+                )),
+                _mm256_set1_ps(HLG_B),
+            ),
+            _mm256_set1_ps(1. / 12.),
+        );
+        let select = _mm256_cmp_ps(x, _mm256_set1_ps(0.5), _CMP_GT_OS);
+        _mm256_blendv_ps(low, high, select)
+    }
+
+    /// Approximates `x.powf(y)`, returning 0 for non-positive `x`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn pow_avx2(x: __m256, y: f32) -> __m256 {
+        let positive = _mm256_cmp_ps(x, _mm256_setzero_ps(), _CMP_GT_OS);
+        let result = exp_avx2(_mm256_mul_ps(
+            ln_avx2(_mm256_max_ps(x, _mm256_set1_ps(f32::MIN_POSITIVE))),
+            _mm256_set1_ps(y),
+        ));
+        _mm256_and_ps(result, positive)
+    }
+
+    /// Approximates the natural logarithm of positive, normal `x`.
+    ///
+    /// Adapted from the Cephes library's `logf`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    #[allow(clippy::excessive_precision)]
+    unsafe fn ln_avx2(x: __m256) -> __m256 {
+        let bits = _mm256_castps_si256(x);
+        // Split x into an exponent and a mantissa in [0.5, 1)
+        let exponent = _mm256_cvtepi32_ps(_mm256_sub_epi32(
+            _mm256_srli_epi32(bits, 23),
+            _mm256_set1_epi32(0x7e),
+        ));
+        let mantissa = _mm256_or_ps(
+            _mm256_and_ps(
+                x,
+                _mm256_castsi256_ps(_mm256_set1_epi32(0x807fffffu32 as i32)),
+            ),
+            _mm256_set1_ps(0.5),
+        );
+
+        // Keep the mantissa in [sqrt(0.5), sqrt(2)) for the polynomial
+        let small = _mm256_cmp_ps(
+            mantissa,
+            _mm256_set1_ps(std::f32::consts::FRAC_1_SQRT_2),
+            _CMP_LT_OS,
+        );
+        let exponent = _mm256_sub_ps(exponent, _mm256_and_ps(_mm256_set1_ps(1.), small));
+        let m = _mm256_add_ps(
+            _mm256_sub_ps(mantissa, _mm256_set1_ps(1.)),
+            _mm256_and_ps(mantissa, small),
+        );
+
+        let z = _mm256_mul_ps(m, m);
+        let mut p = _mm256_set1_ps(7.037_683_6e-2);
+        for &c in &[
+            -1.151_461_1e-1,
+            1.167_699_9e-1,
+            -1.242_014_1e-1,
+            1.424_932_3e-1,
+            -1.666_805_8e-1,
+            2.000_071_5e-1,
+            -2.499_999_4e-1,
+            3.333_333_1e-1,
+        ] {
+            p = _mm256_add_ps(_mm256_mul_ps(p, m), _mm256_set1_ps(c));
+        }
+        let mut y = _mm256_mul_ps(_mm256_mul_ps(p, m), z);
+        y = _mm256_add_ps(y, _mm256_mul_ps(exponent, _mm256_set1_ps(-2.121_944_4e-4)));
+        y = _mm256_sub_ps(y, _mm256_mul_ps(z, _mm256_set1_ps(0.5)));
+        _mm256_add_ps(
+            _mm256_add_ps(m, y),
+            _mm256_mul_ps(exponent, _mm256_set1_ps(0.693_359_4)),
+        )
+    }
+
+    /// Approximates `e^x`.
+    ///
+    /// Adapted from the Cephes library's `expf`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    #[allow(clippy::excessive_precision)]
+    unsafe fn exp_avx2(x: __m256) -> __m256 {
+        let x = _mm256_min_ps(
+            _mm256_max_ps(x, _mm256_set1_ps(-87.3)),
+            _mm256_set1_ps(88.3),
+        );
+        // Split x into n * ln(2) + r, where |r| <= ln(2) / 2
+        let n = _mm256_round_ps(
+            _mm256_mul_ps(x, _mm256_set1_ps(std::f32::consts::LOG2_E)),
+            _MM_FROUND_TO_NEAREST_INT | _MM_FROUND_NO_EXC,
+        );
+        let r = _mm256_sub_ps(x, _mm256_mul_ps(n, _mm256_set1_ps(0.693_359_4)));
+        let r = _mm256_sub_ps(r, _mm256_mul_ps(n, _mm256_set1_ps(-2.121_944_4e-4)));
+
+        let z = _mm256_mul_ps(r, r);
+        let mut p = _mm256_set1_ps(1.987_569_1e-4);
+        for &c in &[
+            1.398_199_9e-3,
+            8.333_452e-3,
+            4.166_579_6e-2,
+            1.666_666_5e-1,
+            5.000_000_1e-1,
+        ] {
+            p = _mm256_add_ps(_mm256_mul_ps(p, r), _mm256_set1_ps(c));
+        }
+        let p = _mm256_add_ps(_mm256_add_ps(_mm256_mul_ps(p, z), r), _mm256_set1_ps(1.));
+
+        // Multiply by 2^n
+        let scale = _mm256_castsi256_ps(_mm256_slli_epi32(
+            _mm256_add_epi32(_mm256_cvtps_epi32(n), _mm256_set1_epi32(0x7f)),
+            23,
+        ));
+        _mm256_mul_ps(p, scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use std::fs::File;
+
+    fn run(name: &str, transfer_characteristics: TransferCharacteristics, simd: bool) -> f64 {
+        let mut file1 = File::open(format!("./testfiles/{}_input.y4m", name)).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open(format!("./testfiles/{}_output.y4m", name)).unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let options = VideoOptions {
+            transfer_characteristics: Some(transfer_characteristics),
+            ..Default::default()
+        };
+        let result = if simd {
+            calculate_video_deitp(&mut dec1, &mut dec2, &options)
+        } else {
+            calculate_video_deitp_nosimd(&mut dec1, &mut dec2, &options)
+        };
+        result.unwrap().aggregate
+    }
+
+    #[test]
+    fn deitp_identical_frames() {
+        use crate::video::decode::Decoder as _;
+
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        assert_eq!(0.0, calculate_frame_deitp_nosimd(&frame, &frame).unwrap());
+        assert_eq!(0.0, calculate_frame_deitp(&frame, &frame).unwrap());
+    }
+
+    #[test]
+    fn deitp_yuv420p8() {
+        for &simd in &[false, true] {
+            assert_metric_eq(
+                10.1426,
+                run("yuv420p8", TransferCharacteristics::BT1886, simd),
+            );
+            assert_metric_eq(16.7316, run("yuv420p8", TransferCharacteristics::PQ, simd));
+            assert_metric_eq(12.7116, run("yuv420p8", TransferCharacteristics::HLG, simd));
+        }
+    }
+
+    #[test]
+    fn deitp_yuv420p10() {
+        for &simd in &[false, true] {
+            assert_metric_eq(16.6585, run("yuv420p10", TransferCharacteristics::PQ, simd));
+            assert_metric_eq(
+                12.6847,
+                run("yuv420p10", TransferCharacteristics::HLG, simd),
+            );
+        }
+    }
+
+    #[test]
+    fn deitp_yuv444p8() {
+        for &simd in &[false, true] {
+            assert_metric_eq(14.2036, run("yuv444p8", TransferCharacteristics::PQ, simd));
+        }
+    }
+}
@@ -1,12 +1,26 @@
 //! Contains metrics related to video/image quality.
 
+#[cfg(feature = "decode")]
+pub mod align;
+pub mod bit_depth;
+pub mod butteraugli;
+pub mod chroma;
 pub mod ciede;
 #[cfg(feature = "decode")]
 mod decode;
+pub mod deitp;
 mod pixel;
 pub mod psnr;
 pub mod psnr_hvs;
+pub mod scale;
 pub mod ssim;
+pub mod ssimulacra2;
+#[cfg(feature = "decode")]
+pub mod suite;
+pub mod vif;
+#[cfg(feature = "vmaf")]
+pub mod vmaf;
+pub mod xpsnr;
 
 use crate::MetricsError;
 use std::error::Error;
@@ -33,7 +47,42 @@ pub struct FrameInfo<T: Pixel> {
     pub chroma_sampling: ChromaSampling,
 }
 
-impl<T: Pixel> FrameInfo<T> {
+/// Contains the data for one plane in a video frame. For chroma planes, this data is
+/// represented in the original chroma sampling. E.g. if this is a 4:2:0 video clip,
+/// the chroma planes will have half the resolution, in each dimension, of the luma
+/// plane.
+#[derive(Clone, Debug)]
+pub struct PlaneData<T: Pixel> {
+    /// The width, in pixels, of this plane.
+    pub width: usize,
+    /// The height, in pixels, of this plane.
+    pub height: usize,
+    /// A plane's pixels are contained in this `Vec`, in row-major order.
+    /// A `u8` should be used for low-bit-depth video, and `u16` for high-bit-depth.
+    pub data: Vec<T>,
+}
+
+/// A borrowed view of one video frame, for calculating metrics on frames
+/// which are already held in memory without copying them into a `FrameInfo`.
+///
+/// A `FrameRef` can be created from a `FrameInfo` using `FrameRef::from`,
+/// or constructed directly from `PlaneRef`s which point into an existing buffer.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameRef<'a, T: Pixel> {
+    /// Views of the three planes of the frame.
+    /// The indices in the array correspond to the following planes:
+    ///
+    /// - 0 - Y/Luma plane
+    /// - 1 - U/Cb plane
+    /// - 2 - V/Cr plane
+    pub planes: [PlaneRef<'a, T>; 3],
+    /// The number of bits per pixel.
+    pub bit_depth: usize,
+    /// The chroma sampling format of the video. Most videos are in 4:2:0 format.
+    pub chroma_sampling: ChromaSampling,
+}
+
+impl<'a, T: Pixel> FrameRef<'a, T> {
     pub(crate) fn can_compare(&self, other: &Self) -> Result<(), MetricsError> {
         if self.bit_depth != other.bit_depth {
             return Err(MetricsError::InputMismatch {
@@ -58,22 +107,108 @@ impl<T: Pixel> FrameInfo<T> {
     }
 }
 
-/// Contains the data for one plane in a video frame. For chroma planes, this data is
-/// represented in the original chroma sampling. E.g. if this is a 4:2:0 video clip,
-/// the chroma planes will have half the resolution, in each dimension, of the luma
-/// plane.
-#[derive(Clone, Debug)]
-pub struct PlaneData<T: Pixel> {
+impl<'a, T: Pixel> From<&'a FrameInfo<T>> for FrameRef<'a, T> {
+    fn from(frame: &'a FrameInfo<T>) -> Self {
+        FrameRef {
+            planes: [
+                PlaneRef::from(&frame.planes[0]),
+                PlaneRef::from(&frame.planes[1]),
+                PlaneRef::from(&frame.planes[2]),
+            ],
+            bit_depth: frame.bit_depth,
+            chroma_sampling: frame.chroma_sampling,
+        }
+    }
+}
+
+/// A borrowed view of one plane in a video frame.
+///
+/// Rows of the plane do not need to be tightly packed. Each row begins
+/// `stride` pixels after the previous one, so padded buffers, such as those
+/// used by encoders and decoders, can be referenced without copying.
+#[derive(Clone, Copy, Debug)]
+pub struct PlaneRef<'a, T: Pixel> {
+    data: &'a [T],
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl<'a, T: Pixel> PlaneRef<'a, T> {
+    /// Creates a view of a plane with the given dimensions.
+    ///
+    /// `data` must begin with the top-left visible pixel of the plane.
+    /// `stride` is the distance, in pixels, between the start of each row.
+    ///
+    /// Returns an error if `stride` is smaller than `width`,
+    /// or if `data` is too short to contain `height` rows.
+    pub fn new(
+        data: &'a [T],
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Result<Self, MetricsError> {
+        if stride < width {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Plane stride must not be smaller than its width",
+            });
+        }
+        if height > 0 && data.len() < (height - 1) * stride + width {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Plane data is too short for its dimensions",
+            });
+        }
+        Ok(PlaneRef {
+            data,
+            width,
+            height,
+            stride,
+        })
+    }
+
     /// The width, in pixels, of this plane.
-    pub width: usize,
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
     /// The height, in pixels, of this plane.
-    pub height: usize,
-    /// A plane's pixels are contained in this `Vec`, in row-major order.
-    /// A `u8` should be used for low-bit-depth video, and `u16` for high-bit-depth.
-    pub data: Vec<T>,
-}
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The distance, in pixels, between the start of each row.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The visible pixels of row `y`.
+    ///
+    /// Panics if `y` is not less than the height of the plane.
+    pub fn row(&self, y: usize) -> &'a [T] {
+        assert!(y < self.height);
+        let start = y * self.stride;
+        &self.data[start..start + self.width]
+    }
+
+    /// An iterator over the visible pixels of each row, from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> {
+        let plane = *self;
+        (0..self.height).map(move |y| plane.row(y))
+    }
+
+    /// A view of the rectangle of this plane with the given top-left corner and size.
+    ///
+    /// Panics if the rectangle does not lie within the plane.
+    pub(crate) fn region(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        assert!(x + width <= self.width && y + height <= self.height);
+        PlaneRef {
+            data: &self.data[(y * self.stride + x).min(self.data.len())..],
+            width,
+            height,
+            stride: self.stride,
+        }
+    }
 
-impl<T: Pixel> PlaneData<T> {
     pub(crate) fn can_compare(&self, other: &Self) -> Result<(), MetricsError> {
         if self.width != other.width || self.height != other.height {
             return Err(MetricsError::InputMismatch {
@@ -85,6 +220,17 @@ impl<T: Pixel> PlaneData<T> {
     }
 }
 
+impl<'a, T: Pixel> From<&'a PlaneData<T>> for PlaneRef<'a, T> {
+    fn from(plane: &'a PlaneData<T>) -> Self {
+        PlaneRef {
+            data: &plane.data,
+            width: plane.width,
+            height: plane.height,
+            stride: plane.width,
+        }
+    }
+}
+
 /// Available chroma sampling formats.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ChromaSampling {
@@ -122,7 +268,6 @@ impl ChromaSampling {
     }
 
     /// Calculates the size of a chroma plane for this sampling type, given the luma plane dimensions.
-    #[cfg(feature = "decode")]
     pub(crate) fn get_chroma_dimensions(
         self,
         luma_width: usize,
@@ -170,8 +315,70 @@ impl Default for ChromaSamplePosition {
     }
 }
 
+/// The matrix coefficients used to convert between YUV and RGB.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum MatrixCoefficients {
+    /// ITU-R BT.601, used by most standard definition video.
+    BT601,
+    /// ITU-R BT.709, used by most high definition video.
+    #[default]
+    BT709,
+    /// ITU-R BT.2020 non-constant luminance, used by most UHD and HDR video.
+    BT2020NCL,
+}
+
+impl MatrixCoefficients {
+    /// The luma weights of the red and blue components, `(Kr, Kb)`.
+    pub(crate) fn luma_weights(self) -> (f32, f32) {
+        match self {
+            MatrixCoefficients::BT601 => (0.299, 0.114),
+            MatrixCoefficients::BT709 => (0.2126, 0.0722),
+            MatrixCoefficients::BT2020NCL => (0.2627, 0.0593),
+        }
+    }
+}
+
+/// The range of sample values used by a video.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ColorRange {
+    /// Samples use the "studio swing" range, e.g. 16-235 for 8-bit luma.
+    #[default]
+    Limited,
+    /// Samples use the full range of values, as in JPEG images.
+    Full,
+}
+
+/// The transfer function which maps sample values to light output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TransferCharacteristics {
+    /// The ITU-R BT.1886 gamma curve, used by standard dynamic range video.
+    #[default]
+    BT1886,
+    /// The perceptual quantizer from SMPTE ST 2084, used by HDR10 video.
+    PQ,
+    /// Hybrid log-gamma from ITU-R BT.2100.
+    HLG,
+}
+
+/// Describes how the samples of a video map to colors,
+/// for metrics which convert frames to RGB.
+///
+/// The default is BT.709 limited range, with the BT.1886 transfer function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct ColorDescription {
+    /// The matrix coefficients used to convert between YUV and RGB.
+    pub matrix_coefficients: MatrixCoefficients,
+    /// The range of sample values.
+    pub range: ColorRange,
+    /// The transfer function, which is used by metrics that work in linear light.
+    pub transfer_characteristics: TransferCharacteristics,
+}
+
 /// Certain metrics return a value per plane. This struct contains the output
 /// for those metrics per plane, as well as a weighted average of the planes.
+///
+/// For 4:0:0 video, which has no chroma planes, `u` and `v` are `NaN`
+/// and `avg` is the luma score.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PlanarMetrics {
@@ -185,74 +392,590 @@ pub struct PlanarMetrics {
     pub avg: f64,
 }
 
-trait VideoMetric {
-    type FrameResult;
+/// The result of running a metric over an entire video. This contains the
+/// aggregate score for the video, as well as the score for each individual frame.
+///
+/// `T` is the type of score produced by the metric, e.g. `PlanarMetrics` for PSNR
+/// or `f64` for CIEDE2000.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VideoMetricResults<T> {
+    /// The aggregate score for all compared frames.
+    pub aggregate: T,
+    /// The score for each compared frame, in display order.
+    /// These use the same scale as the aggregate score.
+    pub frames: Vec<T>,
+    /// The number of frames in each input video.
+    ///
+    /// This is only known if the videos were compared using a
+    /// [`LengthPolicy`](enum.LengthPolicy.html) other than `Truncate`.
+    pub frame_counts: Option<FrameCounts>,
+    /// Which frames of each video were compared to each other.
+    ///
+    /// This is only set if the videos were compared with `VideoOptions::resync_lookahead`.
+    pub resync: Option<ResyncReport>,
+}
+
+/// The number of frames in each of the two input videos.
+///
+/// Any frames which were skipped using `VideoOptions` are not included,
+/// and if a frame limit was used, these are capped at that limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FrameCounts {
+    /// The number of frames in the first video.
+    pub input1: usize,
+    /// The number of frames in the second video.
+    pub input2: usize,
+}
+
+/// How the frames of two videos were matched when re-syncing them.
+///
+/// Frame indices count from the start of each video, including any frames
+/// which were skipped using `VideoOptions`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResyncReport {
+    /// The index of the frame in the first and second video for each compared pair.
+    pub pairs: Vec<(usize, usize)>,
+    /// Frames of the first video which were not matched by any frame of the second video.
+    pub dropped: Vec<usize>,
+    /// Frames of the second video which repeat the previously matched frame
+    /// of the first video.
+    pub duplicated: Vec<usize>,
+}
+
+/// Information about which frames were compared, which is passed to
+/// [`VideoMetric::aggregate_frame_results`](trait.VideoMetric.html#tymethod.aggregate_frame_results)
+/// and included in each metric's results.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComparisonInfo {
+    /// The number of frames in each input video, if known.
+    pub frame_counts: Option<FrameCounts>,
+    /// How the frames of the two videos were matched, if they were re-synced.
+    pub resync: Option<ResyncReport>,
+}
+
+/// Options controlling which frames of two videos are compared.
+///
+/// Frames which are skipped are read from the decoder and discarded,
+/// without calculating any metrics on them.
+#[cfg(feature = "decode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoOptions {
+    /// The number of frames to skip at the start of the first video.
+    ///
+    /// This can be used to align videos where one has leading frames
+    /// which the other does not.
+    pub offset1: usize,
+    /// The number of frames to skip at the start of the second video.
+    pub offset2: usize,
+    /// The index of the first frame to compare, after applying `offset1` and `offset2`.
+    pub start_frame: usize,
+    /// If set, the comparison ends before this frame, after applying `offset1` and `offset2`.
+    /// This must not be less than `start_frame`.
+    pub end_frame: Option<usize>,
+    /// If set, at most `frame_limit` frames are compared, beginning at `start_frame`.
+    /// In other words, the last frame which may be compared is
+    /// `start_frame + frame_limit - 1`.
+    ///
+    /// If both `end_frame` and `frame_limit` are set, the comparison ends at whichever
+    /// is reached first.
+    pub frame_limit: Option<usize>,
+    /// What to do if the videos have a different number of frames.
+    ///
+    /// This is ignored when re-syncing, since frames are expected to be
+    /// dropped or duplicated.
+    pub length_policy: LengthPolicy,
+    /// If set, each frame of the second video is matched to the frame of the first
+    /// video with the highest PSNR, to handle frames which were dropped or duplicated
+    /// in the middle of the second video.
+    ///
+    /// Candidates are the previously matched frame, which detects a duplicated frame,
+    /// and the next `resync_lookahead + 1` frames, which detects up to
+    /// `resync_lookahead` consecutive dropped frames.
+    /// The matches are reported in the result's `resync` field.
+    pub resync_lookahead: Option<usize>,
+    /// If set, frames are rescaled before they are compared,
+    /// which allows videos with different resolutions to be compared.
+    pub scale: Option<scale::ScaleOptions>,
+    /// If set, videos with different bit depths are converted to a common bit depth
+    /// before they are compared.
+    pub bit_depth_normalization: Option<bit_depth::BitDepthNormalization>,
+    /// If set, videos with different chroma sampling are resampled to a common
+    /// chroma sampling before they are compared.
+    pub chroma_resampling: Option<chroma::ChromaResampling>,
+    /// If set, overrides the matrix coefficients reported by the decoders,
+    /// for metrics which convert frames to RGB.
+    pub matrix_coefficients: Option<MatrixCoefficients>,
+    /// If set, overrides the color range reported by the decoders,
+    /// for metrics which convert frames to RGB.
+    pub color_range: Option<ColorRange>,
+    /// If set, overrides the transfer function reported by the decoders,
+    /// for metrics which work in linear light.
+    pub transfer_characteristics: Option<TransferCharacteristics>,
+}
+
+#[cfg(feature = "decode")]
+impl VideoOptions {
+    /// The maximum number of frames to compare, from `end_frame` and `frame_limit`.
+    fn max_frames(&self) -> Option<usize> {
+        let end_limit = self
+            .end_frame
+            .map(|end_frame| end_frame.saturating_sub(self.start_frame));
+        match (end_limit, self.frame_limit) {
+            (Some(end_limit), Some(frame_limit)) => Some(end_limit.min(frame_limit)),
+            (end_limit, frame_limit) => end_limit.or(frame_limit),
+        }
+    }
+
+    /// The color description of a video, with any overrides from these options applied.
+    pub(crate) fn color_description(&self, details: &VideoDetails) -> ColorDescription {
+        let description = details.color_description;
+        ColorDescription {
+            matrix_coefficients: self
+                .matrix_coefficients
+                .unwrap_or(description.matrix_coefficients),
+            range: self.color_range.unwrap_or(description.range),
+            transfer_characteristics: self
+                .transfer_characteristics
+                .unwrap_or(description.transfer_characteristics),
+        }
+    }
+}
+
+/// How to handle two videos which have a different number of frames.
+#[cfg(feature = "decode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthPolicy {
+    /// Compare frames up to the end of the shorter video,
+    /// and ignore any remaining frames in the longer video.
+    #[default]
+    Truncate,
+    /// Compare frames up to the end of the shorter video,
+    /// then read the rest of the longer video to report the length of each video
+    /// in the result's `frame_counts`.
+    Warn,
+    /// Return an error if the videos have a different number of frames.
+    Error,
+}
+
+/// Per-plane scores for a single frame, before the planes have been weighted
+/// against each other. Used by metrics where the weighting depends on whether
+/// the result is reported per-frame or per-video.
+#[derive(Debug, Clone, Copy)]
+pub struct UnweightedPlanarMetrics {
+    pub(crate) y: f64,
+    pub(crate) u: f64,
+    pub(crate) v: f64,
+    /// The relative impact of chroma planes compared to luma.
+    pub(crate) cweight: f64,
+}
+
+/// Combines per-plane scores, with the chroma scores weighted by `cweight`.
+/// Chroma is left out when it has no weight, as for 4:0:0 video,
+/// whose chroma scores are `NaN`.
+pub(crate) fn weighted_plane_sum(y: f64, u: f64, v: f64, cweight: f64) -> f64 {
+    if cweight > 0.0 {
+        y + cweight * (u + v)
+    } else {
+        y
+    }
+}
+
+/// A metric which can be calculated over a video, one frame at a time.
+///
+/// This is implemented by each of the metrics in this crate, e.g.
+/// [`Psnr`](psnr/struct.Psnr.html) or [`Ssim`](ssim/struct.Ssim.html).
+/// For most uses, the `calculate_video_*` functions or a
+/// [`VideoMetricAccumulator`](struct.VideoMetricAccumulator.html) are more convenient
+/// than using this trait directly.
+pub trait VideoMetric: Sync {
+    /// The intermediate result calculated for a single frame.
+    type FrameResult: Send;
+    /// The final result calculated for the whole video.
     type VideoResult;
 
-    /// Generic method for internal use that processes multiple frames from a video
-    /// into an aggregate metric.
+    /// Processes multiple frames from a video into an aggregate metric.
     ///
-    /// `frame_fn` is the function to calculate metrics on one frame of the video.
-    /// `acc_fn` is the accumulator function to calculate the aggregate metric.
+    /// By default, this will return at the end of the shorter of the two clips,
+    /// comparing any frames up to that point.
+    /// See [`VideoOptions`](struct.VideoOptions.html) for other options.
+    ///
+    /// Returns an error if either decoder fails for any reason
+    /// other than reaching the end of the video.
     #[cfg(feature = "decode")]
     fn process_video<D: Decoder>(
-        &mut self,
+        &self,
         decoder1: &mut D,
         decoder2: &mut D,
-        frame_limit: Option<usize>,
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        if decoder1.get_bit_depth() != decoder2.get_bit_depth() {
-            return Err(Box::new(MetricsError::InputMismatch {
-                reason: "Bit depths do not match",
-            }));
-        }
-
-        let mut metrics = Vec::with_capacity(frame_limit.unwrap_or(0));
-        let mut frame_no = 0;
-        while frame_limit.map(|limit| limit > frame_no).unwrap_or(true) {
-            if decoder1.get_bit_depth() > 8 {
-                let frame1 = decoder1.read_video_frame::<u16>();
-                let frame2 = decoder2.read_video_frame::<u16>();
-                if let Ok(frame1) = frame1 {
-                    if let Ok(frame2) = frame2 {
-                        metrics.push(self.process_frame(&frame1, &frame2)?);
-                        frame_no += 1;
-                        continue;
-                    }
-                }
-            } else {
-                let frame1 = decoder1.read_video_frame::<u8>();
-                let frame2 = decoder2.read_video_frame::<u8>();
-                if let Ok(frame1) = frame1 {
-                    if let Ok(frame2) = frame2 {
-                        metrics.push(self.process_frame(&frame1, &frame2)?);
-                        frame_no += 1;
-                        continue;
-                    }
-                }
+        options: &VideoOptions,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        decode_and_process_video(self, decoder1, decoder2, options)
+    }
+
+    /// Processes a video using the given pixel type.
+    #[doc(hidden)]
+    #[cfg(feature = "decode")]
+    fn process_video_with<T: Pixel, D: Decoder>(
+        &self,
+        decoder1: &mut D,
+        decoder2: &mut D,
+        options: &VideoOptions,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        if options
+            .end_frame
+            .map_or(false, |end_frame| end_frame < options.start_frame)
+        {
+            return Err(MetricsError::InvalidOptions {
+                reason: "The end frame must not be before the start frame",
             }
-            // At end of video
-            break;
+            .into());
         }
-        if frame_no == 0 {
+
+        let mut reader = FramePairReader::<T, D>::new(decoder1, decoder2, options);
+        let metrics = self.process_video_frames(&mut reader)?;
+        if metrics.is_empty() {
             return Err(MetricsError::UnsupportedInput {
                 reason: "No readable frames found in one or more input files",
             }
             .into());
         }
 
-        self.aggregate_frame_results(&metrics)
+        let info = ComparisonInfo {
+            frame_counts: reader.frame_counts,
+            resync: reader.resync.map(|resync| resync.report),
+        };
+        self.aggregate_frame_results(&metrics, &info)
+    }
+
+    /// Reads and processes frames until the end of the shorter video,
+    /// or until the frame limit is reached.
+    #[doc(hidden)]
+    #[cfg(all(feature = "decode", not(feature = "parallel")))]
+    fn process_video_frames<T: Pixel, D: Decoder>(
+        &self,
+        reader: &mut FramePairReader<T, D>,
+    ) -> Result<Vec<Self::FrameResult>, Box<dyn Error + Send + Sync>> {
+        let mut metrics = Vec::with_capacity(reader.options.max_frames().unwrap_or(0));
+        while let Some((frame1, frame2)) = reader.read_pair()? {
+            metrics.push(self.process_frame(&FrameRef::from(&frame1), &FrameRef::from(&frame2))?);
+        }
+        Ok(metrics)
+    }
+
+    /// Reads and processes frames until the end of the shorter video,
+    /// or until the frame limit is reached.
+    ///
+    /// Frames are decoded in batches on the calling thread, while the previous
+    /// batch is processed in the current rayon thread pool.
+    #[doc(hidden)]
+    #[cfg(all(feature = "decode", feature = "parallel"))]
+    fn process_video_frames<T: Pixel, D: Decoder>(
+        &self,
+        reader: &mut FramePairReader<T, D>,
+    ) -> Result<Vec<Self::FrameResult>, Box<dyn Error + Send + Sync>> {
+        let batch_size = rayon::current_num_threads();
+        let mut metrics = Vec::with_capacity(reader.options.max_frames().unwrap_or(0));
+        let mut batch = reader.read_batch(batch_size)?;
+        while !batch.is_empty() {
+            let mut results: Vec<Option<Result<Self::FrameResult, _>>> =
+                batch.iter().map(|_| None).collect();
+            let next_batch = rayon::in_place_scope(|scope| {
+                for ((frame1, frame2), result) in batch.iter().zip(results.iter_mut()) {
+                    scope.spawn(move |_| {
+                        *result = Some(
+                            self.process_frame(&FrameRef::from(frame1), &FrameRef::from(frame2)),
+                        );
+                    });
+                }
+                reader.read_batch(batch_size)
+            });
+            for result in results {
+                metrics.push(result.unwrap()?);
+            }
+            batch = next_batch?;
+        }
+        Ok(metrics)
     }
 
+    /// Calculates the intermediate result for one pair of frames.
     fn process_frame<T: Pixel>(
-        &mut self,
-        frame1: &FrameInfo<T>,
-        frame2: &FrameInfo<T>,
-    ) -> Result<Self::FrameResult, Box<dyn Error>>;
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>>;
 
-    #[cfg(feature = "decode")]
+    /// Combines the intermediate results of each frame into the final result.
+    ///
+    /// `metrics` must contain at least one frame result.
+    /// `info` describes which frames were compared, and should be included in the result.
     fn aggregate_frame_results(
         &self,
         metrics: &[Self::FrameResult],
-    ) -> Result<Self::VideoResult, Box<dyn Error>>;
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>>;
+}
+
+/// The default implementation of `VideoMetric::process_video`,
+/// for metrics which need to do some setup before calling it.
+#[cfg(feature = "decode")]
+pub(crate) fn decode_and_process_video<M: VideoMetric + ?Sized, D: Decoder>(
+    metric: &M,
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &VideoOptions,
+) -> Result<M::VideoResult, Box<dyn Error + Send + Sync>> {
+    // Frames are passed through unchanged if these conversions are disabled
+    let (mut decoder1, mut decoder2) =
+        bit_depth::normalize_decoders(decoder1, decoder2, options.bit_depth_normalization);
+    let (mut decoder1, mut decoder2) =
+        chroma::resample_chroma_decoders(&mut decoder1, &mut decoder2, options.chroma_resampling);
+    let (mut decoder1, mut decoder2) =
+        scale::scale_decoders(&mut decoder1, &mut decoder2, options.scale.as_ref());
+    let details1 = decoder1.get_video_details();
+    let details2 = decoder2.get_video_details();
+    details1.can_compare(&details2)?;
+
+    if details1.bit_depth > 8 {
+        metric.process_video_with::<u16, _>(&mut decoder1, &mut decoder2, options)
+    } else {
+        metric.process_video_with::<u8, _>(&mut decoder1, &mut decoder2, options)
+    }
+}
+
+/// Calculates a metric incrementally, as frames become available.
+///
+/// This is useful when frames are produced in memory one at a time,
+/// rather than read from a [`Decoder`](trait.Decoder.html).
+/// The final result is identical to the one returned by the corresponding
+/// `calculate_video_*` function.
+///
+/// ```ignore
+/// use av_metrics::video::ssim::Ssim;
+/// use av_metrics::video::VideoMetricAccumulator;
+///
+/// let mut accumulator = VideoMetricAccumulator::new(Ssim);
+/// for (frame1, frame2) in frames {
+///     accumulator.push(&frame1, &frame2)?;
+/// }
+/// let result = accumulator.finish()?;
+/// ```
+pub struct VideoMetricAccumulator<M: VideoMetric> {
+    metric: M,
+    frame_results: Vec<M::FrameResult>,
+}
+
+impl<M: VideoMetric> VideoMetricAccumulator<M> {
+    /// Creates an accumulator for the given metric.
+    pub fn new(metric: M) -> Self {
+        VideoMetricAccumulator {
+            metric,
+            frame_results: Vec::new(),
+        }
+    }
+
+    /// Calculates the metric for one pair of frames and adds it to the running result.
+    ///
+    /// The frames may be either `&FrameInfo`s or borrowed `FrameRef` views.
+    pub fn push<'a, T: Pixel>(
+        &mut self,
+        frame1: impl Into<FrameRef<'a, T>>,
+        frame2: impl Into<FrameRef<'a, T>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let result = self.metric.process_frame(&frame1.into(), &frame2.into())?;
+        self.frame_results.push(result);
+        Ok(())
+    }
+
+    /// The number of frame pairs which have been pushed so far.
+    pub fn frame_count(&self) -> usize {
+        self.frame_results.len()
+    }
+
+    /// Calculates the final result for all of the frames which were pushed.
+    ///
+    /// Returns an error if no frames were pushed.
+    pub fn finish(self) -> Result<M::VideoResult, Box<dyn Error + Send + Sync>> {
+        if self.frame_results.is_empty() {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "No frames were provided to the accumulator",
+            }
+            .into());
+        }
+        self.metric
+            .aggregate_frame_results(&self.frame_results, &ComparisonInfo::default())
+    }
+}
+
+/// A frame from each of the two videos being compared.
+#[cfg(feature = "decode")]
+type FramePair<T> = (FrameInfo<T>, FrameInfo<T>);
+
+/// Reads pairs of frames from two decoders, according to the given `VideoOptions`.
+#[doc(hidden)]
+#[cfg(feature = "decode")]
+pub struct FramePairReader<'a, T: Pixel, D: Decoder> {
+    decoder1: &'a mut D,
+    decoder2: &'a mut D,
+    options: &'a VideoOptions,
+    frames_read: usize,
+    frame_counts: Option<FrameCounts>,
+    resync: Option<align::Resync<T>>,
+    finished: bool,
+}
+
+#[cfg(feature = "decode")]
+impl<'a, T: Pixel, D: Decoder> FramePairReader<'a, T, D> {
+    fn new(decoder1: &'a mut D, decoder2: &'a mut D, options: &'a VideoOptions) -> Self {
+        FramePairReader {
+            decoder1,
+            decoder2,
+            options,
+            frames_read: 0,
+            frame_counts: None,
+            resync: options.resync_lookahead.map(|lookahead| {
+                align::Resync::new(
+                    lookahead,
+                    options.offset1 + options.start_frame,
+                    options.offset2 + options.start_frame,
+                )
+            }),
+            finished: false,
+        }
+    }
+
+    /// Reads and discards the frames before `start_frame` in each video.
+    fn skip_frames(&mut self) -> Result<(), MetricsError> {
+        let options = self.options;
+        for _ in 0..(options.offset1 + options.start_frame) {
+            if read_frame::<T, _>(self.decoder1)?.is_none() {
+                break;
+            }
+        }
+        for _ in 0..(options.offset2 + options.start_frame) {
+            if read_frame::<T, _>(self.decoder2)?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the next frame from each decoder.
+    /// Returns `None` once the end of either video or the frame limit is reached,
+    /// and for every call after that.
+    fn read_pair(&mut self) -> Result<Option<FramePair<T>>, MetricsError> {
+        if self.finished {
+            return Ok(None);
+        }
+        if self.frames_read == 0 {
+            self.skip_frames()?;
+        }
+        let frames = self.read_next_pair()?;
+        self.finished = frames.is_none();
+        Ok(frames)
+    }
+
+    fn read_next_pair(&mut self) -> Result<Option<FramePair<T>>, MetricsError> {
+        if self.is_at_limit(self.frames_read) {
+            self.set_frame_counts(self.frames_read, self.frames_read);
+            return Ok(None);
+        }
+        if let Some(resync) = self.resync.as_mut() {
+            let frames = resync.read_pair(self.decoder1, self.decoder2)?;
+            if frames.is_some() {
+                self.frames_read += 1;
+            }
+            return Ok(frames);
+        }
+        let frame1 = read_frame::<T, _>(self.decoder1)?;
+        if frame1.is_none() && self.options.length_policy == LengthPolicy::Truncate {
+            return Ok(None);
+        }
+        let frame2 = read_frame::<T, _>(self.decoder2)?;
+        match (frame1, frame2) {
+            (Some(frame1), Some(frame2)) => {
+                self.frames_read += 1;
+                Ok(Some((frame1, frame2)))
+            }
+            (None, None) => {
+                self.set_frame_counts(self.frames_read, self.frames_read);
+                Ok(None)
+            }
+            (frame1, _) => {
+                self.handle_length_mismatch(frame1.is_some())?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads up to `batch_size` frames from each decoder, stopping early at the
+    /// end of either video or once the frame limit is reached.
+    #[cfg(feature = "parallel")]
+    fn read_batch(&mut self, batch_size: usize) -> Result<Vec<FramePair<T>>, MetricsError> {
+        let mut batch = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            match self.read_pair()? {
+                Some(frames) => batch.push(frames),
+                // At end of video
+                None => break,
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Called when one video has ended, while the other has at least one more frame.
+    /// `video1_is_longer` indicates which video still has frames remaining.
+    fn handle_length_mismatch(&mut self, video1_is_longer: bool) -> Result<(), MetricsError> {
+        match self.options.length_policy {
+            LengthPolicy::Truncate => Ok(()),
+            LengthPolicy::Error => Err(MetricsError::InputMismatch {
+                reason: "Videos have a different number of frames",
+            }),
+            LengthPolicy::Warn => {
+                let max_frames = self.options.max_frames();
+                let decoder = if video1_is_longer {
+                    &mut *self.decoder1
+                } else {
+                    &mut *self.decoder2
+                };
+                // One frame was already read from the longer video
+                let mut longer_count = self.frames_read + 1;
+                while max_frames.map_or(true, |limit| longer_count < limit)
+                    && read_frame::<T, _>(decoder)?.is_some()
+                {
+                    longer_count += 1;
+                }
+                if video1_is_longer {
+                    self.set_frame_counts(longer_count, self.frames_read);
+                } else {
+                    self.set_frame_counts(self.frames_read, longer_count);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn is_at_limit(&self, frame_count: usize) -> bool {
+        self.options
+            .max_frames()
+            .map_or(false, |limit| frame_count >= limit)
+    }
+
+    fn set_frame_counts(&mut self, input1: usize, input2: usize) {
+        if self.options.length_policy != LengthPolicy::Truncate && self.resync.is_none() {
+            self.frame_counts = Some(FrameCounts { input1, input2 });
+        }
+    }
+}
+
+/// Reads the next frame from a decoder.
+/// Returns `None` once the end of the video is reached.
+#[cfg(feature = "decode")]
+fn read_frame<T: Pixel, D: Decoder>(decoder: &mut D) -> Result<Option<FrameInfo<T>>, MetricsError> {
+    match decoder.read_video_frame::<T>() {
+        Ok(frame) => Ok(Some(frame)),
+        Err(DecodeError::EndOfStream) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
 }
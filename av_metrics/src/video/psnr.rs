@@ -8,7 +8,12 @@
 use crate::video::decode::Decoder;
 use crate::video::pixel::CastFromPrimitive;
 use crate::video::pixel::Pixel;
-use crate::video::{FrameInfo, PlanarMetrics, PlaneData, VideoMetric};
+#[cfg(feature = "decode")]
+use crate::video::VideoOptions;
+use crate::video::{
+    ComparisonInfo, FrameRef, PlanarMetrics, PlaneRef, VideoMetric, VideoMetricResults,
+};
+use crate::MetricsError;
 use std::error::Error;
 
 /// Calculates the PSNR for two videos. Higher is better.
@@ -21,9 +26,9 @@ use std::error::Error;
 pub fn calculate_video_psnr<D: Decoder>(
     decoder1: &mut D,
     decoder2: &mut D,
-    frame_limit: Option<usize>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    let metrics = Psnr.process_video(decoder1, decoder2, frame_limit)?;
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<PlanarMetrics>, Box<dyn Error + Send + Sync>> {
+    let metrics = Psnr.process_video(decoder1, decoder2, options)?;
     Ok(metrics.psnr)
 }
 
@@ -37,9 +42,9 @@ pub fn calculate_video_psnr<D: Decoder>(
 pub fn calculate_video_apsnr<D: Decoder>(
     decoder1: &mut D,
     decoder2: &mut D,
-    frame_limit: Option<usize>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    let metrics = Psnr.process_video(decoder1, decoder2, frame_limit)?;
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<PlanarMetrics>, Box<dyn Error + Send + Sync>> {
+    let metrics = Psnr.process_video(decoder1, decoder2, options)?;
     Ok(metrics.apsnr)
 }
 
@@ -49,50 +54,49 @@ pub fn calculate_video_apsnr<D: Decoder>(
 /// from e.g. all black frames, which would
 /// otherwise show a PSNR of infinity.
 #[inline]
-pub fn calculate_frame_psnr<T: Pixel>(
-    frame1: &FrameInfo<T>,
-    frame2: &FrameInfo<T>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    let metrics = Psnr.process_frame(frame1, frame2)?;
-    Ok(PlanarMetrics {
-        y: calculate_psnr(metrics[0]),
-        u: calculate_psnr(metrics[1]),
-        v: calculate_psnr(metrics[2]),
-        avg: calculate_summed_psnr(&metrics),
-    })
+pub fn calculate_frame_psnr<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<PlanarMetrics, Box<dyn Error + Send + Sync>> {
+    let metrics = Psnr.process_frame(&frame1.into(), &frame2.into())?;
+    Ok(calculate_frame_result(&metrics))
 }
 
-#[derive(Debug, Clone, Copy)]
-struct PsnrResults {
-    psnr: PlanarMetrics,
-    apsnr: PlanarMetrics,
+/// The results of the PSNR metric over a video.
+///
+/// Both PSNR and APSNR are calculated from the same per-frame data,
+/// so they are returned together.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PsnrResults {
+    /// PSNR, calculated from the mean squared error over all frames.
+    pub psnr: VideoMetricResults<PlanarMetrics>,
+    /// APSNR, the average of the PSNR of each frame.
+    pub apsnr: VideoMetricResults<PlanarMetrics>,
 }
 
-struct Psnr;
+/// The PSNR and APSNR metrics, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Psnr;
 
 impl VideoMetric for Psnr {
     type FrameResult = [PsnrMetrics; 3];
     type VideoResult = PsnrResults;
 
     fn process_frame<T: Pixel>(
-        &mut self,
-        frame1: &FrameInfo<T>,
-        frame2: &FrameInfo<T>,
-    ) -> Result<Self::FrameResult, Box<dyn Error>> {
-        frame1.can_compare(&frame2)?;
-
-        let bit_depth = frame1.bit_depth;
-        let y = calculate_plane_psnr_metrics(&frame1.planes[0], &frame2.planes[0], bit_depth);
-        let u = calculate_plane_psnr_metrics(&frame1.planes[1], &frame2.planes[1], bit_depth);
-        let v = calculate_plane_psnr_metrics(&frame1.planes[2], &frame2.planes[2], bit_depth);
-        Ok([y, u, v])
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        Ok(calculate_frame_psnr_metrics(frame1, frame2)?)
     }
 
-    #[cfg(feature = "decode")]
     fn aggregate_frame_results(
         &self,
         metrics: &[Self::FrameResult],
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
         let psnr = PlanarMetrics {
             y: calculate_summed_psnr(&metrics.iter().map(|m| m[0]).collect::<Vec<_>>()),
             u: calculate_summed_psnr(&metrics.iter().map(|m| m[1]).collect::<Vec<_>>()),
@@ -109,18 +113,70 @@ impl VideoMetric for Psnr {
                 .sum::<f64>()
                 / metrics.len() as f64,
         };
-        Ok(PsnrResults { psnr, apsnr })
+        // PSNR and APSNR are identical when looking at a single frame
+        let frames = metrics
+            .iter()
+            .map(calculate_frame_result)
+            .collect::<Vec<_>>();
+        Ok(PsnrResults {
+            psnr: VideoMetricResults {
+                aggregate: psnr,
+                frames: frames.clone(),
+                frame_counts: info.frame_counts,
+                resync: info.resync.clone(),
+            },
+            apsnr: VideoMetricResults {
+                aggregate: apsnr,
+                frames,
+                frame_counts: info.frame_counts,
+                resync: info.resync.clone(),
+            },
+        })
     }
 }
 
+/// The intermediate PSNR data for a single plane of a frame.
 #[derive(Debug, Clone, Copy, Default)]
-struct PsnrMetrics {
-    sq_err: f64,
-    n_pixels: usize,
-    sample_max: usize,
+pub struct PsnrMetrics {
+    pub(crate) sq_err: f64,
+    pub(crate) n_pixels: usize,
+    pub(crate) sample_max: usize,
+}
+
+fn calculate_frame_psnr_metrics<T: Pixel>(
+    frame1: &FrameRef<T>,
+    frame2: &FrameRef<T>,
+) -> Result<[PsnrMetrics; 3], MetricsError> {
+    frame1.can_compare(frame2)?;
+
+    let bit_depth = frame1.bit_depth;
+    let y = calculate_plane_psnr_metrics(&frame1.planes[0], &frame2.planes[0], bit_depth);
+    let u = calculate_plane_psnr_metrics(&frame1.planes[1], &frame2.planes[1], bit_depth);
+    let v = calculate_plane_psnr_metrics(&frame1.planes[2], &frame2.planes[2], bit_depth);
+    Ok([y, u, v])
+}
+
+/// Calculates the PSNR of all planes of two frames combined.
+#[cfg(feature = "decode")]
+pub(crate) fn calculate_frame_psnr_avg<T: Pixel>(
+    frame1: &FrameRef<T>,
+    frame2: &FrameRef<T>,
+) -> Result<f64, MetricsError> {
+    Ok(calculate_summed_psnr(&calculate_frame_psnr_metrics(
+        frame1, frame2,
+    )?))
 }
 
-fn calculate_summed_psnr(metrics: &[PsnrMetrics]) -> f64 {
+pub(crate) fn calculate_frame_result(metrics: &[PsnrMetrics; 3]) -> PlanarMetrics {
+    PlanarMetrics {
+        y: calculate_psnr(metrics[0]),
+        u: calculate_psnr(metrics[1]),
+        v: calculate_psnr(metrics[2]),
+        avg: calculate_summed_psnr(metrics),
+    }
+}
+
+pub(crate) fn calculate_summed_psnr(metrics: &[PsnrMetrics]) -> f64 {
     calculate_psnr(
         metrics
             .iter()
@@ -134,21 +190,25 @@ fn calculate_summed_psnr(metrics: &[PsnrMetrics]) -> f64 {
 
 /// Calculate the PSNR metrics for a `Plane` by comparing the original (uncompressed) to
 /// the compressed version.
-fn calculate_plane_psnr_metrics<T: Pixel>(
-    plane1: &PlaneData<T>,
-    plane2: &PlaneData<T>,
+pub(crate) fn calculate_plane_psnr_metrics<T: Pixel>(
+    plane1: &PlaneRef<T>,
+    plane2: &PlaneRef<T>,
     bit_depth: usize,
 ) -> PsnrMetrics {
     let sq_err = calculate_plane_total_squared_error(plane1, plane2);
     let max = (1 << bit_depth) - 1;
     PsnrMetrics {
         sq_err,
-        n_pixels: plane1.width * plane1.height,
+        n_pixels: plane1.width() * plane1.height(),
         sample_max: max,
     }
 }
 
-fn calculate_psnr(metrics: PsnrMetrics) -> f64 {
+pub(crate) fn calculate_psnr(metrics: PsnrMetrics) -> f64 {
+    // Only planes which don't exist, such as the chroma planes of 4:0:0 video, are empty
+    if metrics.n_pixels == 0 {
+        return f64::NAN;
+    }
     if metrics.sq_err <= std::f64::EPSILON {
         return 100.0;
     }
@@ -158,14 +218,14 @@ fn calculate_psnr(metrics: PsnrMetrics) -> f64 {
 
 /// Calculate the squared error for a `Plane` by comparing the original (uncompressed)
 /// to the compressed version.
-fn calculate_plane_total_squared_error<T: Pixel>(
-    plane1: &PlaneData<T>,
-    plane2: &PlaneData<T>,
+pub(crate) fn calculate_plane_total_squared_error<T: Pixel>(
+    plane1: &PlaneRef<T>,
+    plane2: &PlaneRef<T>,
 ) -> f64 {
     plane1
-        .data
-        .iter()
-        .zip(plane2.data.iter())
+        .rows()
+        .zip(plane2.rows())
+        .flat_map(|(row1, row2)| row1.iter().zip(row2.iter()))
         .map(|(a, b)| (i32::cast_from(*a) - i32::cast_from(*b)).abs() as u64)
         .map(|err| err * err)
         .sum::<u64>() as f64
@@ -175,110 +235,509 @@ fn calculate_plane_total_squared_error<T: Pixel>(
 mod tests {
     use super::*;
     use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
     use std::fs::File;
-    use y4m::Decoder;
 
     #[test]
     fn psnr_yuv420p8() {
         let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(32.5281, result.y);
-        assert_metric_eq(36.4083, result.u);
-        assert_metric_eq(39.8238, result.v);
-        assert_metric_eq(33.6861, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(32.5281, result.aggregate.y);
+        assert_metric_eq(36.4083, result.aggregate.u);
+        assert_metric_eq(39.8238, result.aggregate.v);
+        assert_metric_eq(33.6861, result.aggregate.avg);
+    }
+
+    #[test]
+    fn psnr_yuv400p8() {
+        let mut file1 = File::open("./testfiles/yuv400p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv400p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(32.5281, result.aggregate.y);
+        assert!(result.aggregate.u.is_nan());
+        assert!(result.aggregate.v.is_nan());
+        assert_metric_eq(32.5281, result.aggregate.avg);
     }
 
     #[test]
     fn psnr_yuv422p8() {
         let mut file1 = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv422p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(38.6740, result.y);
-        assert_metric_eq(47.5219, result.u);
-        assert_metric_eq(48.8615, result.v);
-        assert_metric_eq(41.2190, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(38.6740, result.aggregate.y);
+        assert_metric_eq(47.5219, result.aggregate.u);
+        assert_metric_eq(48.8615, result.aggregate.v);
+        assert_metric_eq(41.2190, result.aggregate.avg);
     }
 
     #[test]
     fn psnr_yuv444p8() {
         let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(32.4235, result.y);
-        assert_metric_eq(40.1212, result.u);
-        assert_metric_eq(43.1900, result.v);
-        assert_metric_eq(36.2126, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(32.4235, result.aggregate.y);
+        assert_metric_eq(40.1212, result.aggregate.u);
+        assert_metric_eq(43.1900, result.aggregate.v);
+        assert_metric_eq(36.2126, result.aggregate.avg);
     }
 
     #[test]
     fn psnr_yuv420p10() {
         let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(32.5421, result.y);
-        assert_metric_eq(36.4922, result.u);
-        assert_metric_eq(39.8558, result.v);
-        assert_metric_eq(33.7071, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(32.5421, result.aggregate.y);
+        assert_metric_eq(36.4922, result.aggregate.u);
+        assert_metric_eq(39.8558, result.aggregate.v);
+        assert_metric_eq(33.7071, result.aggregate.avg);
     }
 
     #[test]
     fn apsnr_yuv420p8() {
         let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_apsnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(32.5450, result.y);
-        assert_metric_eq(36.4087, result.u);
-        assert_metric_eq(39.8244, result.v);
-        assert_metric_eq(33.6995, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_apsnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(32.5450, result.aggregate.y);
+        assert_metric_eq(36.4087, result.aggregate.u);
+        assert_metric_eq(39.8244, result.aggregate.v);
+        assert_metric_eq(33.6995, result.aggregate.avg);
     }
 
     #[test]
     fn apsnr_yuv422p8() {
         let mut file1 = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv422p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_apsnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(38.6741, result.y);
-        assert_metric_eq(47.5219, result.u);
-        assert_metric_eq(48.8616, result.v);
-        assert_metric_eq(41.2191, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_apsnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(38.6741, result.aggregate.y);
+        assert_metric_eq(47.5219, result.aggregate.u);
+        assert_metric_eq(48.8616, result.aggregate.v);
+        assert_metric_eq(41.2191, result.aggregate.avg);
     }
 
     #[test]
     fn apsnr_yuv444p8() {
         let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_apsnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(32.4412, result.y);
-        assert_metric_eq(40.1264, result.u);
-        assert_metric_eq(43.1943, result.v);
-        assert_metric_eq(36.2271, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_apsnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(32.4412, result.aggregate.y);
+        assert_metric_eq(40.1264, result.aggregate.u);
+        assert_metric_eq(43.1943, result.aggregate.v);
+        assert_metric_eq(36.2271, result.aggregate.avg);
     }
 
     #[test]
     fn apsnr_yuv420p10() {
         let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_apsnr::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(32.5586, result.y);
-        assert_metric_eq(36.4923, result.u);
-        assert_metric_eq(39.8563, result.v);
-        assert_metric_eq(33.7200, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_apsnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(32.5586, result.aggregate.y);
+        assert_metric_eq(36.4923, result.aggregate.u);
+        assert_metric_eq(39.8563, result.aggregate.v);
+        assert_metric_eq(33.7200, result.aggregate.avg);
+    }
+
+    #[test]
+    fn psnr_frames_yuv420p8() {
+        use crate::video::Decoder as _;
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let mut frame_count = 0;
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<u8>(), dec2.read_video_frame::<u8>())
+        {
+            let expected = calculate_frame_psnr(&frame1, &frame2).unwrap();
+            let frame = result.frames[frame_count];
+            assert_metric_eq(expected.y, frame.y);
+            assert_metric_eq(expected.u, frame.u);
+            assert_metric_eq(expected.v, frame.v);
+            assert_metric_eq(expected.avg, frame.avg);
+            frame_count += 1;
+        }
+        assert_eq!(frame_count, result.frames.len());
+    }
+
+    #[test]
+    fn psnr_frame_limit_yuv420p8() {
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let options = VideoOptions {
+            frame_limit: Some(2),
+            ..Default::default()
+        };
+        let result = calculate_video_psnr::<_>(&mut dec1, &mut dec2, &options).unwrap();
+        assert_eq!(2, result.frames.len());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn psnr_yuv420p8_single_thread() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result = pool.install(|| {
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap()
+        });
+        assert_metric_eq(32.5281, result.aggregate.y);
+        assert_metric_eq(36.4083, result.aggregate.u);
+        assert_metric_eq(39.8238, result.aggregate.v);
+        assert_metric_eq(33.6861, result.aggregate.avg);
+    }
+
+    #[test]
+    fn process_frame_error_yuv420p8() {
+        // Fails on every frame, so the error must come from `process_frame`
+        struct FailingMetric;
+
+        impl VideoMetric for FailingMetric {
+            type FrameResult = ();
+            type VideoResult = ();
+
+            fn process_frame<T: Pixel>(
+                &self,
+                _frame1: &FrameRef<T>,
+                _frame2: &FrameRef<T>,
+            ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+                Err(MetricsError::UnsupportedInput {
+                    reason: "Failing metric",
+                }
+                .into())
+            }
+
+            fn aggregate_frame_results(
+                &self,
+                _metrics: &[Self::FrameResult],
+                _info: &ComparisonInfo,
+            ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+                Ok(())
+            }
+        }
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let error = FailingMetric
+            .process_video(&mut dec1, &mut dec2, &VideoOptions::default())
+            .unwrap_err();
+        // The original error is kept with and without the `parallel` feature
+        match error.downcast_ref::<MetricsError>() {
+            Some(MetricsError::UnsupportedInput { .. }) => (),
+            _ => panic!("Unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn apsnr_accumulator_yuv420p8() {
+        use crate::video::{Decoder as _, VideoMetricAccumulator};
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let mut accumulator = VideoMetricAccumulator::new(Psnr);
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<u8>(), dec2.read_video_frame::<u8>())
+        {
+            accumulator.push(&frame1, &frame2).unwrap();
+        }
+        let result = accumulator.finish().unwrap().apsnr;
+        assert_metric_eq(32.5450, result.aggregate.y);
+        assert_metric_eq(36.4087, result.aggregate.u);
+        assert_metric_eq(39.8244, result.aggregate.v);
+        assert_metric_eq(33.6995, result.aggregate.avg);
+    }
+
+    #[test]
+    fn psnr_strided_frame_ref_yuv420p8() {
+        use crate::video::{Decoder as _, FrameRef, PlaneRef};
+
+        const PADDING: usize = 13;
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let frame1 = dec1.read_video_frame::<u8>().unwrap();
+        let frame2 = dec2.read_video_frame::<u8>().unwrap();
+        let expected = calculate_frame_psnr(&frame1, &frame2).unwrap();
+
+        // Copy each plane into a buffer with garbage at the end of every row
+        let padded = frame2
+            .planes
+            .iter()
+            .map(|plane| {
+                plane
+                    .data
+                    .chunks(plane.width)
+                    .flat_map(|row| row.iter().copied().chain(vec![255u8; PADDING]))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let plane_ref = |i: usize| {
+            let plane = &frame2.planes[i];
+            PlaneRef::new(&padded[i], plane.width, plane.height, plane.width + PADDING).unwrap()
+        };
+        let frame_ref = FrameRef {
+            planes: [plane_ref(0), plane_ref(1), plane_ref(2)],
+            bit_depth: frame2.bit_depth,
+            chroma_sampling: frame2.chroma_sampling,
+        };
+        let result = calculate_frame_psnr(&frame1, frame_ref).unwrap();
+        assert_metric_eq(expected.y, result.y);
+        assert_metric_eq(expected.u, result.u);
+        assert_metric_eq(expected.v, result.v);
+        assert_metric_eq(expected.avg, result.avg);
+
+        let plane = &frame2.planes[0];
+        assert!(PlaneRef::new(&padded[0], plane.width, plane.height, plane.width - 1).is_err());
+        assert!(PlaneRef::new(&padded[1], plane.width, plane.height, plane.width).is_err());
+    }
+
+    #[test]
+    fn psnr_malformed_frame_yuv420p8() {
+        use crate::video::DecodeError;
+        use crate::MetricsError;
+        use std::io::{Cursor, Read};
+
+        let read_file = |filename| {
+            let mut data = Vec::new();
+            File::open(filename)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            data
+        };
+        let mut input1 = Cursor::new(read_file("./testfiles/yuv420p8_input.y4m"));
+        let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+        let mut data = read_file("./testfiles/yuv420p8_output.y4m");
+        // Corrupt the header of the second frame
+        let second_frame = data
+            .windows(6)
+            .enumerate()
+            .filter(|(_, window)| window == b"FRAME\n")
+            .nth(1)
+            .unwrap()
+            .0;
+        data[second_frame] = b'X';
+        let mut input2 = Cursor::new(data);
+        let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+        let error =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap_err();
+        match error.downcast_ref::<MetricsError>() {
+            Some(MetricsError::DecodeFailed(DecodeError::Malformed { .. })) => (),
+            _ => panic!("Unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn psnr_truncated_frame_yuv420p8() {
+        use crate::video::DecodeError;
+        use crate::MetricsError;
+        use std::io::{Cursor, Read};
+
+        let read_file = |filename| {
+            let mut data = Vec::new();
+            File::open(filename)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            data
+        };
+        let mut input1 = Cursor::new(read_file("./testfiles/yuv420p8_input.y4m"));
+        let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+        let mut data = read_file("./testfiles/yuv420p8_output.y4m");
+        // Cut the file off in the middle of the last frame
+        data.truncate(data.len() - 1000);
+        let mut input2 = Cursor::new(data);
+        let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+        let error =
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap_err();
+        match error.downcast_ref::<MetricsError>() {
+            Some(MetricsError::DecodeFailed(DecodeError::Malformed { .. })) => (),
+            _ => panic!("Unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn psnr_length_policy_yuv420p8() {
+        use crate::video::{FrameCounts, LengthPolicy};
+        use std::io::{Cursor, Read};
+
+        let read_file = |filename| {
+            let mut data = Vec::new();
+            File::open(filename)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            data
+        };
+        let input1 = read_file("./testfiles/yuv420p8_input.y4m");
+        let mut input2 = read_file("./testfiles/yuv420p8_output.y4m");
+        // Remove the last frame from the second video
+        let third_frame = input2
+            .windows(6)
+            .enumerate()
+            .filter(|(_, window)| window == b"FRAME\n")
+            .nth(2)
+            .unwrap()
+            .0;
+        input2.truncate(third_frame);
+        let run = |length_policy| {
+            let mut input1 = Cursor::new(&input1);
+            let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+            let mut input2 = Cursor::new(&input2);
+            let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+            let options = VideoOptions {
+                length_policy,
+                ..Default::default()
+            };
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &options)
+        };
+
+        let result = run(LengthPolicy::Truncate).unwrap();
+        assert_eq!(2, result.frames.len());
+        assert_eq!(None, result.frame_counts);
+
+        let result = run(LengthPolicy::Warn).unwrap();
+        assert_eq!(2, result.frames.len());
+        assert_eq!(
+            Some(FrameCounts {
+                input1: 3,
+                input2: 2
+            }),
+            result.frame_counts
+        );
+
+        assert!(run(LengthPolicy::Error).is_err());
+
+        // Reading continues on the calling thread while frames are processed,
+        // which must not affect the result
+        #[cfg(feature = "parallel")]
+        {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(4)
+                .build()
+                .unwrap();
+            let result = pool.install(|| run(LengthPolicy::Warn).unwrap());
+            assert_eq!(2, result.frames.len());
+            assert_eq!(
+                Some(FrameCounts {
+                    input1: 3,
+                    input2: 2
+                }),
+                result.frame_counts
+            );
+        }
+    }
+
+    #[test]
+    fn psnr_start_and_offsets_yuv420p8() {
+        let run = |options: VideoOptions| {
+            let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+            let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+            let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+            let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+            calculate_video_psnr::<_>(&mut dec1, &mut dec2, &options).unwrap()
+        };
+        let full = run(VideoOptions::default());
+
+        let result = run(VideoOptions {
+            start_frame: 1,
+            frame_limit: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(1, result.frames.len());
+        assert_metric_eq(full.frames[1].y, result.aggregate.y);
+        assert_metric_eq(full.frames[1].avg, result.aggregate.avg);
+
+        // The end frame is exclusive, and applies along with the frame limit
+        let result = run(VideoOptions {
+            start_frame: 1,
+            end_frame: Some(2),
+            frame_limit: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(1, result.frames.len());
+        assert_metric_eq(full.frames[1].y, result.aggregate.y);
+
+        // Offsetting both inputs equally is the same as starting later
+        let result = run(VideoOptions {
+            offset1: 1,
+            offset2: 1,
+            ..Default::default()
+        });
+        assert_eq!(2, result.frames.len());
+        assert_metric_eq(full.frames[2].y, result.frames[1].y);
+
+        // Skipping past the end of either input leaves no frames to compare
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let options = VideoOptions {
+            offset2: 3,
+            ..Default::default()
+        };
+        assert!(calculate_video_psnr::<_>(&mut dec1, &mut dec2, &options).is_err());
+
+        // The end frame must not be before the start frame
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let options = VideoOptions {
+            start_frame: 2,
+            end_frame: Some(1),
+            ..Default::default()
+        };
+        let error = calculate_video_psnr::<_>(&mut dec1, &mut dec2, &options).unwrap_err();
+        match error.downcast_ref::<MetricsError>() {
+            Some(MetricsError::InvalidOptions { .. }) => (),
+            _ => panic!("Unexpected error: {}", error),
+        }
     }
 }
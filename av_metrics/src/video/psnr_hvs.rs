@@ -10,7 +10,12 @@
 use crate::video::decode::Decoder;
 use crate::video::pixel::CastFromPrimitive;
 use crate::video::pixel::Pixel;
-use crate::video::{FrameInfo, PlanarMetrics, PlaneData, VideoMetric};
+#[cfg(feature = "decode")]
+use crate::video::VideoOptions;
+use crate::video::{
+    weighted_plane_sum, ChromaSampling, ComparisonInfo, FrameRef, PlanarMetrics, PlaneRef,
+    UnweightedPlanarMetrics, VideoMetric, VideoMetricResults,
+};
 use std::error::Error;
 
 /// Calculates the PSNR-HVS score between two videos. Higher is better.
@@ -19,82 +24,79 @@ use std::error::Error;
 pub fn calculate_video_psnr_hvs<D: Decoder>(
     decoder1: &mut D,
     decoder2: &mut D,
-    frame_limit: Option<usize>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    PsnrHvs::default().process_video(decoder1, decoder2, frame_limit)
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<PlanarMetrics>, Box<dyn Error + Send + Sync>> {
+    PsnrHvs.process_video(decoder1, decoder2, options)
 }
 
 /// Calculates the PSNR-HVS score between two video frames. Higher is better.
 #[inline]
-pub fn calculate_frame_psnr_hvs<T: Pixel>(
-    frame1: &FrameInfo<T>,
-    frame2: &FrameInfo<T>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    let mut processor = PsnrHvs::default();
-    let result = processor.process_frame(frame1, frame2)?;
-    let cweight = processor.cweight.unwrap();
-    Ok(PlanarMetrics {
-        y: log10_convert(result.y, 1.0),
-        u: log10_convert(result.u, 1.0),
-        v: log10_convert(result.v, 1.0),
-        avg: log10_convert(
-            result.y + cweight * (result.u + result.v),
-            1.0 + 2.0 * cweight,
-        ),
-    })
+pub fn calculate_frame_psnr_hvs<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<PlanarMetrics, Box<dyn Error + Send + Sync>> {
+    let result = PsnrHvs.process_frame(&frame1.into(), &frame2.into())?;
+    Ok(convert_frame_result(&result))
 }
 
-#[derive(Default)]
-struct PsnrHvs {
-    pub cweight: Option<f64>,
-}
+/// The PSNR-HVS metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PsnrHvs;
 
 impl VideoMetric for PsnrHvs {
-    type FrameResult = PlanarMetrics;
-    type VideoResult = PlanarMetrics;
+    type FrameResult = UnweightedPlanarMetrics;
+    type VideoResult = VideoMetricResults<PlanarMetrics>;
 
     /// Returns the *unweighted* scores. Depending on whether we output per-frame
     /// or per-video, these will be weighted at different points.
     fn process_frame<T: Pixel>(
-        &mut self,
-        frame1: &FrameInfo<T>,
-        frame2: &FrameInfo<T>,
-    ) -> Result<Self::FrameResult, Box<dyn Error>> {
-        frame1.can_compare(&frame2)?;
-        if self.cweight.is_none() {
-            self.cweight = Some(frame1.chroma_sampling.get_chroma_weight());
-        }
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
 
         let bit_depth = frame1.bit_depth;
         let y = calculate_plane_psnr_hvs(&frame1.planes[0], &frame2.planes[0], 0, bit_depth);
-        let u = calculate_plane_psnr_hvs(&frame1.planes[1], &frame2.planes[1], 1, bit_depth);
-        let v = calculate_plane_psnr_hvs(&frame1.planes[2], &frame2.planes[2], 2, bit_depth);
-        Ok(PlanarMetrics {
+        let (u, v) = if frame1.chroma_sampling == ChromaSampling::Cs400 {
+            (f64::NAN, f64::NAN)
+        } else {
+            (
+                calculate_plane_psnr_hvs(&frame1.planes[1], &frame2.planes[1], 1, bit_depth),
+                calculate_plane_psnr_hvs(&frame1.planes[2], &frame2.planes[2], 2, bit_depth),
+            )
+        };
+        Ok(UnweightedPlanarMetrics {
             y,
             u,
             v,
-            // field not used here
-            avg: 0.,
+            cweight: frame1.chroma_sampling.get_chroma_weight(),
         })
     }
 
-    #[cfg(feature = "decode")]
     fn aggregate_frame_results(
         &self,
         metrics: &[Self::FrameResult],
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        let cweight = self.cweight.unwrap();
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let cweight = metrics[0].cweight;
         let sum_y = metrics.iter().map(|m| m.y).sum::<f64>();
         let sum_u = metrics.iter().map(|m| m.u).sum::<f64>();
         let sum_v = metrics.iter().map(|m| m.v).sum::<f64>();
-        Ok(PlanarMetrics {
-            y: log10_convert(sum_y, 1. / metrics.len() as f64),
-            u: log10_convert(sum_u, 1. / metrics.len() as f64),
-            v: log10_convert(sum_v, 1. / metrics.len() as f64),
-            avg: log10_convert(
-                sum_y + cweight * (sum_u + sum_v),
-                (1. + 2. * cweight) * 1. / metrics.len() as f64,
-            ),
+        Ok(VideoMetricResults {
+            aggregate: PlanarMetrics {
+                y: log10_convert(sum_y, 1. / metrics.len() as f64),
+                u: log10_convert(sum_u, 1. / metrics.len() as f64),
+                v: log10_convert(sum_v, 1. / metrics.len() as f64),
+                avg: log10_convert(
+                    weighted_plane_sum(sum_y, sum_u, sum_v, cweight),
+                    (1. + 2. * cweight) * 1. / metrics.len() as f64,
+                ),
+            },
+            frames: metrics.iter().map(convert_frame_result).collect(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
         })
     }
 }
@@ -139,8 +141,8 @@ const CSF_CR420: [[f64; 8]; 8] = [
 ];
 
 fn calculate_plane_psnr_hvs<T: Pixel>(
-    plane1: &PlaneData<T>,
-    plane2: &PlaneData<T>,
+    plane1: &PlaneRef<T>,
+    plane2: &PlaneRef<T>,
     plane_idx: usize,
     bit_depth: usize,
 ) -> f64 {
@@ -178,14 +180,12 @@ fn calculate_plane_psnr_hvs<T: Pixel>(
         }
     }
 
-    let height = plane1.height;
-    let width = plane1.width;
+    let height = plane1.height();
+    let width = plane1.width();
     let mut p1 = [0i16; 8 * 8];
     let mut p2 = [0i16; 8 * 8];
     let mut dct_p1 = [0i32; 8 * 8];
     let mut dct_p2 = [0i32; 8 * 8];
-    assert!(plane1.data.len() == width * height);
-    assert!(plane2.data.len() == width * height);
     for y in (0..(height - STEP)).step_by(STEP) {
         for x in (0..(width - STEP)).step_by(STEP) {
             let mut p1_means = [0.0; 4];
@@ -200,9 +200,11 @@ fn calculate_plane_psnr_hvs<T: Pixel>(
             let mut p2_mask = 0.0;
 
             for i in 0..8 {
+                let row1 = plane1.row(y + i);
+                let row2 = plane2.row(y + i);
                 for j in 0..8 {
-                    p1[i * 8 + j] = i16::cast_from(plane1.data[(y + i) * width + x + j]);
-                    p2[i * 8 + j] = i16::cast_from(plane2.data[(y + i) * width + x + j]);
+                    p1[i * 8 + j] = i16::cast_from(row1[x + j]);
+                    p2[i * 8 + j] = i16::cast_from(row2[x + j]);
 
                     let sub = ((i & 12) >> 2) + ((j & 12) >> 1);
                     p1_gmean += p1[i * 8 + j] as f64;
@@ -283,6 +285,20 @@ fn calculate_plane_psnr_hvs<T: Pixel>(
     result
 }
 
+/// Converts the unweighted scores for a single frame to their final, weighted values.
+fn convert_frame_result(result: &UnweightedPlanarMetrics) -> PlanarMetrics {
+    let cweight = result.cweight;
+    PlanarMetrics {
+        y: log10_convert(result.y, 1.0),
+        u: log10_convert(result.u, 1.0),
+        v: log10_convert(result.v, 1.0),
+        avg: log10_convert(
+            weighted_plane_sum(result.y, result.u, result.v, cweight),
+            1.0 + 2.0 * cweight,
+        ),
+    }
+}
+
 fn log10_convert(score: f64, weight: f64) -> f64 {
     10.0 * (-1.0 * (weight * score).log10())
 }
@@ -380,58 +396,76 @@ fn od_dct_rshift(a: i32, b: u32) -> i32 {
 mod tests {
     use super::*;
     use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
     use std::fs::File;
-    use y4m::Decoder;
 
     #[test]
     fn psnr_hvs_yuv420p8() {
         let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(34.3227, result.y);
-        assert_metric_eq(37.7400, result.u);
-        assert_metric_eq(40.5570, result.v);
-        assert_metric_eq(31.8676, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(34.3227, result.aggregate.y);
+        assert_metric_eq(37.7400, result.aggregate.u);
+        assert_metric_eq(40.5570, result.aggregate.v);
+        assert_metric_eq(31.8676, result.aggregate.avg);
+    }
+
+    #[test]
+    fn psnr_hvs_yuv400p8() {
+        let mut file1 = File::open("./testfiles/yuv400p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv400p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(34.3227, result.aggregate.y);
+        assert!(result.aggregate.u.is_nan());
+        assert!(result.aggregate.v.is_nan());
+        assert_metric_eq(34.3227, result.aggregate.avg);
     }
 
     #[test]
     fn psnr_hvs_yuv422p8() {
         let mut file1 = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv422p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(45.3473, result.y);
-        assert_metric_eq(46.3951, result.u);
-        assert_metric_eq(45.1177, result.v);
-        assert_metric_eq(39.5041, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(45.3473, result.aggregate.y);
+        assert_metric_eq(46.3951, result.aggregate.u);
+        assert_metric_eq(45.1177, result.aggregate.v);
+        assert_metric_eq(39.5041, result.aggregate.avg);
     }
 
     #[test]
     fn psnr_hvs_yuv444p8() {
         let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(34.1887, result.y);
-        assert_metric_eq(38.0190, result.u);
-        assert_metric_eq(40.4087, result.v);
-        assert_metric_eq(27.2354, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(34.1887, result.aggregate.y);
+        assert_metric_eq(38.0190, result.aggregate.u);
+        assert_metric_eq(40.4087, result.aggregate.v);
+        assert_metric_eq(27.2354, result.aggregate.avg);
     }
 
     #[test]
     fn psnr_hvs_yuv420p10() {
         let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(34.4843, result.y);
-        assert_metric_eq(38.1651, result.u);
-        assert_metric_eq(41.0645, result.v);
-        assert_metric_eq(32.0711, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_psnr_hvs::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(34.4843, result.aggregate.y);
+        assert_metric_eq(38.1651, result.aggregate.u);
+        assert_metric_eq(41.0645, result.aggregate.v);
+        assert_metric_eq(32.0711, result.aggregate.avg);
     }
 }
@@ -0,0 +1,444 @@
+//! Rescales frames, so that videos with different resolutions can be compared.
+//!
+//! This is useful for e.g. scoring the lower resolutions of an adaptive bitrate
+//! ladder against the full resolution source.
+//! Each plane is resampled separately with a separable filter,
+//! so all chroma sampling formats are supported.
+
+#[cfg(feature = "decode")]
+use crate::video::decode::{DecodeError, Decoder, VideoDetails};
+use crate::video::pixel::{CastFromPrimitive, Pixel};
+use crate::video::{FrameInfo, FrameRef, PlaneData, PlaneRef};
+
+/// The filter used to resample each plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleFilter {
+    /// Linear interpolation between the two nearest pixels.
+    Bilinear,
+    /// Cubic interpolation using the Catmull-Rom spline.
+    Bicubic,
+    /// A 3-lobed Lanczos filter. This is the sharpest of the available filters.
+    #[default]
+    Lanczos,
+}
+
+impl ScaleFilter {
+    /// The distance from the center, in pixels, at which the kernel becomes zero.
+    fn support(self) -> f64 {
+        match self {
+            ScaleFilter::Bilinear => 1.0,
+            ScaleFilter::Bicubic => 2.0,
+            ScaleFilter::Lanczos => 3.0,
+        }
+    }
+
+    fn kernel(self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            ScaleFilter::Bilinear => (1.0 - x).max(0.0),
+            ScaleFilter::Bicubic => {
+                const A: f64 = -0.5;
+                if x < 1.0 {
+                    ((A + 2.0) * x - (A + 3.0)) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((A * x - 5.0 * A) * x + 8.0 * A) * x - 4.0 * A
+                } else {
+                    0.0
+                }
+            }
+            ScaleFilter::Lanczos => {
+                if x < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * std::f64::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// The resolution which videos are scaled to before they are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleTarget {
+    /// Scale the second video to the resolution of the first video.
+    Reference,
+    /// Scale both videos to the given luma resolution.
+    Size {
+        /// The width, in pixels, of the luma plane.
+        width: usize,
+        /// The height, in pixels, of the luma plane.
+        height: usize,
+    },
+}
+
+/// Options for rescaling videos with different resolutions before they are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleOptions {
+    /// The resolution to scale to.
+    pub target: ScaleTarget,
+    /// The filter used for scaling.
+    pub filter: ScaleFilter,
+}
+
+impl Default for ScaleOptions {
+    fn default() -> Self {
+        ScaleOptions {
+            target: ScaleTarget::Reference,
+            filter: ScaleFilter::default(),
+        }
+    }
+}
+
+/// Scales a frame to the given luma resolution.
+///
+/// The chroma planes are scaled to the matching size for the frame's chroma sampling.
+pub fn scale_frame<'a, T: Pixel>(
+    frame: impl Into<FrameRef<'a, T>>,
+    width: usize,
+    height: usize,
+    filter: ScaleFilter,
+) -> FrameInfo<T> {
+    let frame = frame.into();
+    let (chroma_width, chroma_height) = frame.chroma_sampling.get_chroma_dimensions(width, height);
+    let bit_depth = frame.bit_depth;
+    FrameInfo {
+        planes: [
+            scale_plane(&frame.planes[0], width, height, filter, bit_depth),
+            scale_plane(
+                &frame.planes[1],
+                chroma_width,
+                chroma_height,
+                filter,
+                bit_depth,
+            ),
+            scale_plane(
+                &frame.planes[2],
+                chroma_width,
+                chroma_height,
+                filter,
+                bit_depth,
+            ),
+        ],
+        bit_depth,
+        chroma_sampling: frame.chroma_sampling,
+    }
+}
+
+/// The weights of the source pixels which contribute to one output pixel.
+struct FilterTaps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Maps the position of each output pixel to a position in the source plane,
+/// along one dimension.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SampleMapping {
+    /// The distance in the source plane between two adjacent output pixels.
+    pub(crate) ratio: f64,
+    /// Added to the source position of each output pixel, to change the siting.
+    pub(crate) offset: f64,
+}
+
+impl SampleMapping {
+    /// Maps the centers of the output pixels evenly over the source pixels.
+    fn resize(src_len: usize, dst_len: usize) -> Self {
+        SampleMapping {
+            ratio: src_len as f64 / dst_len as f64,
+            offset: 0.0,
+        }
+    }
+}
+
+/// Calculates the filter taps for each output pixel along one dimension.
+fn calculate_taps(
+    src_len: usize,
+    dst_len: usize,
+    filter: ScaleFilter,
+    mapping: SampleMapping,
+) -> Vec<FilterTaps> {
+    // When downscaling, the kernel is stretched to avoid aliasing
+    let kernel_scale = mapping.ratio.max(1.0);
+    let support = filter.support() * kernel_scale;
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f64 + 0.5) * mapping.ratio + mapping.offset;
+            let start = ((center - support).floor().max(0.0) as usize).min(src_len - 1);
+            let end = ((center + support).ceil().max(0.0) as usize).clamp(start + 1, src_len);
+            let weights = (start..end)
+                .map(|j| filter.kernel((j as f64 + 0.5 - center) / kernel_scale))
+                .collect::<Vec<_>>();
+            let sum: f64 = weights.iter().sum();
+            FilterTaps {
+                start,
+                weights: if sum == 0.0 {
+                    // The output pixel lies beyond the edge, so use the nearest source pixel
+                    let mut weights = vec![0.0; weights.len()];
+                    let nearest = if center < 0.0 { 0 } else { weights.len() - 1 };
+                    weights[nearest] = 1.0;
+                    weights
+                } else {
+                    weights.iter().map(|w| (w / sum) as f32).collect()
+                },
+            }
+        })
+        .collect()
+}
+
+fn scale_plane<T: Pixel>(
+    plane: &PlaneRef<T>,
+    width: usize,
+    height: usize,
+    filter: ScaleFilter,
+    bit_depth: usize,
+) -> PlaneData<T> {
+    if plane.width() == width && plane.height() == height {
+        return PlaneData {
+            width,
+            height,
+            data: plane.rows().flatten().copied().collect(),
+        };
+    }
+    resample_plane(
+        plane,
+        width,
+        height,
+        filter,
+        bit_depth,
+        SampleMapping::resize(plane.width(), width),
+        SampleMapping::resize(plane.height(), height),
+    )
+}
+
+/// Resamples a plane to the given size, using `horizontal` and `vertical`
+/// to find the source position of each output pixel.
+pub(crate) fn resample_plane<T: Pixel>(
+    plane: &PlaneRef<T>,
+    width: usize,
+    height: usize,
+    filter: ScaleFilter,
+    bit_depth: usize,
+    horizontal: SampleMapping,
+    vertical: SampleMapping,
+) -> PlaneData<T> {
+    if width == 0 || height == 0 || plane.width() == 0 || plane.height() == 0 {
+        return PlaneData {
+            width,
+            height,
+            data: vec![T::cast_from(0u8); width * height],
+        };
+    }
+
+    let horizontal = calculate_taps(plane.width(), width, filter, horizontal);
+    let vertical = calculate_taps(plane.height(), height, filter, vertical);
+
+    let mut scaled_rows = Vec::with_capacity(width * plane.height());
+    for row in plane.rows() {
+        scaled_rows.extend(horizontal.iter().map(|taps| {
+            taps.weights
+                .iter()
+                .zip(&row[taps.start..])
+                .map(|(&weight, &pixel)| weight * u32::cast_from(pixel) as f32)
+                .sum::<f32>()
+        }));
+    }
+
+    let max_value = ((1u32 << bit_depth) - 1) as f32;
+    let mut data = Vec::with_capacity(width * height);
+    for taps in &vertical {
+        for x in 0..width {
+            let value = taps
+                .weights
+                .iter()
+                .enumerate()
+                .map(|(i, &weight)| weight * scaled_rows[(taps.start + i) * width + x])
+                .sum::<f32>();
+            data.push(T::cast_from(value.round().max(0.0).min(max_value) as u32));
+        }
+    }
+    PlaneData {
+        width,
+        height,
+        data,
+    }
+}
+
+/// A decoder which scales each frame to a given resolution after decoding it.
+///
+/// This is used to implement `VideoOptions::scale`, but can also be used
+/// directly, e.g. with [`find_alignment`](../align/fn.find_alignment.html).
+#[cfg(feature = "decode")]
+pub struct ScaledDecoder<'a, D: Decoder> {
+    decoder: &'a mut D,
+    /// The luma resolution to scale to, or `None` to leave frames unchanged.
+    size: Option<(usize, usize)>,
+    filter: ScaleFilter,
+}
+
+#[cfg(feature = "decode")]
+impl<'a, D: Decoder> ScaledDecoder<'a, D> {
+    /// Wraps a decoder, scaling its frames to the given luma resolution
+    /// if `size` is set.
+    pub fn new(decoder: &'a mut D, size: Option<(usize, usize)>, filter: ScaleFilter) -> Self {
+        ScaledDecoder {
+            decoder,
+            size,
+            filter,
+        }
+    }
+}
+
+#[cfg(feature = "decode")]
+impl<D: Decoder> Decoder for ScaledDecoder<'_, D> {
+    fn read_video_frame<T: Pixel>(&mut self) -> Result<FrameInfo<T>, DecodeError> {
+        let frame = self.decoder.read_video_frame()?;
+        match self.size {
+            Some((width, height))
+                if (width, height) != (frame.planes[0].width, frame.planes[0].height) =>
+            {
+                Ok(scale_frame(&frame, width, height, self.filter))
+            }
+            _ => Ok(frame),
+        }
+    }
+
+    fn get_video_details(&self) -> VideoDetails {
+        let mut details = self.decoder.get_video_details();
+        if let Some((width, height)) = self.size {
+            details.width = width;
+            details.height = height;
+        }
+        details
+    }
+}
+
+/// Wraps two decoders so that their frames are scaled according to `options`.
+/// If `options` is `None`, frames are passed through unchanged.
+#[cfg(feature = "decode")]
+pub fn scale_decoders<'a, D: Decoder>(
+    decoder1: &'a mut D,
+    decoder2: &'a mut D,
+    options: Option<&ScaleOptions>,
+) -> (ScaledDecoder<'a, D>, ScaledDecoder<'a, D>) {
+    let (size1, size2, filter) = match options {
+        Some(ScaleOptions {
+            target: ScaleTarget::Reference,
+            filter,
+        }) => {
+            let details1 = decoder1.get_video_details();
+            (None, Some((details1.width, details1.height)), *filter)
+        }
+        Some(ScaleOptions {
+            target: ScaleTarget::Size { width, height },
+            filter,
+        }) => (Some((*width, *height)), Some((*width, *height)), *filter),
+        None => (None, None, ScaleFilter::default()),
+    };
+    (
+        ScaledDecoder::new(decoder1, size1, filter),
+        ScaledDecoder::new(decoder2, size2, filter),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::psnr::calculate_video_psnr;
+    use crate::video::{ChromaSampling, VideoOptions};
+    use std::fs::File;
+    use std::io::Cursor;
+
+    const FILTERS: [ScaleFilter; 3] = [
+        ScaleFilter::Bilinear,
+        ScaleFilter::Bicubic,
+        ScaleFilter::Lanczos,
+    ];
+
+    #[test]
+    fn scale_flat_frame() {
+        let plane = |width, height| PlaneData {
+            width,
+            height,
+            data: vec![200u16; width * height],
+        };
+        let frame = FrameInfo {
+            planes: [plane(64, 36), plane(32, 36), plane(32, 36)],
+            bit_depth: 10,
+            chroma_sampling: ChromaSampling::Cs422,
+        };
+        for &filter in &FILTERS {
+            for &(width, height) in &[(64, 36), (128, 72), (30, 17)] {
+                let scaled = scale_frame(&frame, width, height, filter);
+                assert_eq!(width, scaled.planes[0].width);
+                assert_eq!(height, scaled.planes[0].height);
+                assert_eq!((width + 1) / 2, scaled.planes[1].width);
+                assert_eq!(height, scaled.planes[2].height);
+                for plane in &scaled.planes {
+                    assert_eq!(plane.width * plane.height, plane.data.len());
+                    assert!(plane.data.iter().all(|&pixel| pixel == 200));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scale_to_reference_yuv420p8() {
+        // Create a half resolution copy of the input
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let mut downscaled = b"YUV4MPEG2 W320 H180 F25:1 Ip A1:1 C420jpeg\n".to_vec();
+        while let Ok(frame) = dec.read_video_frame::<u8>() {
+            let frame = scale_frame(&frame, 320, 180, ScaleFilter::Lanczos);
+            downscaled.extend_from_slice(b"FRAME\n");
+            for plane in &frame.planes {
+                downscaled.extend_from_slice(&plane.data);
+            }
+        }
+
+        let input = std::fs::read("./testfiles/yuv420p8_input.y4m").unwrap();
+        let run = |options: &VideoOptions| {
+            let mut input1 = Cursor::new(&input);
+            let mut dec1 = Y4mDecoder::new(&mut input1).unwrap();
+            let mut input2 = Cursor::new(&downscaled);
+            let mut dec2 = Y4mDecoder::new(&mut input2).unwrap();
+            calculate_video_psnr(&mut dec1, &mut dec2, options)
+        };
+        assert!(run(&VideoOptions::default()).is_err());
+        for &filter in &FILTERS {
+            let options = VideoOptions {
+                scale: Some(ScaleOptions {
+                    target: ScaleTarget::Reference,
+                    filter,
+                }),
+                ..Default::default()
+            };
+            let result = run(&options).unwrap();
+            assert_eq!(3, result.frames.len());
+            assert!(result.aggregate.avg > 30.0);
+        }
+
+        // Scaling both inputs to the same size as the downscaled input
+        let options = VideoOptions {
+            scale: Some(ScaleOptions {
+                target: ScaleTarget::Size {
+                    width: 320,
+                    height: 180,
+                },
+                filter: ScaleFilter::Lanczos,
+            }),
+            ..Default::default()
+        };
+        let result = run(&options).unwrap();
+        assert_eq!(100.0, result.aggregate.avg);
+    }
+}
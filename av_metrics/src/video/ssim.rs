@@ -12,7 +12,12 @@
 use crate::video::decode::Decoder;
 use crate::video::pixel::CastFromPrimitive;
 use crate::video::pixel::Pixel;
-use crate::video::{FrameInfo, PlanarMetrics, PlaneData, VideoMetric};
+#[cfg(feature = "decode")]
+use crate::video::VideoOptions;
+use crate::video::{
+    weighted_plane_sum, ChromaSampling, ComparisonInfo, FrameRef, PlanarMetrics, PlaneRef,
+    UnweightedPlanarMetrics, VideoMetric, VideoMetricResults,
+};
 use std::cmp;
 use std::error::Error;
 use std::f64::consts::{E, PI};
@@ -23,118 +28,93 @@ use std::f64::consts::{E, PI};
 pub fn calculate_video_ssim<D: Decoder>(
     decoder1: &mut D,
     decoder2: &mut D,
-    frame_limit: Option<usize>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    Ssim::default().process_video(decoder1, decoder2, frame_limit)
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<PlanarMetrics>, Box<dyn Error + Send + Sync>> {
+    Ssim.process_video(decoder1, decoder2, options)
 }
 
 /// Calculates the SSIM score between two video frames. Higher is better.
 #[inline]
-pub fn calculate_frame_ssim<T: Pixel>(
-    frame1: &FrameInfo<T>,
-    frame2: &FrameInfo<T>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    let mut processor = Ssim::default();
-    let result = processor.process_frame(frame1, frame2)?;
-    let cweight = processor.cweight.unwrap();
-    Ok(PlanarMetrics {
-        y: log10_convert(result.y, 1.0),
-        u: log10_convert(result.u, 1.0),
-        v: log10_convert(result.v, 1.0),
-        avg: log10_convert(
-            result.y + cweight * (result.u + result.v),
-            1.0 + 2.0 * cweight,
-        ),
-    })
+pub fn calculate_frame_ssim<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<PlanarMetrics, Box<dyn Error + Send + Sync>> {
+    let result = Ssim.process_frame(&frame1.into(), &frame2.into())?;
+    Ok(convert_frame_result(&result))
 }
 
-#[derive(Default)]
-struct Ssim {
-    pub cweight: Option<f64>,
-}
+/// The SSIM metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ssim;
 
 impl VideoMetric for Ssim {
-    type FrameResult = PlanarMetrics;
-    type VideoResult = PlanarMetrics;
+    type FrameResult = UnweightedPlanarMetrics;
+    type VideoResult = VideoMetricResults<PlanarMetrics>;
 
     /// Returns the *unweighted* scores. Depending on whether we output per-frame
     /// or per-video, these will be weighted at different points.
     fn process_frame<T: Pixel>(
-        &mut self,
-        frame1: &FrameInfo<T>,
-        frame2: &FrameInfo<T>,
-    ) -> Result<Self::FrameResult, Box<dyn Error>> {
-        frame1.can_compare(&frame2)?;
-        if self.cweight.is_none() {
-            self.cweight = Some(frame1.chroma_sampling.get_chroma_weight());
-        }
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
 
         const KERNEL_SHIFT: usize = 8;
         const KERNEL_WEIGHT: usize = 1 << KERNEL_SHIFT;
         let sample_max = (1 << frame1.bit_depth) - 1;
 
-        let y_kernel = build_gaussian_kernel(
-            frame1.planes[0].height as f64 * 1.5 / 256.0,
-            cmp::min(frame1.planes[0].width, frame1.planes[0].height),
-            KERNEL_WEIGHT,
-        );
-        let y = calculate_plane_ssim(
-            &frame1.planes[0],
-            &frame2.planes[0],
-            sample_max,
-            &y_kernel,
-            &y_kernel,
-        );
-        let u_kernel = build_gaussian_kernel(
-            frame1.planes[1].height as f64 * 1.5 / 256.0,
-            cmp::min(frame1.planes[1].width, frame1.planes[1].height),
-            KERNEL_WEIGHT,
-        );
-        let u = calculate_plane_ssim(
-            &frame1.planes[1],
-            &frame2.planes[1],
-            sample_max,
-            &u_kernel,
-            &u_kernel,
-        );
-        let v_kernel = build_gaussian_kernel(
-            frame1.planes[2].height as f64 * 1.5 / 256.0,
-            cmp::min(frame1.planes[2].width, frame1.planes[2].height),
-            KERNEL_WEIGHT,
-        );
-        let v = calculate_plane_ssim(
-            &frame1.planes[2],
-            &frame2.planes[2],
-            sample_max,
-            &v_kernel,
-            &v_kernel,
-        );
-        Ok(PlanarMetrics {
+        let plane_ssim = |plane: usize| {
+            let kernel = build_gaussian_kernel(
+                frame1.planes[plane].height() as f64 * 1.5 / 256.0,
+                cmp::min(frame1.planes[plane].width(), frame1.planes[plane].height()),
+                KERNEL_WEIGHT,
+            );
+            calculate_plane_ssim(
+                &frame1.planes[plane],
+                &frame2.planes[plane],
+                sample_max,
+                &kernel,
+                &kernel,
+            )
+        };
+        let y = plane_ssim(0);
+        let (u, v) = if frame1.chroma_sampling == ChromaSampling::Cs400 {
+            (f64::NAN, f64::NAN)
+        } else {
+            (plane_ssim(1), plane_ssim(2))
+        };
+        Ok(UnweightedPlanarMetrics {
             y,
             u,
             v,
-            // Not used here
-            avg: 0.,
+            cweight: frame1.chroma_sampling.get_chroma_weight(),
         })
     }
 
-    #[cfg(feature = "decode")]
     fn aggregate_frame_results(
         &self,
         metrics: &[Self::FrameResult],
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        let cweight = self.cweight.unwrap();
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let cweight = metrics[0].cweight;
         let y_sum = metrics.iter().map(|m| m.y).sum::<f64>();
         let u_sum = metrics.iter().map(|m| m.u).sum::<f64>();
         let v_sum = metrics.iter().map(|m| m.v).sum::<f64>();
-        Ok(PlanarMetrics {
-            y: log10_convert(y_sum, metrics.len() as f64),
-            u: log10_convert(u_sum, metrics.len() as f64),
-            v: log10_convert(v_sum, metrics.len() as f64),
-            avg: log10_convert(
-                y_sum + cweight * (u_sum + v_sum),
-                (1. + 2. * cweight) * metrics.len() as f64,
-            ),
+        Ok(VideoMetricResults {
+            aggregate: PlanarMetrics {
+                y: log10_convert(y_sum, metrics.len() as f64),
+                u: log10_convert(u_sum, metrics.len() as f64),
+                v: log10_convert(v_sum, metrics.len() as f64),
+                avg: log10_convert(
+                    weighted_plane_sum(y_sum, u_sum, v_sum, cweight),
+                    (1. + 2. * cweight) * metrics.len() as f64,
+                ),
+            },
+            frames: metrics.iter().map(convert_frame_result).collect(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
         })
     }
 }
@@ -149,9 +129,9 @@ impl VideoMetric for Ssim {
 pub fn calculate_video_msssim<D: Decoder>(
     decoder1: &mut D,
     decoder2: &mut D,
-    frame_limit: Option<usize>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    MsSsim::default().process_video(decoder1, decoder2, frame_limit)
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<PlanarMetrics>, Box<dyn Error + Send + Sync>> {
+    MsSsim.process_video(decoder1, decoder2, options)
 }
 
 /// Calculates the MSSSIM score between two video frames. Higher is better.
@@ -160,72 +140,71 @@ pub fn calculate_video_msssim<D: Decoder>(
 /// of an image. It is designed to be a more accurate metric
 /// than SSIM.
 #[inline]
-pub fn calculate_frame_msssim<T: Pixel>(
-    frame1: &FrameInfo<T>,
-    frame2: &FrameInfo<T>,
-) -> Result<PlanarMetrics, Box<dyn Error>> {
-    let mut processor = MsSsim::default();
-    let result = processor.process_frame(frame1, frame2)?;
-    let cweight = processor.cweight.unwrap();
-    Ok(PlanarMetrics {
-        y: log10_convert(result.y, 1.0),
-        u: log10_convert(result.u, 1.0),
-        v: log10_convert(result.v, 1.0),
-        avg: log10_convert(
-            result.y + cweight * (result.u + result.v),
-            1.0 + 2.0 * cweight,
-        ),
-    })
+pub fn calculate_frame_msssim<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<PlanarMetrics, Box<dyn Error + Send + Sync>> {
+    let result = MsSsim.process_frame(&frame1.into(), &frame2.into())?;
+    Ok(convert_frame_result(&result))
 }
 
-#[derive(Default)]
-struct MsSsim {
-    pub cweight: Option<f64>,
-}
+/// The MSSSIM metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsSsim;
 
 impl VideoMetric for MsSsim {
-    type FrameResult = PlanarMetrics;
-    type VideoResult = PlanarMetrics;
+    type FrameResult = UnweightedPlanarMetrics;
+    type VideoResult = VideoMetricResults<PlanarMetrics>;
 
     /// Returns the *unweighted* scores. Depending on whether we output per-frame
     /// or per-video, these will be weighted at different points.
     fn process_frame<T: Pixel>(
-        &mut self,
-        frame1: &FrameInfo<T>,
-        frame2: &FrameInfo<T>,
-    ) -> Result<Self::FrameResult, Box<dyn Error>> {
-        frame1.can_compare(&frame2)?;
-        if self.cweight.is_none() {
-            self.cweight = Some(frame1.chroma_sampling.get_chroma_weight());
-        }
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
 
         let bit_depth = frame1.bit_depth;
-        Ok(PlanarMetrics {
-            y: calculate_plane_msssim(&frame1.planes[0], &frame2.planes[0], bit_depth),
-            u: calculate_plane_msssim(&frame1.planes[1], &frame2.planes[1], bit_depth),
-            v: calculate_plane_msssim(&frame1.planes[2], &frame2.planes[2], bit_depth),
-            // Not used here
-            avg: 0.,
+        let plane_msssim = |plane: usize| {
+            calculate_plane_msssim(&frame1.planes[plane], &frame2.planes[plane], bit_depth)
+        };
+        let (u, v) = if frame1.chroma_sampling == ChromaSampling::Cs400 {
+            (f64::NAN, f64::NAN)
+        } else {
+            (plane_msssim(1), plane_msssim(2))
+        };
+        Ok(UnweightedPlanarMetrics {
+            y: plane_msssim(0),
+            u,
+            v,
+            cweight: frame1.chroma_sampling.get_chroma_weight(),
         })
     }
 
-    #[cfg(feature = "decode")]
     fn aggregate_frame_results(
         &self,
         metrics: &[Self::FrameResult],
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        let cweight = self.cweight.unwrap();
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let cweight = metrics[0].cweight;
         let y_sum = metrics.iter().map(|m| m.y).sum::<f64>();
         let u_sum = metrics.iter().map(|m| m.u).sum::<f64>();
         let v_sum = metrics.iter().map(|m| m.v).sum::<f64>();
-        Ok(PlanarMetrics {
-            y: log10_convert(y_sum, metrics.len() as f64),
-            u: log10_convert(u_sum, metrics.len() as f64),
-            v: log10_convert(v_sum, metrics.len() as f64),
-            avg: log10_convert(
-                y_sum + cweight * (u_sum + v_sum),
-                (1. + 2. * cweight) * metrics.len() as f64,
-            ),
+        Ok(VideoMetricResults {
+            aggregate: PlanarMetrics {
+                y: log10_convert(y_sum, metrics.len() as f64),
+                u: log10_convert(u_sum, metrics.len() as f64),
+                v: log10_convert(v_sum, metrics.len() as f64),
+                avg: log10_convert(
+                    weighted_plane_sum(y_sum, u_sum, v_sum, cweight),
+                    (1. + 2. * cweight) * metrics.len() as f64,
+                ),
+            },
+            frames: metrics.iter().map(convert_frame_result).collect(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
         })
     }
 }
@@ -244,8 +223,8 @@ const SSIM_K1: f64 = 0.01 * 0.01;
 const SSIM_K2: f64 = 0.03 * 0.03;
 
 fn calculate_plane_ssim<T: Pixel>(
-    plane1: &PlaneData<T>,
-    plane2: &PlaneData<T>,
+    plane1: &PlaneRef<T>,
+    plane2: &PlaneRef<T>,
     sample_max: usize,
     vert_kernel: &[i64],
     horiz_kernel: &[i64],
@@ -255,8 +234,8 @@ fn calculate_plane_ssim<T: Pixel>(
     calculate_plane_ssim_internal(
         &vec1,
         &vec2,
-        plane1.width,
-        plane1.height,
+        plane1.width(),
+        plane1.height(),
         sample_max,
         vert_kernel,
         horiz_kernel,
@@ -341,8 +320,8 @@ fn calculate_plane_ssim_internal(
 }
 
 fn calculate_plane_msssim<T: Pixel>(
-    plane1: &PlaneData<T>,
-    plane2: &PlaneData<T>,
+    plane1: &PlaneRef<T>,
+    plane2: &PlaneRef<T>,
     bit_depth: usize,
 ) -> f64 {
     const KERNEL_SHIFT: usize = 10;
@@ -355,8 +334,8 @@ fn calculate_plane_msssim<T: Pixel>(
     let mut sample_max = (1 << bit_depth) - 1;
     let mut ssim = [0.0; 5];
     let mut cs = [0.0; 5];
-    let mut width = plane1.width;
-    let mut height = plane1.height;
+    let mut width = plane1.width();
+    let mut height = plane1.height();
     let mut plane1 = plane_to_vec(plane1);
     let mut plane2 = plane_to_vec(plane2);
 
@@ -387,7 +366,7 @@ fn calculate_plane_msssim<T: Pixel>(
         * ssim[4].powf(MS_WEIGHT[4])
 }
 
-fn build_gaussian_kernel(sigma: f64, max_len: usize, kernel_weight: usize) -> Vec<i64> {
+pub(crate) fn build_gaussian_kernel(sigma: f64, max_len: usize, kernel_weight: usize) -> Vec<i64> {
     let scale = 1.0 / ((2.0 * PI).sqrt() * sigma);
     let nhisigma2 = -0.5 / sigma.powi(2);
     // Compute the kernel size so that the error in the first truncated
@@ -414,8 +393,11 @@ fn build_gaussian_kernel(sigma: f64, max_len: usize, kernel_weight: usize) -> Ve
     kernel
 }
 
-fn plane_to_vec<T: Pixel>(input: &PlaneData<T>) -> Vec<u32> {
-    input.data.iter().map(|pix| u32::cast_from(*pix)).collect()
+fn plane_to_vec<T: Pixel>(input: &PlaneRef<T>) -> Vec<u32> {
+    input
+        .rows()
+        .flat_map(|row| row.iter().map(|pix| u32::cast_from(*pix)))
+        .collect()
 }
 
 // This acts differently from downscaling a plane, and is what
@@ -441,6 +423,20 @@ fn msssim_downscale(input: &[u32], input_width: usize, input_height: usize) -> V
     output
 }
 
+/// Converts the unweighted scores for a single frame to their final, weighted values.
+fn convert_frame_result(result: &UnweightedPlanarMetrics) -> PlanarMetrics {
+    let cweight = result.cweight;
+    PlanarMetrics {
+        y: log10_convert(result.y, 1.0),
+        u: log10_convert(result.u, 1.0),
+        v: log10_convert(result.v, 1.0),
+        avg: log10_convert(
+            weighted_plane_sum(result.y, result.u, result.v, cweight),
+            1.0 + 2.0 * cweight,
+        ),
+    }
+}
+
 fn log10_convert(score: f64, weight: f64) -> f64 {
     10.0 * (weight.log10() - (weight - score).log10())
 }
@@ -449,110 +445,197 @@ fn log10_convert(score: f64, weight: f64) -> f64 {
 mod tests {
     use super::*;
     use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
     use std::fs::File;
-    use y4m::Decoder;
 
     #[test]
     fn ssim_yuv420p8() {
         let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(13.2572, result.y);
-        assert_metric_eq(10.8624, result.u);
-        assert_metric_eq(12.8369, result.v);
-        assert_metric_eq(12.6899, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(13.2572, result.aggregate.y);
+        assert_metric_eq(10.8624, result.aggregate.u);
+        assert_metric_eq(12.8369, result.aggregate.v);
+        assert_metric_eq(12.6899, result.aggregate.avg);
     }
 
     #[test]
     fn msssim_yuv420p8() {
         let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_msssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(18.8343, result.y);
-        assert_metric_eq(16.6943, result.u);
-        assert_metric_eq(18.7662, result.v);
-        assert_metric_eq(18.3859, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_msssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(18.8343, result.aggregate.y);
+        assert_metric_eq(16.6943, result.aggregate.u);
+        assert_metric_eq(18.7662, result.aggregate.v);
+        assert_metric_eq(18.3859, result.aggregate.avg);
+    }
+
+    #[test]
+    fn ssim_yuv400p8() {
+        let mut file1 = File::open("./testfiles/yuv400p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv400p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(13.2572, result.aggregate.y);
+        assert!(result.aggregate.u.is_nan());
+        assert!(result.aggregate.v.is_nan());
+        assert_metric_eq(13.2572, result.aggregate.avg);
+    }
+
+    #[test]
+    fn msssim_yuv400p8() {
+        let mut file1 = File::open("./testfiles/yuv400p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv400p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_msssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(18.8343, result.aggregate.y);
+        assert!(result.aggregate.u.is_nan());
+        assert!(result.aggregate.v.is_nan());
+        assert_metric_eq(18.8343, result.aggregate.avg);
     }
 
     #[test]
     fn ssim_yuv422p8() {
         let mut file1 = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv422p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(21.1130, result.y);
-        assert_metric_eq(21.9978, result.u);
-        assert_metric_eq(22.7898, result.v);
-        assert_metric_eq(21.6987, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(21.1130, result.aggregate.y);
+        assert_metric_eq(21.9978, result.aggregate.u);
+        assert_metric_eq(22.7898, result.aggregate.v);
+        assert_metric_eq(21.6987, result.aggregate.avg);
     }
 
     #[test]
     fn msssim_yuv422p8() {
         let mut file1 = File::open("./testfiles/yuv422p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv422p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_msssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(28.6035, result.y);
-        assert_metric_eq(28.0332, result.u);
-        assert_metric_eq(28.0097, result.v);
-        assert_metric_eq(28.3027, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_msssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(28.6035, result.aggregate.y);
+        assert_metric_eq(28.0332, result.aggregate.u);
+        assert_metric_eq(28.0097, result.aggregate.v);
+        assert_metric_eq(28.3027, result.aggregate.avg);
     }
 
     #[test]
     fn ssim_yuv444p8() {
         let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(13.2989, result.y);
-        assert_metric_eq(14.0089, result.u);
-        assert_metric_eq(15.7419, result.v);
-        assert_metric_eq(14.2338, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(13.2989, result.aggregate.y);
+        assert_metric_eq(14.0089, result.aggregate.u);
+        assert_metric_eq(15.7419, result.aggregate.v);
+        assert_metric_eq(14.2338, result.aggregate.avg);
     }
 
     #[test]
     fn msssim_yuv444p8() {
         let mut file1 = File::open("./testfiles/yuv444p8_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv444p8_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_msssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(18.8897, result.y);
-        assert_metric_eq(17.6092, result.u);
-        assert_metric_eq(19.2732, result.v);
-        assert_metric_eq(18.5308, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_msssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(18.8897, result.aggregate.y);
+        assert_metric_eq(17.6092, result.aggregate.u);
+        assert_metric_eq(19.2732, result.aggregate.v);
+        assert_metric_eq(18.5308, result.aggregate.avg);
     }
 
     #[test]
     fn ssim_yuv420p10() {
         let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_ssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(13.3603, result.y);
-        assert_metric_eq(10.9323, result.u);
-        assert_metric_eq(12.8685, result.v);
-        assert_metric_eq(12.7729, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(13.3603, result.aggregate.y);
+        assert_metric_eq(10.9323, result.aggregate.u);
+        assert_metric_eq(12.8685, result.aggregate.v);
+        assert_metric_eq(12.7729, result.aggregate.avg);
     }
 
     #[test]
     fn msssim_yuv420p10() {
         let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
-        let mut dec1 = Decoder::new(&mut file1).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
         let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
-        let mut dec2 = Decoder::new(&mut file2).unwrap();
-        let result = calculate_video_msssim::<_>(&mut dec1, &mut dec2, None).unwrap();
-        assert_metric_eq(19.0390, result.y);
-        assert_metric_eq(16.8539, result.u);
-        assert_metric_eq(18.8647, result.v);
-        assert_metric_eq(18.5631, result.avg);
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_msssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(19.0390, result.aggregate.y);
+        assert_metric_eq(16.8539, result.aggregate.u);
+        assert_metric_eq(18.8647, result.aggregate.v);
+        assert_metric_eq(18.5631, result.aggregate.avg);
+    }
+
+    #[test]
+    fn ssim_frames_yuv420p8() {
+        use crate::video::Decoder as _;
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_ssim::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let mut frame_count = 0;
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<u8>(), dec2.read_video_frame::<u8>())
+        {
+            let expected = calculate_frame_ssim(&frame1, &frame2).unwrap();
+            let frame = result.frames[frame_count];
+            assert_metric_eq(expected.y, frame.y);
+            assert_metric_eq(expected.u, frame.u);
+            assert_metric_eq(expected.v, frame.v);
+            assert_metric_eq(expected.avg, frame.avg);
+            frame_count += 1;
+        }
+        assert_eq!(frame_count, result.frames.len());
+    }
+
+    #[test]
+    fn ssim_accumulator_yuv420p8() {
+        use crate::video::{Decoder as _, VideoMetricAccumulator};
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let mut accumulator = VideoMetricAccumulator::new(Ssim);
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<u8>(), dec2.read_video_frame::<u8>())
+        {
+            accumulator.push(&frame1, &frame2).unwrap();
+        }
+        let result = accumulator.finish().unwrap();
+        assert_metric_eq(13.2572, result.aggregate.y);
+        assert_metric_eq(10.8624, result.aggregate.u);
+        assert_metric_eq(12.8369, result.aggregate.v);
+        assert_metric_eq(12.6899, result.aggregate.avg);
     }
 }
@@ -0,0 +1,736 @@
+//! SSIMULACRA2, a perceptual image quality metric from the JPEG XL project.
+//!
+//! Each frame is converted to linear RGB, then compared at six scales in the
+//! XYB color space of JPEG XL. At each scale, a variant of SSIM is combined with
+//! maps of edge artifacts, such as ringing and blocking, and lost detail, such as
+//! blurring and smoothing. The error at every scale is fused into a single score
+//! by a mapping fitted to subjective image quality ratings.
+//!
+//! Scores are at most 100, which means the frames are identical, and higher is better.
+//! A score of 90 is roughly visually lossless, and 50 is a medium quality encode.
+//! Very low quality may produce negative scores.
+//!
+//! See https://github.com/cloudinary/ssimulacra2 for more details.
+
+#[cfg(feature = "decode")]
+use crate::video::decode::Decoder;
+use crate::video::deitp::RowBuffer;
+use crate::video::pixel::Pixel;
+#[cfg(feature = "decode")]
+use crate::video::{decode_and_process_video, VideoOptions};
+use crate::video::{
+    ColorDescription, ColorRange, ComparisonInfo, FrameRef, TransferCharacteristics, VideoMetric,
+    VideoMetricResults,
+};
+use crate::MetricsError;
+use std::error::Error;
+use std::f64::consts::PI;
+
+/// The number of scales at which the frames are compared.
+const SCALES: usize = 6;
+/// The standard deviation of the Gaussian blur used for local statistics.
+const BLUR_SIGMA: f64 = 1.5;
+
+/// Calculate the SSIMULACRA2 metric between two video clips. Higher is better.
+///
+/// Frames are converted to RGB using the color description reported by each decoder,
+/// which may be overridden through [`VideoOptions`](../struct.VideoOptions.html).
+/// The aggregate score is the mean of the per-frame scores.
+/// By default, this will return at the end of the shorter of the two clips,
+/// comparing any frames up to that point.
+#[cfg(feature = "decode")]
+#[inline]
+pub fn calculate_video_ssimulacra2<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<f64>, Box<dyn Error + Send + Sync>> {
+    Ssimulacra2::default().process_video(decoder1, decoder2, options)
+}
+
+/// Calculate the SSIMULACRA2 metric between two video frames. Higher is better.
+///
+/// The frames are assumed to use the default `ColorDescription`.
+/// Use [`Ssimulacra2::with_color_descriptions`](struct.Ssimulacra2.html#method.with_color_descriptions)
+/// for other inputs.
+#[inline]
+pub fn calculate_frame_ssimulacra2<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    Ssimulacra2::default().process_frame(&frame1.into(), &frame2.into())
+}
+
+/// The SSIMULACRA2 metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+///
+/// Frames are converted to RGB according to the matrix coefficients and range
+/// in the color description of each video. Only the BT.1886 transfer function
+/// is supported, because SSIMULACRA2 was designed for standard dynamic range images.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ssimulacra2 {
+    color_descriptions: [ColorDescription; 2],
+}
+
+impl Ssimulacra2 {
+    /// Creates a SSIMULACRA2 metric which converts the frames of the first and second
+    /// video to RGB according to the given color descriptions.
+    ///
+    /// When processing a video, the color descriptions reported by the decoders
+    /// are used instead, unless they are overridden by `VideoOptions`.
+    pub fn with_color_descriptions(
+        color_description1: ColorDescription,
+        color_description2: ColorDescription,
+    ) -> Self {
+        Ssimulacra2 {
+            color_descriptions: [color_description1, color_description2],
+        }
+    }
+
+    /// Returns a copy of this metric using the color descriptions of the given videos.
+    #[cfg(feature = "decode")]
+    pub(crate) fn for_videos<D: Decoder>(
+        self,
+        decoder1: &D,
+        decoder2: &D,
+        options: &VideoOptions,
+    ) -> Self {
+        Ssimulacra2 {
+            color_descriptions: [
+                options.color_description(&decoder1.get_video_details()),
+                options.color_description(&decoder2.get_video_details()),
+            ],
+        }
+    }
+}
+
+impl VideoMetric for Ssimulacra2 {
+    type FrameResult = f64;
+    type VideoResult = VideoMetricResults<f64>;
+
+    #[cfg(feature = "decode")]
+    fn process_video<D: Decoder>(
+        &self,
+        decoder1: &mut D,
+        decoder2: &mut D,
+        options: &VideoOptions,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let metric = self.for_videos(decoder1, decoder2, options);
+        decode_and_process_video(&metric, decoder1, decoder2, options)
+    }
+
+    fn process_frame<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
+        if frame1.bit_depth < 8 {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Bit depths below 8 are not supported",
+            }
+            .into());
+        }
+        if self
+            .color_descriptions
+            .iter()
+            .any(|desc| desc.transfer_characteristics != TransferCharacteristics::BT1886)
+        {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "SSIMULACRA2 only supports standard dynamic range video",
+            }
+            .into());
+        }
+        if frame1.planes[0].width() < 8 || frame1.planes[0].height() < 8 {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Frames must be at least 8x8 to calculate SSIMULACRA2",
+            }
+            .into());
+        }
+
+        let mut reference = LinearRgb::new(frame1, self.color_descriptions[0]);
+        let mut distorted = LinearRgb::new(frame2, self.color_descriptions[1]);
+        let blur = RecursiveGaussian::new(BLUR_SIGMA);
+        let mut scales = [ScaleError::default(); SCALES];
+        for (scale, error) in scales.iter_mut().enumerate() {
+            // Smaller scales would be dominated by the edges of the frame
+            if reference.width < 8 || reference.height < 8 {
+                break;
+            }
+            if scale > 0 {
+                reference = reference.downsample();
+                distorted = distorted.downsample();
+            }
+            *error = ScaleError::new(&reference.to_xyb(), &distorted.to_xyb(), &blur);
+        }
+        Ok(score(&scales))
+    }
+
+    fn aggregate_frame_results(
+        &self,
+        metrics: &[Self::FrameResult],
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        Ok(VideoMetricResults {
+            aggregate: metrics.iter().copied().sum::<f64>() / metrics.len() as f64,
+            frames: metrics.to_vec(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+        })
+    }
+}
+
+/// A frame with three planes of floating point samples.
+#[derive(Clone)]
+pub(crate) struct Image {
+    pub(crate) planes: [Vec<f32>; 3],
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+impl Image {
+    fn zip_map(&self, other: &Image, f: impl Fn(f32, f32) -> f32) -> Image {
+        let plane = |p: usize| {
+            self.planes[p]
+                .iter()
+                .zip(other.planes[p].iter())
+                .map(|(&a, &b)| f(a, b))
+                .collect()
+        };
+        Image {
+            planes: [plane(0), plane(1), plane(2)],
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// A frame converted to linear RGB, where 1.0 is the peak white of the display.
+pub(crate) struct LinearRgb(Image);
+
+impl std::ops::Deref for LinearRgb {
+    type Target = Image;
+
+    fn deref(&self) -> &Image {
+        &self.0
+    }
+}
+
+impl LinearRgb {
+    pub(crate) fn new<T: Pixel>(frame: &FrameRef<T>, color_description: ColorDescription) -> Self {
+        let width = frame.planes[0].width();
+        let height = frame.planes[0].height();
+        let bit_depth = frame.bit_depth;
+
+        let (kr, kb) = color_description.matrix_coefficients.luma_weights();
+        let kg = 1. - kr - kb;
+        let (y_offset, y_scale, uv_scale) = match color_description.range {
+            ColorRange::Limited => {
+                let scale = (1 << (bit_depth - 8)) as f32;
+                (16. * scale, 1. / (219. * scale), 1. / (224. * scale))
+            }
+            ColorRange::Full => {
+                let max = ((1 << bit_depth) - 1) as f32;
+                (0., 1. / max, 1. / max)
+            }
+        };
+        let uv_offset = (1 << (bit_depth - 1)) as f32;
+        // A BT.1886 reference display with no black level
+        let linearize = |value: f32| value.clamp(0., 1.).powf(2.4);
+
+        let mut planes = [
+            Vec::with_capacity(width * height),
+            Vec::with_capacity(width * height),
+            Vec::with_capacity(width * height),
+        ];
+        let mut row = RowBuffer::new(width);
+        for i in 0..height {
+            row.fill(frame, i);
+            for x in 0..width {
+                let y = (row.y[x] - y_offset) * y_scale;
+                let u = (row.u[x] - uv_offset) * uv_scale;
+                let v = (row.v[x] - uv_offset) * uv_scale;
+                planes[0].push(linearize(y + 2. * (1. - kr) * v));
+                planes[1].push(linearize(
+                    y - 2. * kb * (1. - kb) / kg * u - 2. * kr * (1. - kr) / kg * v,
+                ));
+                planes[2].push(linearize(y + 2. * (1. - kb) * u));
+            }
+        }
+        LinearRgb(Image {
+            planes,
+            width,
+            height,
+        })
+    }
+
+    /// Halves the resolution by averaging each 2x2 block of samples.
+    ///
+    /// This is done in linear light, so that it matches the perceived brightness.
+    pub(crate) fn downsample(&self) -> LinearRgb {
+        let (width, height) = ((self.width + 1) / 2, (self.height + 1) / 2);
+        let plane = |src: &[f32]| {
+            let mut dst = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for x in 0..width {
+                    let mut sum = 0.;
+                    for iy in 0..2 {
+                        for ix in 0..2 {
+                            let sx = (2 * x + ix).min(self.width - 1);
+                            let sy = (2 * y + iy).min(self.height - 1);
+                            sum += src[sy * self.width + sx];
+                        }
+                    }
+                    dst.push(sum * 0.25);
+                }
+            }
+            dst
+        };
+        LinearRgb(Image {
+            planes: [
+                plane(&self.planes[0]),
+                plane(&self.planes[1]),
+                plane(&self.planes[2]),
+            ],
+            width,
+            height,
+        })
+    }
+
+    /// Converts to the XYB color space, offset and scaled so that
+    /// each component is roughly in the range 0 to 1.
+    fn to_xyb(&self) -> Image {
+        // The opsin absorbance matrix and bias of JPEG XL
+        const M: [[f32; 3]; 3] = [
+            [0.30, 0.622, 0.078],
+            [0.23, 0.692, 0.078],
+            [0.243_422_69, 0.204_767_44, 0.551_809_87],
+        ];
+        const BIAS: f32 = 0.003_793_073_3;
+
+        let bias_cbrt = BIAS.cbrt();
+        let mut xyb = Image {
+            planes: [
+                vec![0.; self.planes[0].len()],
+                vec![0.; self.planes[0].len()],
+                vec![0.; self.planes[0].len()],
+            ],
+            width: self.width,
+            height: self.height,
+        };
+        for i in 0..self.planes[0].len() {
+            let rgb = [self.planes[0][i], self.planes[1][i], self.planes[2][i]];
+            let mixed = M.map(|m| {
+                let mixed = m[0] * rgb[0] + m[1] * rgb[1] + m[2] * rgb[2] + BIAS;
+                mixed.max(0.).cbrt() - bias_cbrt
+            });
+            let x = 0.5 * (mixed[0] - mixed[1]);
+            let y = 0.5 * (mixed[0] + mixed[1]);
+            let b = mixed[2];
+            xyb.planes[0][i] = x * 14. + 0.42;
+            xyb.planes[1][i] = y + 0.01;
+            xyb.planes[2][i] = (b - y) + 0.55;
+        }
+        xyb
+    }
+}
+
+/// The error at one scale, for each of the X, Y and B planes.
+#[derive(Debug, Clone, Copy, Default)]
+struct ScaleError {
+    /// The mean and 4-norm of the SSIM error.
+    ssim: [[f64; 2]; 3],
+    /// The mean and 4-norm of the edge artifacts.
+    artifacts: [[f64; 2]; 3],
+    /// The mean and 4-norm of the lost detail.
+    detail_lost: [[f64; 2]; 3],
+}
+
+impl ScaleError {
+    fn new(reference: &Image, distorted: &Image, blur: &RecursiveGaussian) -> Self {
+        let blurred = |image: &Image| Image {
+            planes: [
+                blur.apply(&image.planes[0], image.width, image.height),
+                blur.apply(&image.planes[1], image.width, image.height),
+                blur.apply(&image.planes[2], image.width, image.height),
+            ],
+            width: image.width,
+            height: image.height,
+        };
+        let mu1 = blurred(reference);
+        let mu2 = blurred(distorted);
+        let sigma1_sq = blurred(&reference.zip_map(reference, |a, b| a * b));
+        let sigma2_sq = blurred(&distorted.zip_map(distorted, |a, b| a * b));
+        let sigma12 = blurred(&reference.zip_map(distorted, |a, b| a * b));
+
+        const C2: f64 = 0.0009;
+        let mut error = ScaleError::default();
+        let one_per_pixel = 1. / reference.planes[0].len() as f64;
+        let norms =
+            |sum: f64, sum4: f64| [one_per_pixel * sum, (one_per_pixel * sum4).sqrt().sqrt()];
+        for plane in 0..3 {
+            let mut ssim = [0.; 2];
+            let mut artifacts = [0.; 2];
+            let mut detail_lost = [0.; 2];
+            for i in 0..reference.planes[plane].len() {
+                let m1 = mu1.planes[plane][i] as f64;
+                let m2 = mu2.planes[plane][i] as f64;
+                let s11 = sigma1_sq.planes[plane][i] as f64 - m1 * m1;
+                let s22 = sigma2_sq.planes[plane][i] as f64 - m2 * m2;
+                let s12 = sigma12.planes[plane][i] as f64 - m1 * m2;
+
+                // Unlike SSIM, the luminance term has no denominator,
+                // because XYB is already perceptually uniform
+                let num_m = 1. - (m1 - m2) * (m1 - m2);
+                let num_s = 2. * s12 + C2;
+                let denom_s = s11 + s22 + C2;
+                let d = (1. - num_m * num_s / denom_s).max(0.);
+                ssim[0] += d;
+                ssim[1] += d.powi(4);
+
+                // Compare the local detail of each frame
+                let d1 = (1. + (distorted.planes[plane][i] - mu2.planes[plane][i]).abs() as f64)
+                    / (1. + (reference.planes[plane][i] - mu1.planes[plane][i]).abs() as f64)
+                    - 1.;
+                // The distorted frame has an edge where the reference is smooth
+                let artifact = d1.max(0.);
+                artifacts[0] += artifact;
+                artifacts[1] += artifact.powi(4);
+                // The reference has an edge where the distorted frame is smooth
+                let lost = (-d1).max(0.);
+                detail_lost[0] += lost;
+                detail_lost[1] += lost.powi(4);
+            }
+            error.ssim[plane] = norms(ssim[0], ssim[1]);
+            error.artifacts[plane] = norms(artifacts[0], artifacts[1]);
+            error.detail_lost[plane] = norms(detail_lost[0], detail_lost[1]);
+        }
+        error
+    }
+}
+
+/// Fuses the error at each scale into a single score.
+fn score(scales: &[ScaleError; SCALES]) -> f64 {
+    // The weight of each error, for each plane, scale, and norm
+    const WEIGHTS: [f64; 108] = [
+        0.0,
+        0.000_737_660_670_740_658_6,
+        0.0,
+        0.0,
+        0.000_779_348_168_286_730_9,
+        0.0,
+        0.0,
+        0.000_437_115_573_010_737_9,
+        0.0,
+        1.104_172_642_665_734_6,
+        0.000_662_848_341_292_71,
+        0.000_152_316_327_837_187_52,
+        0.0,
+        0.001_640_643_745_659_975_4,
+        0.0,
+        1.842_245_552_053_929_8,
+        11.441_172_603_757_666,
+        0.0,
+        0.000_798_910_943_601_516_3,
+        0.000_176_816_438_078_653,
+        0.0,
+        1.878_759_497_954_638_7,
+        10.949_069_906_051_42,
+        0.0,
+        0.000_728_934_699_150_807_2,
+        0.967_793_708_062_683_3,
+        0.0,
+        0.000_140_034_242_854_358_84,
+        0.998_176_697_785_496_7,
+        0.000_319_497_559_344_350_53,
+        0.000_455_099_211_379_206_3,
+        0.0,
+        0.0,
+        0.001_364_876_616_324_339_8,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        7.466_890_328_078_848,
+        0.0,
+        17.445_833_984_131_262,
+        0.000_623_560_163_404_146_6,
+        0.0,
+        0.0,
+        6.683_678_146_179_332,
+        0.000_377_244_079_796_112_96,
+        1.027_889_937_768_264,
+        225.205_153_008_492_74,
+        0.0,
+        0.0,
+        19.213_238_186_143_016,
+        0.001_140_152_458_661_836_1,
+        0.001_237_755_635_509_985,
+        176.393_175_984_506_94,
+        0.0,
+        0.0,
+        24.433_009_998_704_76,
+        0.285_208_026_121_177_57,
+        0.000_448_543_692_383_340_8,
+        0.0,
+        0.0,
+        0.0,
+        34.779_063_444_837_72,
+        44.835_625_328_877_896,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.000_868_055_657_329_169_8,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.000_531_319_187_435_874_7,
+        0.0,
+        0.000_165_338_141_613_791_12,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        0.000_417_917_180_325_133_6,
+        0.001_729_082_823_472_283_3,
+        0.0,
+        0.002_082_700_584_663_643_7,
+        0.0,
+        0.0,
+        8.826_982_764_996_862,
+        23.192_433_439_989_26,
+        0.0,
+        95.108_049_881_108_6,
+        0.986_397_803_440_068_2,
+        0.983_438_279_246_535_3,
+        0.001_228_640_504_827_849_3,
+        171.266_725_589_730_7,
+        0.980_785_887_243_537_9,
+        0.0,
+        0.0,
+        0.0,
+        0.000_513_006_458_899_067_9,
+        0.0,
+        0.000_108_540_578_584_115_37,
+    ];
+
+    let mut weights = WEIGHTS.iter();
+    let mut sum = 0.;
+    for plane in 0..3 {
+        for scale in scales {
+            for norm in 0..2 {
+                sum += weights.next().unwrap() * scale.ssim[plane][norm];
+                sum += weights.next().unwrap() * scale.artifacts[plane][norm];
+                sum += weights.next().unwrap() * scale.detail_lost[plane][norm];
+            }
+        }
+    }
+
+    // Map the weighted error to a score, using a curve fitted to subjective ratings
+    let sum = sum * 0.956_238_261_683_484_4;
+    let sum = 2.326_765_642_916_932 * sum - 0.020_884_521_182_843_837 * sum * sum
+        + 6.248_496_625_763_138e-5 * sum * sum * sum;
+    if sum > 0. {
+        100. - 10. * sum.powf(0.627_633_646_783_138_7)
+    } else {
+        100.
+    }
+}
+
+/// A recursive approximation of a Gaussian blur, as used by JPEG XL.
+///
+/// See Charalampidis, "Recursive Implementation of the Gaussian Filter
+/// Using Truncated Cosine Functions", IEEE Transactions on Signal Processing, 2016.
+struct RecursiveGaussian {
+    radius: isize,
+    n2: [f32; 3],
+    d1: [f32; 3],
+}
+
+impl RecursiveGaussian {
+    fn new(sigma: f64) -> Self {
+        let radius = (3.2795 * sigma + 0.2546).round();
+        let pi_div_2r = PI / (2. * radius);
+        let omega = [pi_div_2r, 3. * pi_div_2r, 5. * pi_div_2r];
+
+        let p1 = 1. / (0.5 * omega[0]).tan();
+        let p3 = -1. / (0.5 * omega[1]).tan();
+        let p5 = 1. / (0.5 * omega[2]).tan();
+        let r1 = p1 * p1 / omega[0].sin();
+        let r3 = -p3 * p3 / omega[1].sin();
+        let r5 = p5 * p5 / omega[2].sin();
+
+        let rho = omega.map(|omega| (-0.5 * sigma * sigma * omega * omega).exp() / radius);
+        let d13 = p1 * r3 - r1 * p3;
+        let d35 = p3 * r5 - r3 * p5;
+        let d51 = p5 * r1 - r5 * p1;
+        let zeta15 = d35 / d13;
+        let zeta35 = d51 / d13;
+
+        // Solve for the weights of each cosine, which normalize the filter
+        let a = [[p1, p3, p5], [r1, r3, r5], [zeta15, zeta35, 1.]];
+        let gamma = [
+            1.,
+            radius * radius - sigma * sigma,
+            zeta15 * rho[0] + zeta35 * rho[1] + rho[2],
+        ];
+        let beta = solve_3x3(a, gamma);
+        debug_assert!((beta[0] * p1 + beta[1] * p3 + beta[2] * p5 - 1.).abs() < 1e-12);
+
+        RecursiveGaussian {
+            radius: radius as isize,
+            n2: [0, 1, 2].map(|i| (-beta[i] * (omega[i] * (radius + 1.)).cos()) as f32),
+            d1: omega.map(|omega| (-2. * omega.cos()) as f32),
+        }
+    }
+
+    /// Blurs a plane horizontally, then vertically.
+    fn apply(&self, src: &[f32], width: usize, height: usize) -> Vec<f32> {
+        let mut horizontal = vec![0.; width * height];
+        for (src, dst) in src.chunks(width).zip(horizontal.chunks_mut(width)) {
+            self.filter(width, |i| src[i], |i, value| dst[i] = value);
+        }
+        let mut dst = vec![0.; width * height];
+        for x in 0..width {
+            self.filter(
+                height,
+                |i| horizontal[i * width + x],
+                |i, value| dst[i * width + x] = value,
+            );
+        }
+        dst
+    }
+
+    /// Filters one row or column, treating samples outside of it as zero.
+    fn filter(&self, len: usize, src: impl Fn(usize) -> f32, mut dst: impl FnMut(usize, f32)) {
+        let len = len as isize;
+        let n = self.radius;
+        let mut prev = [0f32; 3];
+        let mut prev2 = [0f32; 3];
+        for i in (1 - n)..len {
+            let left = i - n - 1;
+            let right = i + n - 1;
+            let sum = if left >= 0 { src(left as usize) } else { 0. }
+                + if right < len { src(right as usize) } else { 0. };
+            let mut out = [0f32; 3];
+            for k in 0..3 {
+                out[k] = sum * self.n2[k] - self.d1[k] * prev[k] - prev2[k];
+                prev2[k] = prev[k];
+                prev[k] = out[k];
+            }
+            if i >= 0 {
+                dst(i as usize, out[0] + out[1] + out[2]);
+            }
+        }
+    }
+}
+
+/// Solves `a * x = b` for `x` with Cramer's rule.
+fn solve_3x3(a: [[f64; 3]; 3], b: [f64; 3]) -> [f64; 3] {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let det_a = det(a);
+    [0, 1, 2].map(|column| {
+        let mut m = a;
+        for row in 0..3 {
+            m[row][column] = b[row];
+        }
+        det(m) / det_a
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::MatrixCoefficients;
+    use std::fs::File;
+
+    fn run(name: &str) -> VideoMetricResults<f64> {
+        let mut file1 = File::open(format!("./testfiles/{}_input.y4m", name)).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open(format!("./testfiles/{}_output.y4m", name)).unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        calculate_video_ssimulacra2(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn ssimulacra2_identical_frames() {
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        assert_metric_eq(100.0, calculate_frame_ssimulacra2(&frame, &frame).unwrap());
+    }
+
+    #[test]
+    fn ssimulacra2_blur_is_normalized() {
+        // Far from the edges, a blurred constant is unchanged
+        let blur = RecursiveGaussian::new(BLUR_SIGMA);
+        let blurred = blur.apply(&[1.; 64 * 64], 64, 64);
+        assert!((blurred[32 * 64 + 32] - 1.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ssimulacra2_yuv420p8() {
+        let result = run("yuv420p8");
+        assert_eq!(3, result.frames.len());
+        assert_metric_eq(38.7360, result.aggregate);
+    }
+
+    #[test]
+    fn ssimulacra2_yuv420p10() {
+        assert_metric_eq(39.9409, run("yuv420p10").aggregate);
+    }
+
+    #[test]
+    fn ssimulacra2_yuv444p8() {
+        assert_metric_eq(43.3273, run("yuv444p8").aggregate);
+    }
+
+    #[test]
+    fn ssimulacra2_yuv400p8() {
+        assert_metric_eq(46.6520, run("yuv400p8").aggregate);
+    }
+
+    #[test]
+    fn ssimulacra2_color_description() {
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let options = VideoOptions {
+            matrix_coefficients: Some(MatrixCoefficients::BT601),
+            ..Default::default()
+        };
+        let result = calculate_video_ssimulacra2(&mut dec1, &mut dec2, &options).unwrap();
+        assert_metric_eq(38.1482, result.aggregate);
+    }
+
+    #[test]
+    fn ssimulacra2_rejects_hdr() {
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        let pq = ColorDescription {
+            transfer_characteristics: TransferCharacteristics::PQ,
+            ..Default::default()
+        };
+        let metric = Ssimulacra2::with_color_descriptions(pq, pq);
+        assert!(metric
+            .process_frame(&FrameRef::from(&frame), &FrameRef::from(&frame))
+            .is_err());
+    }
+}
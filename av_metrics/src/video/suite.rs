@@ -0,0 +1,432 @@
+//! Calculates multiple metrics in a single pass over two videos.
+//!
+//! Decoding a video is often more expensive than calculating a metric on it.
+//! This module allows several metrics to be calculated while decoding
+//! each input only once.
+
+use crate::video::butteraugli::{Butteraugli, ButteraugliScore};
+use crate::video::ciede::Ciede2000;
+use crate::video::decode::Decoder;
+use crate::video::deitp::DeltaEItp;
+use crate::video::pixel::Pixel;
+use crate::video::psnr::{Psnr, PsnrMetrics};
+use crate::video::psnr_hvs::PsnrHvs;
+use crate::video::ssim::{MsSsim, Ssim};
+use crate::video::ssimulacra2::Ssimulacra2;
+use crate::video::vif::{Vif, VifMetrics};
+use crate::video::{
+    ComparisonInfo, FrameCounts, FrameRef, PlanarMetrics, ResyncReport, UnweightedPlanarMetrics,
+    VideoMetric, VideoMetricResults, VideoOptions,
+};
+use std::error::Error;
+
+/// The metrics which may be calculated as part of a suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// Peak Signal-to-Noise Ratio. See [`psnr`](../psnr/index.html).
+    Psnr,
+    /// Average Peak Signal-to-Noise Ratio. See [`psnr`](../psnr/index.html).
+    APsnr,
+    /// PSNR accounting for the Human Visual System. See [`psnr_hvs`](../psnr_hvs/index.html).
+    PsnrHvs,
+    /// Structural Similarity index. See [`ssim`](../ssim/index.html).
+    Ssim,
+    /// Multi-Scale Structural Similarity index. See [`ssim`](../ssim/index.html).
+    MsSsim,
+    /// The CIEDE2000 color difference formula. See [`ciede`](../ciede/index.html).
+    Ciede2000,
+    /// The ΔE ITP color difference formula. See [`deitp`](../deitp/index.html).
+    DeltaEItp,
+    /// Visual Information Fidelity. See [`vif`](../vif/index.html).
+    Vif,
+    /// The SSIMULACRA2 perceptual metric. See [`ssimulacra2`](../ssimulacra2/index.html).
+    Ssimulacra2,
+    /// The Butteraugli psychovisual distance. See [`butteraugli`](../butteraugli/index.html).
+    Butteraugli,
+}
+
+impl Metric {
+    /// Every metric which is supported by the suite.
+    pub const ALL: [Metric; 10] = [
+        Metric::Psnr,
+        Metric::APsnr,
+        Metric::PsnrHvs,
+        Metric::Ssim,
+        Metric::MsSsim,
+        Metric::Ciede2000,
+        Metric::DeltaEItp,
+        Metric::Vif,
+        Metric::Ssimulacra2,
+        Metric::Butteraugli,
+    ];
+}
+
+/// The combined results of a metric suite.
+///
+/// Each field will be `Some` only if the corresponding metric was requested.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SuiteResults {
+    /// Results for PSNR.
+    pub psnr: Option<VideoMetricResults<PlanarMetrics>>,
+    /// Results for APSNR.
+    pub apsnr: Option<VideoMetricResults<PlanarMetrics>>,
+    /// Results for PSNR-HVS.
+    pub psnr_hvs: Option<VideoMetricResults<PlanarMetrics>>,
+    /// Results for SSIM.
+    pub ssim: Option<VideoMetricResults<PlanarMetrics>>,
+    /// Results for MSSSIM.
+    pub msssim: Option<VideoMetricResults<PlanarMetrics>>,
+    /// Results for CIEDE2000.
+    pub ciede2000: Option<VideoMetricResults<f64>>,
+    /// Results for ΔE ITP.
+    pub deitp: Option<VideoMetricResults<f64>>,
+    /// Results for VIF.
+    pub vif: Option<VideoMetricResults<VifMetrics>>,
+    /// Results for SSIMULACRA2.
+    pub ssimulacra2: Option<VideoMetricResults<f64>>,
+    /// Results for Butteraugli.
+    pub butteraugli: Option<VideoMetricResults<ButteraugliScore>>,
+    /// The number of frames in each input video, if known.
+    /// This is identical to the `frame_counts` of each metric's results.
+    pub frame_counts: Option<FrameCounts>,
+    /// How the frames of the two videos were matched, if they were re-synced.
+    /// This is identical to the `resync` of each metric's results.
+    pub resync: Option<ResyncReport>,
+}
+
+/// Calculates each of the requested `metrics` between two videos.
+///
+/// Each frame is decoded only once, then passed to every requested metric.
+/// The results are identical to those from calling each metric's
+/// `calculate_video_*` function individually.
+///
+/// See [`VideoOptions`](../struct.VideoOptions.html) for options controlling
+/// which frames are compared.
+#[inline]
+pub fn calculate_video_suite<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    metrics: &[Metric],
+    options: &VideoOptions,
+) -> Result<SuiteResults, Box<dyn Error + Send + Sync>> {
+    let mut suite = MetricSuite::new(metrics);
+    suite.ciede2000 = suite
+        .ciede2000
+        .map(|ciede2000| ciede2000.for_videos(decoder1, decoder2, options));
+    suite.deitp = suite
+        .deitp
+        .map(|deitp| deitp.for_videos(decoder1, decoder2, options));
+    suite.ssimulacra2 = suite
+        .ssimulacra2
+        .map(|ssimulacra2| ssimulacra2.for_videos(decoder1, decoder2, options));
+    suite.butteraugli = suite
+        .butteraugli
+        .map(|butteraugli| butteraugli.for_videos(decoder1, decoder2, options));
+    suite.process_video(decoder1, decoder2, options)
+}
+
+struct MetricSuite {
+    metrics: Vec<Metric>,
+    psnr: Option<Psnr>,
+    psnr_hvs: Option<PsnrHvs>,
+    ssim: Option<Ssim>,
+    msssim: Option<MsSsim>,
+    ciede2000: Option<Ciede2000>,
+    deitp: Option<DeltaEItp>,
+    vif: Option<Vif>,
+    ssimulacra2: Option<Ssimulacra2>,
+    butteraugli: Option<Butteraugli>,
+}
+
+impl MetricSuite {
+    fn new(metrics: &[Metric]) -> Self {
+        let has = |metric| metrics.contains(&metric);
+        MetricSuite {
+            metrics: metrics.to_vec(),
+            psnr: if has(Metric::Psnr) || has(Metric::APsnr) {
+                Some(Psnr)
+            } else {
+                None
+            },
+            psnr_hvs: if has(Metric::PsnrHvs) {
+                Some(PsnrHvs)
+            } else {
+                None
+            },
+            ssim: if has(Metric::Ssim) { Some(Ssim) } else { None },
+            msssim: if has(Metric::MsSsim) {
+                Some(MsSsim)
+            } else {
+                None
+            },
+            ciede2000: if has(Metric::Ciede2000) {
+                Some(Ciede2000::default())
+            } else {
+                None
+            },
+            deitp: if has(Metric::DeltaEItp) {
+                Some(DeltaEItp::default())
+            } else {
+                None
+            },
+            vif: if has(Metric::Vif) { Some(Vif) } else { None },
+            ssimulacra2: if has(Metric::Ssimulacra2) {
+                Some(Ssimulacra2::default())
+            } else {
+                None
+            },
+            butteraugli: if has(Metric::Butteraugli) {
+                Some(Butteraugli::default())
+            } else {
+                None
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SuiteFrameResult {
+    psnr: Option<[PsnrMetrics; 3]>,
+    psnr_hvs: Option<UnweightedPlanarMetrics>,
+    ssim: Option<UnweightedPlanarMetrics>,
+    msssim: Option<UnweightedPlanarMetrics>,
+    ciede2000: Option<f64>,
+    deitp: Option<f64>,
+    vif: Option<VifMetrics>,
+    ssimulacra2: Option<f64>,
+    butteraugli: Option<ButteraugliScore>,
+}
+
+impl VideoMetric for MetricSuite {
+    type FrameResult = SuiteFrameResult;
+    type VideoResult = SuiteResults;
+
+    fn process_frame<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        let mut result = SuiteFrameResult::default();
+        if let Some(psnr) = self.psnr.as_ref() {
+            result.psnr = Some(psnr.process_frame(frame1, frame2)?);
+        }
+        if let Some(psnr_hvs) = self.psnr_hvs.as_ref() {
+            result.psnr_hvs = Some(psnr_hvs.process_frame(frame1, frame2)?);
+        }
+        if let Some(ssim) = self.ssim.as_ref() {
+            result.ssim = Some(ssim.process_frame(frame1, frame2)?);
+        }
+        if let Some(msssim) = self.msssim.as_ref() {
+            result.msssim = Some(msssim.process_frame(frame1, frame2)?);
+        }
+        if let Some(ciede2000) = self.ciede2000.as_ref() {
+            result.ciede2000 = Some(ciede2000.process_frame(frame1, frame2)?);
+        }
+        if let Some(deitp) = self.deitp.as_ref() {
+            result.deitp = Some(deitp.process_frame(frame1, frame2)?);
+        }
+        if let Some(vif) = self.vif.as_ref() {
+            result.vif = Some(vif.process_frame(frame1, frame2)?);
+        }
+        if let Some(ssimulacra2) = self.ssimulacra2.as_ref() {
+            result.ssimulacra2 = Some(ssimulacra2.process_frame(frame1, frame2)?);
+        }
+        if let Some(butteraugli) = self.butteraugli.as_ref() {
+            result.butteraugli = Some(butteraugli.process_frame(frame1, frame2)?);
+        }
+        Ok(result)
+    }
+
+    fn aggregate_frame_results(
+        &self,
+        metrics: &[Self::FrameResult],
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let mut results = SuiteResults {
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+            ..Default::default()
+        };
+        if let Some(psnr) = self.psnr.as_ref() {
+            let psnr_results = psnr.aggregate_frame_results(
+                &metrics.iter().filter_map(|m| m.psnr).collect::<Vec<_>>(),
+                info,
+            )?;
+            if self.metrics.contains(&Metric::Psnr) {
+                results.psnr = Some(psnr_results.psnr);
+            }
+            if self.metrics.contains(&Metric::APsnr) {
+                results.apsnr = Some(psnr_results.apsnr);
+            }
+        }
+        if let Some(psnr_hvs) = self.psnr_hvs.as_ref() {
+            results.psnr_hvs = Some(
+                psnr_hvs.aggregate_frame_results(
+                    &metrics
+                        .iter()
+                        .filter_map(|m| m.psnr_hvs)
+                        .collect::<Vec<_>>(),
+                    info,
+                )?,
+            );
+        }
+        if let Some(ssim) = self.ssim.as_ref() {
+            results.ssim = Some(ssim.aggregate_frame_results(
+                &metrics.iter().filter_map(|m| m.ssim).collect::<Vec<_>>(),
+                info,
+            )?);
+        }
+        if let Some(msssim) = self.msssim.as_ref() {
+            results.msssim = Some(msssim.aggregate_frame_results(
+                &metrics.iter().filter_map(|m| m.msssim).collect::<Vec<_>>(),
+                info,
+            )?);
+        }
+        if let Some(ciede2000) = self.ciede2000.as_ref() {
+            results.ciede2000 = Some(
+                ciede2000.aggregate_frame_results(
+                    &metrics
+                        .iter()
+                        .filter_map(|m| m.ciede2000)
+                        .collect::<Vec<_>>(),
+                    info,
+                )?,
+            );
+        }
+        if let Some(deitp) = self.deitp.as_ref() {
+            results.deitp = Some(deitp.aggregate_frame_results(
+                &metrics.iter().filter_map(|m| m.deitp).collect::<Vec<_>>(),
+                info,
+            )?);
+        }
+        if let Some(vif) = self.vif.as_ref() {
+            results.vif = Some(vif.aggregate_frame_results(
+                &metrics.iter().filter_map(|m| m.vif).collect::<Vec<_>>(),
+                info,
+            )?);
+        }
+        if let Some(ssimulacra2) = self.ssimulacra2.as_ref() {
+            results.ssimulacra2 = Some(
+                ssimulacra2.aggregate_frame_results(
+                    &metrics
+                        .iter()
+                        .filter_map(|m| m.ssimulacra2)
+                        .collect::<Vec<_>>(),
+                    info,
+                )?,
+            );
+        }
+        if let Some(butteraugli) = self.butteraugli.as_ref() {
+            results.butteraugli = Some(
+                butteraugli.aggregate_frame_results(
+                    &metrics
+                        .iter()
+                        .filter_map(|m| m.butteraugli)
+                        .collect::<Vec<_>>(),
+                    info,
+                )?,
+            );
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::{psnr, ssim};
+    use std::fs::File;
+
+    #[test]
+    fn suite_yuv420p8() {
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result = calculate_video_suite::<_>(
+            &mut dec1,
+            &mut dec2,
+            &Metric::ALL,
+            &VideoOptions::default(),
+        )
+        .unwrap();
+        let psnr = result.psnr.unwrap().aggregate;
+        assert_metric_eq(32.5281, psnr.y);
+        assert_metric_eq(36.4083, psnr.u);
+        assert_metric_eq(39.8238, psnr.v);
+        assert_metric_eq(33.6861, psnr.avg);
+        let apsnr = result.apsnr.unwrap().aggregate;
+        assert_metric_eq(32.5450, apsnr.y);
+        assert_metric_eq(36.4087, apsnr.u);
+        assert_metric_eq(39.8244, apsnr.v);
+        assert_metric_eq(33.6995, apsnr.avg);
+        let psnr_hvs = result.psnr_hvs.unwrap().aggregate;
+        assert_metric_eq(34.3227, psnr_hvs.y);
+        assert_metric_eq(37.7400, psnr_hvs.u);
+        assert_metric_eq(40.5570, psnr_hvs.v);
+        assert_metric_eq(31.8676, psnr_hvs.avg);
+        let ssim = result.ssim.unwrap().aggregate;
+        assert_metric_eq(13.2572, ssim.y);
+        assert_metric_eq(10.8624, ssim.u);
+        assert_metric_eq(12.8369, ssim.v);
+        assert_metric_eq(12.6899, ssim.avg);
+        let msssim = result.msssim.unwrap().aggregate;
+        assert_metric_eq(18.8343, msssim.y);
+        assert_metric_eq(16.6943, msssim.u);
+        assert_metric_eq(18.7662, msssim.v);
+        assert_metric_eq(18.3859, msssim.avg);
+        assert_metric_eq(36.2821, result.ciede2000.unwrap().aggregate);
+        assert_metric_eq(10.1424, result.deitp.unwrap().aggregate);
+        assert_metric_eq(0.4790, result.vif.unwrap().aggregate.total);
+        assert_metric_eq(38.7360, result.ssimulacra2.unwrap().aggregate);
+        let butteraugli = result.butteraugli.unwrap().aggregate;
+        assert_metric_eq(8.9863, butteraugli.max_norm);
+        assert_metric_eq(2.1416, butteraugli.three_norm);
+    }
+
+    #[test]
+    fn suite_subset_yuv420p10() {
+        let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result = calculate_video_suite::<_>(
+            &mut dec1,
+            &mut dec2,
+            &[Metric::APsnr, Metric::Ssim],
+            &VideoOptions::default(),
+        )
+        .unwrap();
+        assert!(result.psnr.is_none());
+        assert!(result.psnr_hvs.is_none());
+        assert!(result.msssim.is_none());
+        assert!(result.ciede2000.is_none());
+        assert!(result.deitp.is_none());
+        assert!(result.vif.is_none());
+        assert!(result.ssimulacra2.is_none());
+        assert!(result.butteraugli.is_none());
+
+        let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let expected =
+            psnr::calculate_video_apsnr(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        let apsnr = result.apsnr.unwrap();
+        assert_eq!(expected.frames.len(), apsnr.frames.len());
+        assert_metric_eq(expected.aggregate.avg, apsnr.aggregate.avg);
+
+        let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let expected =
+            ssim::calculate_video_ssim(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        let ssim = result.ssim.unwrap();
+        assert_eq!(expected.frames.len(), ssim.frames.len());
+        assert_metric_eq(expected.aggregate.avg, ssim.aggregate.avg);
+    }
+}
@@ -0,0 +1,323 @@
+//! Visual Information Fidelity.
+//!
+//! VIF models the reference image as the output of a natural scene source,
+//! and the distorted image as the reference passed through a distortion channel.
+//! It measures how much of the information which the human visual system can
+//! extract from the reference is still present in the distorted image.
+//! Unlike SSIM, VIF explicitly accounts for detail which was lost or blurred away.
+//!
+//! This is the pixel-domain, multi-scale form of VIF, calculated on the luma plane.
+//! Scores are generally between 0 and 1, where higher is better and 1 means
+//! that no information was lost. Contrast enhancement may produce scores above 1.
+//!
+//! See H. R. Sheikh and A. C. Bovik, "Image information and visual quality",
+//! IEEE Transactions on Image Processing, 2006, for more details.
+
+#[cfg(feature = "decode")]
+use crate::video::decode::Decoder;
+use crate::video::pixel::CastFromPrimitive;
+use crate::video::pixel::Pixel;
+use crate::video::ssim::build_gaussian_kernel;
+#[cfg(feature = "decode")]
+use crate::video::VideoOptions;
+use crate::video::{ComparisonInfo, FrameRef, PlaneRef, VideoMetric, VideoMetricResults};
+use crate::MetricsError;
+use std::error::Error;
+
+/// The number of scales over which VIF is measured.
+const SCALES: usize = 4;
+/// The variance of the noise in the HVS model, for 8-bit samples.
+const SIGMA_NSQ: f64 = 2.0;
+const EPSILON: f64 = 1e-10;
+const KERNEL_WEIGHT: usize = 1 << 16;
+
+/// Calculates the VIF score between two videos. Higher is better.
+///
+/// The aggregate score at each scale is the mean of the per-frame scores.
+#[cfg(feature = "decode")]
+#[inline]
+pub fn calculate_video_vif<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<VifMetrics>, Box<dyn Error + Send + Sync>> {
+    Vif.process_video(decoder1, decoder2, options)
+}
+
+/// Calculates the VIF score between two video frames. Higher is better.
+#[inline]
+pub fn calculate_frame_vif<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<VifMetrics, Box<dyn Error + Send + Sync>> {
+    Vif.process_frame(&frame1.into(), &frame2.into())
+}
+
+/// The VIF score of a frame or video.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VifMetrics {
+    /// The score at each scale, from finest to coarsest.
+    pub scales: [f64; SCALES],
+    /// The score over all scales, which is the ratio of the total information
+    /// in the distorted image to the total information in the reference.
+    pub total: f64,
+}
+
+/// The VIF metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vif;
+
+impl VideoMetric for Vif {
+    type FrameResult = VifMetrics;
+    type VideoResult = VideoMetricResults<VifMetrics>;
+
+    fn process_frame<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
+
+        let mut reference = VifPlane::new(&frame1.planes[0], frame1.bit_depth);
+        let mut distorted = VifPlane::new(&frame2.planes[0], frame2.bit_depth);
+        let mut scales = [0.; SCALES];
+        let (mut num_sum, mut den_sum) = (0., 0.);
+        for (scale, score) in scales.iter_mut().enumerate() {
+            // The kernel covers 17, 9, 5 and 3 pixels at each scale
+            let len = (1 << (SCALES - scale)) + 1;
+            let kernel = build_gaussian_kernel(len as f64 / 5., len / 2 + 1, KERNEL_WEIGHT)
+                .into_iter()
+                .map(|weight| weight as f64 / KERNEL_WEIGHT as f64)
+                .collect::<Vec<_>>();
+
+            if scale > 0 {
+                reference = reference.filter(&kernel)?.decimate();
+                distorted = distorted.filter(&kernel)?.decimate();
+            }
+            let (num, den) = plane_information(&reference, &distorted, &kernel)?;
+            *score = if den == 0. { 1. } else { num / den };
+            num_sum += num;
+            den_sum += den;
+        }
+
+        Ok(VifMetrics {
+            scales,
+            total: if den_sum == 0. { 1. } else { num_sum / den_sum },
+        })
+    }
+
+    fn aggregate_frame_results(
+        &self,
+        metrics: &[Self::FrameResult],
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let mean = |f: &dyn Fn(&VifMetrics) -> f64| {
+            metrics.iter().map(f).sum::<f64>() / metrics.len() as f64
+        };
+        let mut scales = [0.; SCALES];
+        for (scale, score) in scales.iter_mut().enumerate() {
+            *score = mean(&|m| m.scales[scale]);
+        }
+        Ok(VideoMetricResults {
+            aggregate: VifMetrics {
+                scales,
+                total: mean(&|m| m.total),
+            },
+            frames: metrics.to_vec(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+        })
+    }
+}
+
+/// Returns the information in the distorted and reference images at one scale.
+fn plane_information(
+    reference: &VifPlane,
+    distorted: &VifPlane,
+    kernel: &[f64],
+) -> Result<(f64, f64), MetricsError> {
+    let mu1 = reference.filter(kernel)?;
+    let mu2 = distorted.filter(kernel)?;
+    let ref_sq = reference.map(|x| x * x).filter(kernel)?;
+    let dis_sq = distorted.map(|x| x * x).filter(kernel)?;
+    let ref_dis = reference.zip_map(distorted, |x, y| x * y).filter(kernel)?;
+
+    let mut num = 0.;
+    let mut den = 0.;
+    for i in 0..mu1.data.len() {
+        let mut sigma1_sq = (ref_sq.data[i] - mu1.data[i] * mu1.data[i]).max(0.);
+        let sigma2_sq = (dis_sq.data[i] - mu2.data[i] * mu2.data[i]).max(0.);
+        let sigma12 = ref_dis.data[i] - mu1.data[i] * mu2.data[i];
+
+        // The gain and additive noise of the distortion channel
+        let mut g = sigma12 / (sigma1_sq + EPSILON);
+        let mut sv_sq = sigma2_sq - g * sigma12;
+        if sigma1_sq < EPSILON {
+            g = 0.;
+            sv_sq = sigma2_sq;
+            sigma1_sq = 0.;
+        }
+        if sigma2_sq < EPSILON {
+            g = 0.;
+            sv_sq = 0.;
+        }
+        if g < 0. {
+            sv_sq = sigma2_sq;
+            g = 0.;
+        }
+        let sv_sq = sv_sq.max(EPSILON);
+
+        num += (1. + g * g * sigma1_sq / (sv_sq + SIGMA_NSQ)).log10();
+        den += (1. + sigma1_sq / SIGMA_NSQ).log10();
+    }
+    Ok((num, den))
+}
+
+/// A luma plane converted to floating point and scaled to the 8-bit range.
+struct VifPlane {
+    data: Vec<f64>,
+    width: usize,
+    height: usize,
+}
+
+impl VifPlane {
+    fn new<T: Pixel>(plane: &PlaneRef<T>, bit_depth: usize) -> Self {
+        let scale = 256. / (1u64 << bit_depth) as f64;
+        let (width, height) = (plane.width(), plane.height());
+        let mut data = Vec::with_capacity(width * height);
+        for row in plane.rows() {
+            data.extend(
+                row.iter()
+                    .map(|&sample| u16::cast_from(sample) as f64 * scale),
+            );
+        }
+        VifPlane {
+            data,
+            width,
+            height,
+        }
+    }
+
+    fn map(&self, f: impl Fn(f64) -> f64) -> VifPlane {
+        VifPlane {
+            data: self.data.iter().map(|&x| f(x)).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    fn zip_map(&self, other: &VifPlane, f: impl Fn(f64, f64) -> f64) -> VifPlane {
+        VifPlane {
+            data: self
+                .data
+                .iter()
+                .zip(other.data.iter())
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Applies a separable filter, keeping only the samples where
+    /// the kernel lies entirely within the plane.
+    fn filter(&self, kernel: &[f64]) -> Result<VifPlane, MetricsError> {
+        if self.width < kernel.len() || self.height < kernel.len() {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Frames are too small to calculate VIF",
+            });
+        }
+        let width = self.width - kernel.len() + 1;
+        let height = self.height - kernel.len() + 1;
+
+        let mut horizontal = Vec::with_capacity(width * self.height);
+        for row in self.data.chunks(self.width) {
+            horizontal.extend(
+                row.windows(kernel.len())
+                    .map(|window| dot(window.iter().copied(), kernel)),
+            );
+        }
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            data.extend((0..width).map(|x| {
+                let column = (y..y + kernel.len()).map(|row| horizontal[row * width + x]);
+                dot(column, kernel)
+            }));
+        }
+        Ok(VifPlane {
+            data,
+            width,
+            height,
+        })
+    }
+
+    /// Keeps every other sample in each dimension.
+    fn decimate(&self) -> VifPlane {
+        let (width, height) = ((self.width + 1) / 2, (self.height + 1) / 2);
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = &self.data[2 * y * self.width..][..self.width];
+            data.extend(row.iter().step_by(2));
+        }
+        VifPlane {
+            data,
+            width,
+            height,
+        }
+    }
+}
+
+fn dot(samples: impl Iterator<Item = f64>, kernel: &[f64]) -> f64 {
+    samples
+        .zip(kernel)
+        .map(|(sample, weight)| sample * weight)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use std::fs::File;
+
+    #[test]
+    fn vif_identical_frames() {
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        let result = calculate_frame_vif(&frame, &frame).unwrap();
+        for &scale in &result.scales {
+            assert_metric_eq(1.0, scale);
+        }
+        assert_metric_eq(1.0, result.total);
+    }
+
+    #[test]
+    fn vif_yuv420p8() {
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_vif::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(0.3920, result.aggregate.scales[0]);
+        assert_metric_eq(0.8102, result.aggregate.scales[1]);
+        assert_metric_eq(0.8959, result.aggregate.scales[2]);
+        assert_metric_eq(0.9369, result.aggregate.scales[3]);
+        assert_metric_eq(0.4790, result.aggregate.total);
+    }
+
+    #[test]
+    fn vif_yuv420p10() {
+        let mut file1 = File::open("./testfiles/yuv420p10_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p10_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let result =
+            calculate_video_vif::<_>(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap();
+        assert_metric_eq(0.4794, result.aggregate.total);
+    }
+}
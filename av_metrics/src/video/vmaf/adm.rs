@@ -0,0 +1,239 @@
+//! The Additive Distortion Measure, also known as the Detail Loss Metric.
+//!
+//! Each frame is decomposed with a 4-level Daubechies wavelet transform.
+//! The distorted wavelet coefficients are separated into detail which was
+//! preserved from the reference and additive impairments. The preserved detail
+//! is weighted by a contrast sensitivity function and masked by the impairments,
+//! then compared to the detail in the reference.
+
+use super::{mirror, LumaPlane};
+use std::f64::consts::PI;
+
+/// The maximum gain which the distorted image may apply to the reference.
+const ENHANCEMENT_GAIN_LIMIT: f32 = 100.0;
+/// The fraction of each band excluded from the score at its edges.
+const BORDER_FACTOR: f64 = 0.1;
+/// The viewing distance, as a multiple of the display height.
+const VIEW_DISTANCE: f64 = 3.0;
+/// The height of the display, in pixels.
+const DISPLAY_HEIGHT: f64 = 1080.0;
+
+const DB2_LO: [f32; 4] = [0.482_962_9, 0.836_516_3, 0.224_143_87, -0.129_409_52];
+const DB2_HI: [f32; 4] = [-0.129_409_52, -0.224_143_87, 0.836_516_3, -0.482_962_9];
+
+/// Calculates ADM between two frames.
+pub(super) fn adm(reference: &LumaPlane, distorted: &LumaPlane) -> f64 {
+    let mut num = 0.;
+    let mut den = 0.;
+    let mut reference = reference.clone();
+    let mut distorted = distorted.clone();
+    let area = (reference.width * reference.height) as f64;
+    for scale in 0..4 {
+        let ref_bands = dwt2(&reference);
+        let dis_bands = dwt2(&distorted);
+
+        // Horizontal and vertical detail share a weight, and diagonal detail uses another
+        let csf = [
+            1. / quantization_step(scale, 1),
+            1. / quantization_step(scale, 1),
+            1. / quantization_step(scale, 2),
+        ];
+        let (restored, impairments) = decouple(&ref_bands, &dis_bands);
+        let impairments = [
+            impairments[0].map(|x| x * csf[0]),
+            impairments[1].map(|x| x * csf[1]),
+            impairments[2].map(|x| x * csf[2]),
+        ];
+        let masking = masking_threshold(&impairments);
+
+        for theta in 0..3 {
+            num += sum_cubes(&restored[theta], |i, x| {
+                ((x * csf[theta]).abs() - masking.data[i]).max(0.)
+            });
+            den += sum_cubes(&ref_bands.details[theta], |_, x| (x * csf[theta]).abs());
+        }
+
+        reference = ref_bands.approximation;
+        distorted = dis_bands.approximation;
+    }
+
+    let limit = 1e-10 * area / (1920. * 1080.);
+    let num = if num < limit { 0. } else { num };
+    let den = if den < limit { 0. } else { den };
+    if den == 0. {
+        1.
+    } else {
+        num / den
+    }
+}
+
+/// One level of a 2D wavelet transform.
+struct Dwt2 {
+    approximation: LumaPlane,
+    /// The horizontal, vertical and diagonal detail bands.
+    details: [LumaPlane; 3],
+}
+
+/// Performs one level of the Daubechies 2 wavelet transform.
+fn dwt2(src: &LumaPlane) -> Dwt2 {
+    let (width, height) = ((src.width + 1) / 2, (src.height + 1) / 2);
+    let band = || LumaPlane {
+        data: vec![0.; width * height],
+        width,
+        height,
+    };
+    let (mut a, mut h, mut v, mut d) = (band(), band(), band(), band());
+    let mut lo = vec![0.; src.width];
+    let mut hi = vec![0.; src.width];
+    for y in 0..height {
+        let rows = taps(y, src.height);
+        for x in 0..src.width {
+            let samples = rows.map(|row| src.data[row * src.width + x]);
+            lo[x] = dot(&DB2_LO, &samples);
+            hi[x] = dot(&DB2_HI, &samples);
+        }
+        for x in 0..width {
+            let cols = taps(x, src.width);
+            let lo = cols.map(|col| lo[col]);
+            let hi = cols.map(|col| hi[col]);
+            let i = y * width + x;
+            a.data[i] = dot(&DB2_LO, &lo);
+            v.data[i] = dot(&DB2_HI, &lo);
+            h.data[i] = dot(&DB2_LO, &hi);
+            d.data[i] = dot(&DB2_HI, &hi);
+        }
+    }
+    Dwt2 {
+        approximation: a,
+        details: [h, v, d],
+    }
+}
+
+/// The indices of the four samples which contribute to output sample `i`.
+fn taps(i: usize, len: usize) -> Taps {
+    let i = 2 * i as isize;
+    Taps([
+        mirror(i - 1, len),
+        mirror(i, len),
+        mirror(i + 1, len),
+        mirror(i + 2, len),
+    ])
+}
+
+#[derive(Clone, Copy)]
+struct Taps([usize; 4]);
+
+impl Taps {
+    fn map(self, f: impl Fn(usize) -> f32) -> [f32; 4] {
+        [f(self.0[0]), f(self.0[1]), f(self.0[2]), f(self.0[3])]
+    }
+}
+
+fn dot(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3]
+}
+
+/// Splits the distorted detail bands into the detail restored from the reference,
+/// and the impairments which were added to it.
+fn decouple(reference: &Dwt2, distorted: &Dwt2) -> ([LumaPlane; 3], [LumaPlane; 3]) {
+    let cos_1deg_sq = (1.0f32.to_radians().cos()).powi(2);
+    let [oh, ov, od] = &reference.details;
+    let [th, tv, td] = &distorted.details;
+    let mut restored = [oh.clone(), ov.clone(), od.clone()];
+    let mut impairments = [oh.clone(), ov.clone(), od.clone()];
+
+    for i in 0..oh.data.len() {
+        let o = [oh.data[i], ov.data[i], od.data[i]];
+        let t = [th.data[i], tv.data[i], td.data[i]];
+
+        // Distortions which only scale the reference, without rotating it,
+        // are treated as lost or enhanced detail rather than impairments
+        let ot_dp = o[0] * t[0] + o[1] * t[1];
+        let o_mag_sq = o[0] * o[0] + o[1] * o[1];
+        let t_mag_sq = t[0] * t[0] + t[1] * t[1];
+        let angle_flag = ot_dp >= 0. && ot_dp * ot_dp >= cos_1deg_sq * o_mag_sq * t_mag_sq;
+
+        for theta in 0..3 {
+            let k = (t[theta] / (o[theta] + 1e-30)).clamp(0., 1.);
+            let mut rst = k * o[theta];
+            if angle_flag {
+                if rst > 0. {
+                    rst = (rst * ENHANCEMENT_GAIN_LIMIT).min(t[theta]);
+                } else if rst < 0. {
+                    rst = (rst * ENHANCEMENT_GAIN_LIMIT).max(t[theta]);
+                }
+            }
+            restored[theta].data[i] = rst;
+            impairments[theta].data[i] = t[theta] - rst;
+        }
+    }
+    (restored, impairments)
+}
+
+/// The contrast masking threshold at each position, from the impairments in every band.
+fn masking_threshold(impairments: &[LumaPlane; 3]) -> LumaPlane {
+    let (width, height) = (impairments[0].width, impairments[0].height);
+    let mut threshold = LumaPlane {
+        data: vec![0.; width * height],
+        width,
+        height,
+    };
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.;
+            for band in impairments {
+                for dy in -1..=1 {
+                    let row = mirror(y as isize + dy, height) * width;
+                    for dx in -1..=1 {
+                        let weight = if dx == 0 && dy == 0 {
+                            1. / 15.
+                        } else {
+                            1. / 30.
+                        };
+                        sum += weight * band.data[row + mirror(x as isize + dx, width)].abs();
+                    }
+                }
+            }
+            threshold.data[y * width + x] = sum;
+        }
+    }
+    threshold
+}
+
+/// Sums `f` cubed over a band, excluding its borders, and returns the cube root.
+fn sum_cubes(band: &LumaPlane, f: impl Fn(usize, f32) -> f32) -> f64 {
+    let left = (band.width as f64 * BORDER_FACTOR - 0.5) as usize;
+    let top = (band.height as f64 * BORDER_FACTOR - 0.5) as usize;
+    let (right, bottom) = (band.width - left, band.height - top);
+    let mut accum = 0.;
+    for y in top..bottom {
+        for x in left..right {
+            let i = y * band.width + x;
+            let value = f(i, band.data[i]) as f64;
+            accum += value * value * value;
+        }
+    }
+    let inner_area = ((right - left) * (bottom - top)) as f64;
+    accum.cbrt() + (inner_area / 32.).cbrt()
+}
+
+/// The just noticeable quantization step of the given wavelet band,
+/// from Watson et al., "Visibility of Wavelet Quantization Noise".
+fn quantization_step(scale: usize, theta: usize) -> f32 {
+    // Model parameters for luma
+    const A: f64 = 0.495;
+    const K: f64 = 0.466;
+    const F0: f64 = 0.401;
+    const G: [f64; 4] = [1.501, 1.0, 0.534, 1.0];
+    const BASIS_AMPLITUDES: [[f64; 4]; 4] = [
+        [0.62171, 0.67234, 0.72709, 0.67234],
+        [0.34537, 0.41317, 0.49428, 0.41317],
+        [0.18004, 0.22727, 0.28688, 0.22727],
+        [0.091401, 0.11792, 0.15214, 0.11792],
+    ];
+
+    // The resolution of the display, in pixels per degree of visual angle
+    let r = VIEW_DISTANCE * DISPLAY_HEIGHT * PI / 180.;
+    let temp = (2f64.powi(scale as i32 + 1) * F0 * G[theta] / r).log10();
+    (2. * A * 10f64.powf(K * temp * temp) / BASIS_AMPLITUDES[scale][theta]) as f32
+}
@@ -0,0 +1,471 @@
+//! Video Multi-Method Assessment Fusion, a perceptual video quality metric.
+//!
+//! VMAF fuses several elementary features using a regression model
+//! trained on subjective scores. This module calculates the features used by
+//! the `vmaf_v0.6.1` family of models from the luma plane of each frame:
+//!
+//! - VIF, the Visual Information Fidelity at four scales
+//! - ADM, the Additive Distortion Measure, also known as DLM
+//! - Motion, the mean difference between consecutive blurred reference frames
+//!
+//! Features are calculated as in libvmaf's floating point feature extractors.
+//! They are fused by a model loaded from a libvmaf-format JSON file,
+//! such as `vmaf_v0.6.1.json`. No model is bundled with this crate.
+//!
+//! Scores are generally between 0 and 100, where higher is better.
+
+mod adm;
+mod model;
+mod motion;
+mod vif;
+
+pub use self::model::VmafModel;
+
+#[cfg(feature = "decode")]
+use crate::video::decode::Decoder;
+use crate::video::pixel::{CastFromPrimitive, Pixel};
+use crate::video::{ComparisonInfo, FrameRef, PlaneRef, VideoMetric, VideoMetricResults};
+#[cfg(feature = "decode")]
+use crate::video::{FramePairReader, VideoOptions};
+use crate::MetricsError;
+use std::error::Error;
+use std::sync::Mutex;
+
+/// Calculate the VMAF metric between two video clips, using the given model.
+/// Higher is better.
+///
+/// The first video is the reference, and the second is the distorted video.
+/// The aggregate score is the mean of the per-frame scores.
+/// By default, this will return at the end of the shorter of the two clips,
+/// comparing any frames up to that point.
+#[cfg(feature = "decode")]
+#[inline]
+pub fn calculate_video_vmaf<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    model: &VmafModel,
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<f64>, Box<dyn Error + Send + Sync>> {
+    Vmaf::new(model.clone()).process_video(decoder1, decoder2, options)
+}
+
+/// Calculate the VMAF metric between two video frames, using the given model.
+/// Higher is better.
+///
+/// A single frame has no motion, so this may differ from the score
+/// of the same frame within a video.
+#[inline]
+pub fn calculate_frame_vmaf<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+    model: &VmafModel,
+) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    let metric = Vmaf::new(model.clone());
+    let features = metric.process_frame(&frame1.into(), &frame2.into())?;
+    Ok(model.predict(&features, features.motion))
+}
+
+/// The VMAF metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+///
+/// The motion feature depends on the previous reference frame,
+/// so frames must be processed in order, and each `Vmaf` may only be used
+/// for one video at a time.
+#[derive(Debug)]
+pub struct Vmaf {
+    model: VmafModel,
+    /// The blurred luma of the previous reference frame.
+    previous_blurred: Mutex<Option<LumaPlane>>,
+}
+
+impl Vmaf {
+    /// Creates a VMAF metric which fuses features with the given model.
+    pub fn new(model: VmafModel) -> Self {
+        Vmaf {
+            model,
+            previous_blurred: Mutex::new(None),
+        }
+    }
+}
+
+/// The elementary features of VMAF for one frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VmafFeatures {
+    /// VIF at each of the four scales, from finest to coarsest.
+    pub vif: [f64; 4],
+    /// ADM, including the enhancement gain limit (the `adm2` feature of libvmaf).
+    pub adm: f64,
+    /// The mean absolute difference between the blurred reference luma
+    /// of this frame and the previous one, or 0 for the first frame.
+    pub motion: f64,
+}
+
+impl VideoMetric for Vmaf {
+    type FrameResult = VmafFeatures;
+    type VideoResult = VideoMetricResults<f64>;
+
+    /// Frames are always processed one at a time, in order,
+    /// because the motion feature depends on the previous frame.
+    #[cfg(feature = "decode")]
+    fn process_video_frames<T: Pixel, D: Decoder>(
+        &self,
+        reader: &mut FramePairReader<T, D>,
+    ) -> Result<Vec<Self::FrameResult>, Box<dyn Error + Send + Sync>> {
+        *self.previous_blurred.lock().unwrap() = None;
+        let mut metrics = Vec::new();
+        while let Some((frame1, frame2)) = reader.read_pair()? {
+            metrics.push(self.process_frame(&FrameRef::from(&frame1), &FrameRef::from(&frame2))?);
+        }
+        Ok(metrics)
+    }
+
+    fn process_frame<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
+        if frame1.bit_depth < 8 {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Bit depths below 8 are not supported",
+            }
+            .into());
+        }
+
+        let reference = LumaPlane::new(&frame1.planes[0], frame1.bit_depth);
+        let distorted = LumaPlane::new(&frame2.planes[0], frame2.bit_depth);
+
+        let blurred = motion::blur(&reference);
+        let mut previous_blurred = self.previous_blurred.lock().unwrap();
+        let motion = previous_blurred
+            .as_ref()
+            .map_or(0., |previous| motion::mean_abs_diff(previous, &blurred));
+        *previous_blurred = Some(blurred);
+        drop(previous_blurred);
+
+        Ok(VmafFeatures {
+            vif: vif::vif_scales(&reference, &distorted),
+            adm: adm::adm(&reference, &distorted),
+            motion,
+        })
+    }
+
+    fn aggregate_frame_results(
+        &self,
+        metrics: &[Self::FrameResult],
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let frames = metrics
+            .iter()
+            .enumerate()
+            .map(|(i, features)| {
+                // The `motion2` feature is the smaller of the motion
+                // to the previous frame and to the next frame
+                let motion2 = metrics
+                    .get(i + 1)
+                    .map_or(features.motion, |next| features.motion.min(next.motion));
+                self.model.predict(features, motion2)
+            })
+            .collect::<Vec<_>>();
+        Ok(VideoMetricResults {
+            aggregate: frames.iter().sum::<f64>() / frames.len() as f64,
+            frames,
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+        })
+    }
+}
+
+/// A luma plane converted to floating point, scaled to the 8-bit range,
+/// and offset to be centered around zero.
+#[derive(Debug, Clone)]
+struct LumaPlane {
+    data: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+impl LumaPlane {
+    fn new<T: Pixel>(plane: &PlaneRef<T>, bit_depth: usize) -> Self {
+        let scale = 1. / (1 << (bit_depth - 8)) as f32;
+        LumaPlane {
+            data: plane
+                .rows()
+                .flat_map(|row| row.iter())
+                .map(|&pixel| u16::cast_from(pixel) as f32 * scale - 128.)
+                .collect(),
+            width: plane.width(),
+            height: plane.height(),
+        }
+    }
+
+    fn map(&self, f: impl Fn(f32) -> f32) -> LumaPlane {
+        LumaPlane {
+            data: self.data.iter().map(|&x| f(x)).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    fn zip_map(&self, other: &LumaPlane, f: impl Fn(f32, f32) -> f32) -> LumaPlane {
+        LumaPlane {
+            data: self
+                .data
+                .iter()
+                .zip(other.data.iter())
+                .map(|(&x, &y)| f(x, y))
+                .collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Applies a symmetric, separable filter, mirroring the plane at its edges.
+    fn filter(&self, kernel: &[f32]) -> LumaPlane {
+        let (width, height) = (self.width, self.height);
+        let radius = kernel.len() as isize / 2;
+        let mut vertical = vec![0.; width * height];
+        for y in 0..height {
+            for (k, &weight) in kernel.iter().enumerate() {
+                let src_y = mirror(y as isize + k as isize - radius, height);
+                let src = &self.data[src_y * width..][..width];
+                for (dst, &x) in vertical[y * width..][..width].iter_mut().zip(src) {
+                    *dst += weight * x;
+                }
+            }
+        }
+        let mut data = vec![0.; width * height];
+        for y in 0..height {
+            let src = &vertical[y * width..][..width];
+            for (x, dst) in data[y * width..][..width].iter_mut().enumerate() {
+                *dst = kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &weight)| {
+                        weight * src[mirror(x as isize + k as isize - radius, width)]
+                    })
+                    .sum();
+            }
+        }
+        LumaPlane {
+            data,
+            width,
+            height,
+        }
+    }
+
+    /// Keeps every other sample in each dimension.
+    fn decimate(&self) -> LumaPlane {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = &self.data[y * 2 * self.width..];
+            data.extend((0..width).map(|x| row[x * 2]));
+        }
+        LumaPlane {
+            data,
+            width,
+            height,
+        }
+    }
+}
+
+/// Reflects an index which falls outside of `0..len` back into it, as libvmaf does.
+fn mirror(i: isize, len: usize) -> usize {
+    let len = len as isize;
+    let i = if i < 0 {
+        -i
+    } else if i >= len {
+        2 * len - i - 1
+    } else {
+        i
+    };
+    i.max(0).min(len - 1) as usize
+}
+
+/// Builds a normalized Gaussian kernel with `len` taps and a standard deviation of `len / 5`.
+fn gaussian_kernel(len: usize) -> Vec<f32> {
+    let sigma = len as f64 / 5.;
+    let radius = (len / 2) as f64;
+    let kernel = (0..len)
+        .map(|i| (-(i as f64 - radius).powi(2) / (2. * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let sum = kernel.iter().sum::<f64>();
+    kernel.iter().map(|&x| (x / sum) as f32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::VideoMetricAccumulator;
+    use serde_json::Value;
+    use std::fs::{self, File};
+
+    // A minimal model for tests which do not depend on the scores it predicts.
+    const MODEL: &str = r#"{
+        "model_dict": {
+            "model_type": "LIBSVMNUSVR",
+            "norm_type": "linear_rescale",
+            "feature_names": ["VMAF_feature_adm2_score", "VMAF_feature_motion2_score"],
+            "slopes": [0.1, 2.0, 0.5],
+            "intercepts": [-0.5, -1.0, 0.0],
+            "score_clip": [0.0, 10.0],
+            "model": "svm_type nu_svr\nkernel_type rbf\ngamma 0.5\nnr_class 2\ntotal_sv 2\nrho -0.25\nSV\n1.5 1:0.5 2:1 \n-0.75 2:0.5 \n"
+        }
+    }"#;
+
+    // libvmaf calculates the features of `vmaf_v0.6.1` in fixed point by default,
+    // so they differ slightly from the floating point features calculated here.
+    const FEATURE_TOLERANCE: f64 = 0.005;
+    const SCORE_TOLERANCE: f64 = 0.5;
+
+    #[test]
+    fn vmaf_identical_frames() {
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        let features = Vmaf::new(VmafModel::from_json(MODEL).unwrap())
+            .process_frame(&FrameRef::from(&frame), &FrameRef::from(&frame))
+            .unwrap();
+        for &vif in &features.vif {
+            assert_metric_eq(1.0, vif);
+        }
+        assert_metric_eq(1.0, features.adm);
+        assert_eq!(0.0, features.motion);
+    }
+
+    #[test]
+    fn vmaf_accumulator_yuv420p8() {
+        let model = VmafModel::from_json(MODEL).unwrap();
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let expected =
+            calculate_video_vmaf(&mut dec1, &mut dec2, &model, &VideoOptions::default()).unwrap();
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let mut accumulator = VideoMetricAccumulator::new(Vmaf::new(model));
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<u8>(), dec2.read_video_frame::<u8>())
+        {
+            accumulator.push(&frame1, &frame2).unwrap();
+        }
+        let result = accumulator.finish().unwrap();
+        assert_eq!(3, result.frames.len());
+        assert_eq!(expected.frames, result.frames);
+        assert_eq!(expected.aggregate, result.aggregate);
+    }
+
+    /// Looks up a feature in the per-frame metrics of a libvmaf JSON log,
+    /// which prefixes feature names with the extractor used, such as `integer_adm2`.
+    fn libvmaf_metric(metrics: &Value, name: &str) -> f64 {
+        metrics
+            .as_object()
+            .unwrap()
+            .iter()
+            .find(|(key, _)| *key == name || key.ends_with(&format!("_{}", name)))
+            .and_then(|(_, value)| value.as_f64())
+            .unwrap_or_else(|| panic!("libvmaf log has no {}", name))
+    }
+
+    fn assert_within(expected: f64, value: f64, tolerance: f64, name: &str) {
+        assert!(
+            (expected - value).abs() <= tolerance,
+            "{}: expected {} but got {}",
+            name,
+            expected,
+            value
+        );
+    }
+
+    /// Compares the features and scores of each frame with a libvmaf JSON log.
+    fn compare_with_libvmaf<T: Pixel>(input: &str, output: &str, log: &str) {
+        let model = VmafModel::from_file("./testfiles/vmaf_v0.6.1.json").unwrap();
+        let log: Value = serde_json::from_str(&fs::read_to_string(log).unwrap()).unwrap();
+        let expected = log["frames"].as_array().unwrap();
+
+        let mut file1 = File::open(input).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open(output).unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let metric = Vmaf::new(model.clone());
+        let mut features = Vec::new();
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<T>(), dec2.read_video_frame::<T>())
+        {
+            features.push(
+                metric
+                    .process_frame(&FrameRef::from(&frame1), &FrameRef::from(&frame2))
+                    .unwrap(),
+            );
+        }
+        assert_eq!(expected.len(), features.len());
+
+        let info = ComparisonInfo::default();
+        let scores = metric.aggregate_frame_results(&features, &info).unwrap();
+        for (i, (expected, frame)) in expected.iter().zip(&features).enumerate() {
+            let metrics = &expected["metrics"];
+            let motion2 = features
+                .get(i + 1)
+                .map_or(frame.motion, |next| frame.motion.min(next.motion));
+            assert_within(
+                libvmaf_metric(metrics, "adm2"),
+                frame.adm,
+                FEATURE_TOLERANCE,
+                "adm2",
+            );
+            assert_within(
+                libvmaf_metric(metrics, "motion2"),
+                motion2,
+                FEATURE_TOLERANCE,
+                "motion2",
+            );
+            for (scale, &vif) in frame.vif.iter().enumerate() {
+                let name = format!("vif_scale{}", scale);
+                assert_within(
+                    libvmaf_metric(metrics, &name),
+                    vif,
+                    FEATURE_TOLERANCE,
+                    &name,
+                );
+            }
+            assert_within(
+                libvmaf_metric(metrics, "vmaf"),
+                scores.frames[i],
+                SCORE_TOLERANCE,
+                "vmaf",
+            );
+        }
+    }
+
+    // These compare against libvmaf, and need the files it uses and produces in `testfiles`:
+    // `vmaf_v0.6.1.json` from libvmaf's `model` directory, and the logs written by
+    // `vmaf -r yuv420p8_input.y4m -d yuv420p8_output.y4m -m path=vmaf_v0.6.1.json
+    // --json -o vmaf_v0.6.1_yuv420p8.json`, and likewise for `yuv420p10`.
+    #[test]
+    #[ignore]
+    fn vmaf_libvmaf_yuv420p8() {
+        compare_with_libvmaf::<u8>(
+            "./testfiles/yuv420p8_input.y4m",
+            "./testfiles/yuv420p8_output.y4m",
+            "./testfiles/vmaf_v0.6.1_yuv420p8.json",
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn vmaf_libvmaf_yuv420p10() {
+        compare_with_libvmaf::<u16>(
+            "./testfiles/yuv420p10_input.y4m",
+            "./testfiles/yuv420p10_output.y4m",
+            "./testfiles/vmaf_v0.6.1_yuv420p10.json",
+        );
+    }
+}
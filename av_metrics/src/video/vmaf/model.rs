@@ -0,0 +1,333 @@
+//! Loads libvmaf models, which fuse the VMAF features into a score.
+
+use super::VmafFeatures;
+use crate::MetricsError;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// A libvmaf support vector regression model, which fuses VMAF features into a score.
+///
+/// Models are loaded from libvmaf's JSON format, with a `model_dict` containing
+/// an `LIBSVMNUSVR` model, as used by `vmaf_v0.6.1.json` and its variants.
+#[derive(Debug, Clone)]
+pub struct VmafModel {
+    features: Vec<Feature>,
+    normalization: Option<Normalization>,
+    svm: Svm,
+    score_transform: Option<ScoreTransform>,
+    score_clip: Option<(f64, f64)>,
+}
+
+/// A feature which a model may use.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Feature {
+    Adm2,
+    Motion,
+    Motion2,
+    Vif(usize),
+}
+
+impl Feature {
+    fn from_name(name: &str) -> Option<Self> {
+        // libvmaf feature names, such as `VMAF_feature_adm2_score`
+        let name = name.trim_start_matches("VMAF_feature_");
+        Some(match name {
+            "adm2_score" => Feature::Adm2,
+            "motion_score" => Feature::Motion,
+            "motion2_score" => Feature::Motion2,
+            "vif_scale0_score" => Feature::Vif(0),
+            "vif_scale1_score" => Feature::Vif(1),
+            "vif_scale2_score" => Feature::Vif(2),
+            "vif_scale3_score" => Feature::Vif(3),
+            _ => return None,
+        })
+    }
+
+    fn value(self, features: &VmafFeatures, motion2: f64) -> f64 {
+        match self {
+            Feature::Adm2 => features.adm,
+            Feature::Motion => features.motion,
+            Feature::Motion2 => motion2,
+            Feature::Vif(scale) => features.vif[scale],
+        }
+    }
+}
+
+/// Linearly rescales the features before prediction, and the score after it.
+#[derive(Debug, Clone)]
+struct Normalization {
+    /// The first element applies to the score, and the rest to each feature.
+    slopes: Vec<f64>,
+    intercepts: Vec<f64>,
+}
+
+/// An epsilon-SVR or nu-SVR model with a radial basis function kernel.
+#[derive(Debug, Clone)]
+struct Svm {
+    gamma: f64,
+    rho: f64,
+    /// The coefficient and feature values of each support vector.
+    support_vectors: Vec<(f64, Vec<f64>)>,
+}
+
+/// A polynomial which maps the score, if it is enabled.
+#[derive(Debug, Clone)]
+struct ScoreTransform {
+    p0: f64,
+    p1: f64,
+    p2: f64,
+    out_gte_in: bool,
+    out_lte_in: bool,
+}
+
+fn malformed(reason: &'static str) -> Box<dyn Error + Send + Sync> {
+    MetricsError::MalformedInput { reason }.into()
+}
+
+impl VmafModel {
+    /// Loads a model from a libvmaf JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a model in libvmaf's JSON format.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let json: Value = serde_json::from_str(json)?;
+        let dict = json
+            .get("model_dict")
+            .ok_or_else(|| malformed("VMAF model has no model_dict"))?;
+
+        match dict.get("model_type").and_then(Value::as_str) {
+            Some("LIBSVMNUSVR") => {}
+            _ => return Err(malformed("Only LIBSVMNUSVR VMAF models are supported")),
+        }
+
+        let features = dict
+            .get("feature_names")
+            .and_then(Value::as_array)
+            .ok_or_else(|| malformed("VMAF model has no feature_names"))?
+            .iter()
+            .map(|name| {
+                name.as_str()
+                    .and_then(Feature::from_name)
+                    .ok_or_else(|| malformed("VMAF model uses an unsupported feature"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let normalization = match dict.get("norm_type").and_then(Value::as_str) {
+            Some("linear_rescale") => {
+                let slopes = number_array(dict.get("slopes"))
+                    .ok_or_else(|| malformed("VMAF model has no slopes"))?;
+                let intercepts = number_array(dict.get("intercepts"))
+                    .ok_or_else(|| malformed("VMAF model has no intercepts"))?;
+                if slopes.len() != features.len() + 1 || intercepts.len() != features.len() + 1 {
+                    return Err(malformed(
+                        "VMAF model normalization does not match its features",
+                    ));
+                }
+                Some(Normalization { slopes, intercepts })
+            }
+            Some("none") | None => None,
+            _ => return Err(malformed("VMAF model uses an unsupported norm_type")),
+        };
+
+        let svm = Svm::parse(
+            dict.get("model")
+                .and_then(Value::as_str)
+                .ok_or_else(|| malformed("VMAF model has no model"))?,
+            features.len(),
+        )?;
+
+        let score_transform = match dict.get("score_transform") {
+            Some(transform) if transform.get("enabled").map_or(false, is_true) => {
+                let coefficient = |name| transform.get(name).and_then(Value::as_f64).unwrap_or(0.);
+                Some(ScoreTransform {
+                    p0: coefficient("p0"),
+                    p1: coefficient("p1"),
+                    p2: coefficient("p2"),
+                    out_gte_in: transform.get("out_gte_in").map_or(false, is_true),
+                    out_lte_in: transform.get("out_lte_in").map_or(false, is_true),
+                })
+            }
+            _ => None,
+        };
+
+        let score_clip = match number_array(dict.get("score_clip")) {
+            Some(clip) if clip.len() == 2 => Some((clip[0], clip[1])),
+            Some(_) => return Err(malformed("VMAF model score_clip must have two values")),
+            None => None,
+        };
+
+        Ok(VmafModel {
+            features,
+            normalization,
+            svm,
+            score_transform,
+            score_clip,
+        })
+    }
+
+    /// Predicts the score of one frame.
+    ///
+    /// `motion2` is the smaller of the motion of this frame and the next one.
+    pub(super) fn predict(&self, features: &VmafFeatures, motion2: f64) -> f64 {
+        let mut values = self
+            .features
+            .iter()
+            .map(|feature| feature.value(features, motion2))
+            .collect::<Vec<_>>();
+        if let Some(normalization) = &self.normalization {
+            for (i, value) in values.iter_mut().enumerate() {
+                *value = normalization.slopes[i + 1] * *value + normalization.intercepts[i + 1];
+            }
+        }
+
+        let mut score = self.svm.predict(&values);
+        if let Some(normalization) = &self.normalization {
+            score = (score - normalization.intercepts[0]) / normalization.slopes[0];
+        }
+        if let Some(transform) = &self.score_transform {
+            let transformed = transform.p0 + transform.p1 * score + transform.p2 * score * score;
+            score = if transform.out_gte_in {
+                transformed.max(score)
+            } else if transform.out_lte_in {
+                transformed.min(score)
+            } else {
+                transformed
+            };
+        }
+        if let Some((min, max)) = self.score_clip {
+            score = score.max(min).min(max);
+        }
+        score
+    }
+}
+
+impl Svm {
+    /// Parses a model in libsvm's text format.
+    fn parse(model: &str, feature_count: usize) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut gamma = None;
+        let mut rho = None;
+        let mut lines = model.lines();
+        for line in &mut lines {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("svm_type"), Some("nu_svr")) | (Some("svm_type"), Some("epsilon_svr")) => {}
+                (Some("svm_type"), _) => return Err(malformed("VMAF model is not an SVR model")),
+                (Some("kernel_type"), Some("rbf")) => {}
+                (Some("kernel_type"), _) => {
+                    return Err(malformed("VMAF model does not use an RBF kernel"))
+                }
+                (Some("gamma"), Some(value)) => gamma = Some(value.parse::<f64>()?),
+                (Some("rho"), Some(value)) => rho = Some(value.parse::<f64>()?),
+                (Some("SV"), None) => break,
+                _ => {}
+            }
+        }
+
+        let support_vectors = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut words = line.split_whitespace();
+                let coefficient = words.next().unwrap().parse::<f64>()?;
+                let mut values = vec![0.; feature_count];
+                for word in words {
+                    // libsvm feature indices start at 1
+                    let mut parts = word.splitn(2, ':');
+                    let index = parts.next().unwrap().parse::<usize>()?;
+                    let value = parts
+                        .next()
+                        .ok_or_else(|| malformed("VMAF model has a malformed support vector"))?
+                        .parse::<f64>()?;
+                    if index == 0 || index > feature_count {
+                        return Err(malformed("VMAF model support vector has too many features"));
+                    }
+                    values[index - 1] = value;
+                }
+                Ok((coefficient, values))
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
+        Ok(Svm {
+            gamma: gamma.ok_or_else(|| malformed("VMAF model has no gamma"))?,
+            rho: rho.ok_or_else(|| malformed("VMAF model has no rho"))?,
+            support_vectors,
+        })
+    }
+
+    fn predict(&self, values: &[f64]) -> f64 {
+        self.support_vectors
+            .iter()
+            .map(|(coefficient, support_vector)| {
+                let distance_sq = support_vector
+                    .iter()
+                    .zip(values)
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum::<f64>();
+                coefficient * (-self.gamma * distance_sq).exp()
+            })
+            .sum::<f64>()
+            - self.rho
+    }
+}
+
+fn number_array(value: Option<&Value>) -> Option<Vec<f64>> {
+    value?.as_array()?.iter().map(Value::as_f64).collect()
+}
+
+/// libvmaf models store some flags as strings.
+fn is_true(value: &Value) -> bool {
+    value.as_bool() == Some(true) || value.as_str() == Some("true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = r#"{
+        "model_dict": {
+            "model_type": "LIBSVMNUSVR",
+            "norm_type": "linear_rescale",
+            "feature_names": ["VMAF_feature_adm2_score", "VMAF_feature_motion2_score"],
+            "slopes": [0.1, 2.0, 0.5],
+            "intercepts": [-0.5, -1.0, 0.0],
+            "score_clip": [0.0, 10.0],
+            "model": "svm_type nu_svr\nkernel_type rbf\ngamma 0.5\nnr_class 2\ntotal_sv 2\nrho -0.25\nSV\n1.5 1:0.5 2:1 \n-0.75 2:0.5 \n"
+        }
+    }"#;
+
+    #[test]
+    fn vmaf_model_predict() {
+        let model = VmafModel::from_json(MODEL).unwrap();
+        let features = VmafFeatures {
+            vif: [0.; 4],
+            adm: 0.0,
+            motion: 0.0,
+        };
+        // Normalized features are (-1, 0), which are a squared distance of 3.25
+        // from the first support vector and 1.25 from the second
+        let svm = 1.5 * (-1.625f64).exp() - 0.75 * (-0.625f64).exp() + 0.25;
+        let expected = (svm + 0.5) / 0.1;
+        assert!((expected - model.predict(&features, 0.0)).abs() < 1e-9);
+
+        // Scores are clipped to the range of the model
+        let features = VmafFeatures {
+            vif: [0.; 4],
+            adm: 0.75,
+            motion: 1.0,
+        };
+        assert_eq!(10.0, model.predict(&features, 2.0));
+    }
+
+    #[test]
+    fn vmaf_model_unsupported() {
+        let model = MODEL.replace("VMAF_feature_motion2_score", "float_ssim");
+        assert!(VmafModel::from_json(&model).is_err());
+        let model = MODEL.replace("kernel_type rbf", "kernel_type linear");
+        assert!(VmafModel::from_json(&model).is_err());
+        let model = MODEL.replace("LIBSVMNUSVR", "BOOTSTRAP_LIBSVMNUSVR");
+        assert!(VmafModel::from_json(&model).is_err());
+    }
+}
@@ -0,0 +1,19 @@
+//! The motion feature, which measures temporal change in the reference video.
+
+use super::{gaussian_kernel, LumaPlane};
+
+/// Blurs a reference frame before it is compared to the previous one.
+pub(super) fn blur(reference: &LumaPlane) -> LumaPlane {
+    reference.filter(&gaussian_kernel(5))
+}
+
+/// The mean absolute difference between two blurred frames.
+pub(super) fn mean_abs_diff(previous: &LumaPlane, current: &LumaPlane) -> f64 {
+    let sum = previous
+        .data
+        .iter()
+        .zip(current.data.iter())
+        .map(|(&a, &b)| (a - b).abs() as f64)
+        .sum::<f64>();
+    sum / current.data.len() as f64
+}
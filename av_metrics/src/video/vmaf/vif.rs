@@ -0,0 +1,80 @@
+//! Visual Information Fidelity, measured at four scales.
+
+use super::{gaussian_kernel, LumaPlane};
+
+/// The variance of the additive noise in the HVS model.
+const SIGMA_NSQ: f32 = 2.0;
+/// The maximum gain which the distorted image may apply to the reference.
+const ENHANCEMENT_GAIN_LIMIT: f32 = 100.0;
+const EPSILON: f32 = 1e-10;
+
+/// Calculates VIF at each of four scales, from finest to coarsest.
+pub(super) fn vif_scales(reference: &LumaPlane, distorted: &LumaPlane) -> [f64; 4] {
+    let mut scores = [0.; 4];
+    let mut reference = reference.clone();
+    let mut distorted = distorted.clone();
+    for (scale, score) in scores.iter_mut().enumerate() {
+        // The kernel covers 17, 9, 5 and 3 pixels at each scale
+        let kernel = gaussian_kernel((16 >> scale) + 1);
+        if scale > 0 {
+            reference = reference.filter(&kernel).decimate();
+            distorted = distorted.filter(&kernel).decimate();
+        }
+
+        let mu1 = reference.filter(&kernel);
+        let mu2 = distorted.filter(&kernel);
+        let ref_sq = reference.map(|x| x * x).filter(&kernel);
+        let dis_sq = distorted.map(|x| x * x).filter(&kernel);
+        let ref_dis = reference.zip_map(&distorted, |x, y| x * y).filter(&kernel);
+
+        let mut num = 0.;
+        let mut den = 0.;
+        for i in 0..mu1.data.len() {
+            let (n, d) = vif_statistic(
+                mu1.data[i],
+                mu2.data[i],
+                ref_sq.data[i],
+                dis_sq.data[i],
+                ref_dis.data[i],
+            );
+            num += n as f64;
+            den += d as f64;
+        }
+        *score = if den == 0. { 1. } else { num / den };
+    }
+    scores
+}
+
+/// Returns the information in the distorted and reference images around one pixel.
+fn vif_statistic(mu1: f32, mu2: f32, ref_sq: f32, dis_sq: f32, ref_dis: f32) -> (f32, f32) {
+    // The largest variance of 8-bit samples
+    const SIGMA_MAX_INV: f32 = 4.0 / (255.0 * 255.0);
+
+    let sigma1_sq = (ref_sq - mu1 * mu1).max(0.);
+    let sigma2_sq = (dis_sq - mu2 * mu2).max(0.);
+    let sigma12 = ref_dis - mu1 * mu2;
+
+    if sigma1_sq < SIGMA_NSQ {
+        // Flat areas of the reference contain no information,
+        // so only penalize noise which was added to them
+        return (1. - sigma2_sq * SIGMA_MAX_INV, 1.);
+    }
+
+    let mut g = sigma12 / (sigma1_sq + EPSILON);
+    let mut sv_sq = sigma2_sq - g * sigma12;
+    if sigma2_sq < EPSILON {
+        g = 0.;
+        sv_sq = 0.;
+    }
+    if g < 0. {
+        sv_sq = sigma2_sq;
+        g = 0.;
+    }
+    let sv_sq = sv_sq.max(EPSILON);
+    let g = g.min(ENHANCEMENT_GAIN_LIMIT);
+
+    (
+        (1. + g * g * sigma1_sq / (sv_sq + SIGMA_NSQ)).log2(),
+        (1. + sigma1_sq / SIGMA_NSQ).log2(),
+    )
+}
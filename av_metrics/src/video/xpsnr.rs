@@ -0,0 +1,615 @@
+//! Extended Perceptually Weighted Peak Signal-to-Noise Ratio.
+//!
+//! XPSNR weights the squared error of each block of the frame by the visual
+//! activity of the reference in that block. Errors in flat, static areas are more
+//! visible than errors in detailed or moving areas, so they are weighted more heavily.
+//! Spatial activity is measured with a high-pass filter, and temporal activity
+//! with the difference from the previous reference frames. The luma weights of
+//! each block are also applied to the co-located chroma samples.
+//!
+//! Because temporal activity depends on the previous frames, frames must be
+//! processed in order.
+//!
+//! See C. R. Helmrich et al., "XPSNR: A Low-Complexity Extension of the
+//! Perceptually Weighted Peak Signal-to-Noise Ratio for High-Resolution Video
+//! Quality Assessment", ICASSP 2020, for more details.
+
+#[cfg(feature = "decode")]
+use crate::video::decode::Decoder;
+use crate::video::pixel::{CastFromPrimitive, Pixel};
+use crate::video::psnr::{
+    calculate_frame_result, calculate_plane_psnr_metrics, calculate_plane_total_squared_error,
+    calculate_psnr, calculate_summed_psnr, PsnrMetrics,
+};
+#[cfg(feature = "decode")]
+use crate::video::{decode_and_process_video, FramePairReader, VideoOptions};
+use crate::video::{
+    ComparisonInfo, FrameRef, PlanarMetrics, PlaneRef, VideoMetric, VideoMetricResults,
+};
+use crate::MetricsError;
+use std::error::Error;
+use std::sync::Mutex;
+
+/// The weight of temporal activity relative to spatial activity.
+const TEMPORAL_GAMMA: u64 = 2;
+/// Frame rates from which temporal activity uses second-order differences.
+const HIGH_FRAME_RATE: f64 = 32.0;
+
+/// Calculates the XPSNR for two videos. Higher is better.
+///
+/// The frame rate of the first video determines how temporal activity is measured.
+/// The aggregate score of each plane is calculated from the mean of the square root
+/// of each frame's weighted squared error.
+/// XPSNR is capped at 100 in order to avoid skewed statistics
+/// from e.g. all black frames, which would
+/// otherwise show an XPSNR of infinity.
+#[cfg(feature = "decode")]
+#[inline]
+pub fn calculate_video_xpsnr<D: Decoder>(
+    decoder1: &mut D,
+    decoder2: &mut D,
+    options: &VideoOptions,
+) -> Result<VideoMetricResults<PlanarMetrics>, Box<dyn Error + Send + Sync>> {
+    Xpsnr::default().process_video(decoder1, decoder2, options)
+}
+
+/// Calculates the XPSNR for two video frames. Higher is better.
+///
+/// A single frame has no temporal activity, so this may differ from the score
+/// of the same frame within a video.
+#[inline]
+pub fn calculate_frame_xpsnr<'a, T: Pixel>(
+    frame1: impl Into<FrameRef<'a, T>>,
+    frame2: impl Into<FrameRef<'a, T>>,
+) -> Result<PlanarMetrics, Box<dyn Error + Send + Sync>> {
+    let metrics = Xpsnr::default().process_frame(&frame1.into(), &frame2.into())?;
+    Ok(calculate_frame_result(&metrics))
+}
+
+/// The XPSNR metric, for use with a
+/// [`VideoMetricAccumulator`](../struct.VideoMetricAccumulator.html).
+///
+/// Temporal activity depends on the previous reference frames,
+/// so frames must be processed in order, and each `Xpsnr` may only be used
+/// for one video at a time.
+#[derive(Debug, Default)]
+pub struct Xpsnr {
+    /// Whether temporal activity is measured with second-order differences,
+    /// which is done for high frame rate video.
+    second_order: bool,
+    /// The luma of the previous reference frame, and the one before it.
+    history: Mutex<Option<[LumaPlane; 2]>>,
+}
+
+impl Xpsnr {
+    /// Creates an XPSNR metric for video with the given number of frames per second.
+    ///
+    /// When processing a video, the frame rate reported by the first decoder
+    /// is used instead.
+    pub fn with_frame_rate(frame_rate: f64) -> Self {
+        Xpsnr {
+            second_order: frame_rate >= HIGH_FRAME_RATE,
+            history: Mutex::new(None),
+        }
+    }
+}
+
+impl VideoMetric for Xpsnr {
+    type FrameResult = [PsnrMetrics; 3];
+    type VideoResult = VideoMetricResults<PlanarMetrics>;
+
+    #[cfg(feature = "decode")]
+    fn process_video<D: Decoder>(
+        &self,
+        decoder1: &mut D,
+        decoder2: &mut D,
+        options: &VideoOptions,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        let frame_rate = decoder1.get_video_details().frame_rate;
+        let metric = Xpsnr::with_frame_rate(if frame_rate.den > 0 {
+            frame_rate.num as f64 / frame_rate.den as f64
+        } else {
+            0.
+        });
+        decode_and_process_video(&metric, decoder1, decoder2, options)
+    }
+
+    /// Frames are always processed one at a time, in order,
+    /// because temporal activity depends on the previous frames.
+    #[cfg(feature = "decode")]
+    fn process_video_frames<T: Pixel, D: Decoder>(
+        &self,
+        reader: &mut FramePairReader<T, D>,
+    ) -> Result<Vec<Self::FrameResult>, Box<dyn Error + Send + Sync>> {
+        *self.history.lock().unwrap() = None;
+        let mut metrics = Vec::new();
+        while let Some((frame1, frame2)) = reader.read_pair()? {
+            metrics.push(self.process_frame(&FrameRef::from(&frame1), &FrameRef::from(&frame2))?);
+        }
+        Ok(metrics)
+    }
+
+    fn process_frame<T: Pixel>(
+        &self,
+        frame1: &FrameRef<T>,
+        frame2: &FrameRef<T>,
+    ) -> Result<Self::FrameResult, Box<dyn Error + Send + Sync>> {
+        frame1.can_compare(frame2)?;
+        if frame1.bit_depth < 8 {
+            return Err(MetricsError::UnsupportedInput {
+                reason: "Bit depths below 8 are not supported",
+            }
+            .into());
+        }
+
+        let bit_depth = frame1.bit_depth;
+        let luma = LumaPlane::new(&frame1.planes[0]);
+        let mut history = self.history.lock().unwrap();
+        // The first frame has no temporal activity
+        let previous = match history.take() {
+            Some(previous) if previous[0].has_size_of(&luma) => previous,
+            _ => [luma.clone(), luma.clone()],
+        };
+
+        let (width, height) = (luma.width, luma.height);
+        let block_size = block_size(width, height);
+        let metrics = if block_size < 4 {
+            // The frame is too small to weight, so this is the same as PSNR
+            [0, 1, 2].map(|p| {
+                calculate_plane_psnr_metrics(&frame1.planes[p], &frame2.planes[p], bit_depth)
+            })
+        } else {
+            let weights = self.block_weights(&luma, &previous, block_size, bit_depth);
+            // The average activity of a frame, which normalizes the weights
+            let ratio = (width * height) as f64 / (3840. * 2160.);
+            let mean_activity =
+                (16. * (1u64 << (2 * bit_depth - 9)) as f64 / ratio.max(0.00001).sqrt()).sqrt();
+            let chroma_dec = frame1.chroma_sampling.get_decimation().unwrap_or((0, 0));
+            [0, 1, 2].map(|p| {
+                let (plane1, plane2) = (&frame1.planes[p], &frame2.planes[p]);
+                let mut metrics = calculate_plane_psnr_metrics(plane1, plane2, bit_depth);
+                if metrics.n_pixels > 0 {
+                    // Chroma blocks cover the same area as the luma blocks,
+                    // so they use the same grid and weights
+                    let (xdec, ydec) = if p == 0 { (0, 0) } else { chroma_dec };
+                    let wsse = weighted_squared_error(
+                        plane1,
+                        plane2,
+                        block_size >> xdec,
+                        block_size >> ydec,
+                        &weights,
+                    );
+                    metrics.sq_err = wsse * mean_activity;
+                }
+                metrics
+            })
+        };
+
+        *history = Some([luma, previous[0].clone()]);
+        Ok(metrics)
+    }
+
+    fn aggregate_frame_results(
+        &self,
+        metrics: &[Self::FrameResult],
+        info: &ComparisonInfo,
+    ) -> Result<Self::VideoResult, Box<dyn Error + Send + Sync>> {
+        // The root of the squared error is averaged, rather than the squared error itself
+        let planes = [0, 1, 2].map(|p| {
+            let mean_root =
+                metrics.iter().map(|m| m[p].sq_err.sqrt()).sum::<f64>() / metrics.len() as f64;
+            PsnrMetrics {
+                sq_err: mean_root * mean_root,
+                ..metrics[0][p]
+            }
+        });
+        Ok(VideoMetricResults {
+            aggregate: PlanarMetrics {
+                y: calculate_psnr(planes[0]),
+                u: calculate_psnr(planes[1]),
+                v: calculate_psnr(planes[2]),
+                avg: calculate_summed_psnr(&planes),
+            },
+            frames: metrics.iter().map(calculate_frame_result).collect(),
+            frame_counts: info.frame_counts,
+            resync: info.resync.clone(),
+        })
+    }
+}
+
+impl Xpsnr {
+    /// Calculates the weight of each block of the frame, in raster order.
+    fn block_weights(
+        &self,
+        luma: &LumaPlane,
+        previous: &[LumaPlane; 2],
+        block_size: usize,
+        bit_depth: usize,
+    ) -> Vec<f64> {
+        let (width, height) = (luma.width, luma.height);
+        let blocks_per_row = (width + block_size - 1) / block_size;
+        // Above HD resolution, activity is measured at half resolution
+        let downsample = width * height > 2048 * 1152;
+        let border = if downsample { 2 } else { 1 };
+
+        let mut weights = Vec::new();
+        for y in (0..height).step_by(block_size) {
+            let block_height = block_size.min(height - y);
+            for x in (0..width).step_by(block_size) {
+                let block_width = block_size.min(width - x);
+                let i = weights.len();
+
+                // The high-pass filter needs samples around the block,
+                // so the edges of the frame are excluded
+                let x_start = if x > 0 { 0 } else { border };
+                let y_start = if y > 0 { 0 } else { border };
+                let x_end = if x + block_width < width {
+                    block_width
+                } else {
+                    block_width.saturating_sub(border)
+                };
+                let y_end = if y + block_height < height {
+                    block_height
+                } else {
+                    block_height.saturating_sub(border)
+                };
+                if x_end <= x_start || y_end <= y_start {
+                    weights.push(1.);
+                    continue;
+                }
+
+                let spatial = luma.spatial_activity(
+                    (x + x_start, y + y_start),
+                    (x + x_end, y + y_end),
+                    downsample,
+                );
+                let temporal = luma.temporal_activity(
+                    previous,
+                    (x, y),
+                    (x + block_width, y + block_height),
+                    downsample,
+                    self.second_order,
+                );
+                let activity = spatial as f64 / ((x_end - x_start) * (y_end - y_start)) as f64
+                    + (TEMPORAL_GAMMA * temporal) as f64 / (block_width * block_height) as f64;
+                // The lower limit accounts for the gain of the high-pass filter
+                let activity = activity.max((1 << (bit_depth - 6)) as f64);
+                weights.push(1. / activity);
+
+                // Small frames use the lowest weight of each block and its neighbors,
+                // so that isolated flat blocks are not weighted too heavily
+                if width * height <= 640 * 480 {
+                    let mut neighbors = if x == 0 {
+                        if i > 1 {
+                            weights[i - 2]
+                        } else {
+                            0.
+                        }
+                    } else if x > block_size {
+                        weights[i - 2].max(weights[i])
+                    } else {
+                        weights[i]
+                    };
+                    if i > blocks_per_row {
+                        neighbors = neighbors.max(weights[i - 1 - blocks_per_row]);
+                    }
+                    if i > 0 && weights[i - 1] > neighbors {
+                        weights[i - 1] = neighbors;
+                    }
+                    if x + block_size >= width && y + block_size >= height && i > blocks_per_row {
+                        let neighbors = weights[i - 1].max(weights[i - blocks_per_row]);
+                        if weights[i] > neighbors {
+                            weights[i] = neighbors;
+                        }
+                    }
+                }
+            }
+        }
+        weights
+    }
+}
+
+/// The side length of the square blocks which are weighted,
+/// which scales with the resolution of the frame.
+fn block_size(width: usize, height: usize) -> usize {
+    let ratio = (width * height) as f64 / (3840. * 2160.);
+    4 * (32. * ratio.sqrt() + 0.5) as usize
+}
+
+/// Sums the squared error of each block of a plane, multiplied by the weight of the block.
+fn weighted_squared_error<T: Pixel>(
+    plane1: &PlaneRef<T>,
+    plane2: &PlaneRef<T>,
+    block_width: usize,
+    block_height: usize,
+    weights: &[f64],
+) -> f64 {
+    let (width, height) = (plane1.width(), plane1.height());
+    debug_assert_eq!(
+        ((width + block_width - 1) / block_width) * ((height + block_height - 1) / block_height),
+        weights.len()
+    );
+    let mut wsse = 0.;
+    let mut i = 0;
+    for y in (0..height).step_by(block_height) {
+        let h = block_height.min(height - y);
+        for x in (0..width).step_by(block_width) {
+            let w = block_width.min(width - x);
+            let sse = calculate_plane_total_squared_error(
+                &plane1.region(x, y, w, h),
+                &plane2.region(x, y, w, h),
+            );
+            wsse += sse * weights[i];
+            i += 1;
+        }
+    }
+    wsse
+}
+
+/// The luma plane of a reference frame.
+#[derive(Debug, Clone)]
+struct LumaPlane {
+    data: Vec<i32>,
+    width: usize,
+    height: usize,
+}
+
+impl LumaPlane {
+    fn new<T: Pixel>(plane: &PlaneRef<T>) -> Self {
+        let mut data = Vec::with_capacity(plane.width() * plane.height());
+        for row in plane.rows() {
+            data.extend(row.iter().map(|&sample| i32::cast_from(sample)));
+        }
+        LumaPlane {
+            data,
+            width: plane.width(),
+            height: plane.height(),
+        }
+    }
+
+    fn has_size_of(&self, other: &LumaPlane) -> bool {
+        self.width == other.width && self.height == other.height
+    }
+
+    /// The sample at the given position, clamped to the edges of the plane.
+    fn at(&self, x: isize, y: isize) -> i32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.data[y * self.width + x]
+    }
+
+    /// Sums the absolute output of a high-pass filter over the given area.
+    fn spatial_activity(
+        &self,
+        (x0, y0): (usize, usize),
+        (x1, y1): (usize, usize),
+        downsample: bool,
+    ) -> u64 {
+        let mut sum = 0;
+        if downsample {
+            for y in (y0..y1).step_by(2) {
+                for x in (x0..x1).step_by(2) {
+                    let (x, y) = (x as isize, y as isize);
+                    let s = |dx, dy| self.at(x + dx, y + dy);
+                    let f = 12 * (s(0, 0) + s(1, 0) + s(0, 1) + s(1, 1))
+                        - 3 * (s(0, -1) + s(1, -1) + s(0, 2) + s(1, 2))
+                        - 3 * (s(-1, 0) + s(2, 0) + s(-1, 1) + s(2, 1))
+                        - 2 * (s(-1, -1) + s(2, -1) + s(-1, 2) + s(2, 2))
+                        - (s(-1, -2) + s(0, -2) + s(1, -2) + s(2, -2))
+                        - (s(-1, 3) + s(0, 3) + s(1, 3) + s(2, 3))
+                        - (s(-2, -1) + s(-2, 0) + s(-2, 1) + s(-2, 2))
+                        - (s(3, -1) + s(3, 0) + s(3, 1) + s(3, 2));
+                    sum += f.unsigned_abs() as u64;
+                }
+            }
+        } else {
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let (x, y) = (x as isize, y as isize);
+                    let s = |dx, dy| self.at(x + dx, y + dy);
+                    let f = 12 * s(0, 0)
+                        - 2 * (s(-1, 0) + s(1, 0) + s(0, -1) + s(0, 1))
+                        - (s(-1, -1) + s(1, -1) + s(-1, 1) + s(1, 1));
+                    sum += f.unsigned_abs() as u64;
+                }
+            }
+        }
+        sum
+    }
+
+    /// Sums the absolute difference from the previous frames over the given area.
+    fn temporal_activity(
+        &self,
+        previous: &[LumaPlane; 2],
+        (x0, y0): (usize, usize),
+        (x1, y1): (usize, usize),
+        downsample: bool,
+        second_order: bool,
+    ) -> u64 {
+        let step = if downsample { 2 } else { 1 };
+        let mut sum = 0;
+        for y in (y0..y1).step_by(step) {
+            for x in (x0..x1).step_by(step) {
+                let (x, y) = (x as isize, y as isize);
+                let s = |plane: &LumaPlane| {
+                    if downsample {
+                        plane.at(x, y)
+                            + plane.at(x + 1, y)
+                            + plane.at(x, y + 1)
+                            + plane.at(x + 1, y + 1)
+                    } else {
+                        plane.at(x, y)
+                    }
+                };
+                let t = if second_order {
+                    s(self) - 2 * s(&previous[0]) + s(&previous[1])
+                } else {
+                    s(self) - s(&previous[0])
+                };
+                sum += t.unsigned_abs() as u64;
+            }
+        }
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_metric_eq;
+    use crate::video::decode::Y4mDecoder;
+    use crate::video::VideoMetricAccumulator;
+    use std::fs::{self, File};
+
+    fn run(name: &str) -> VideoMetricResults<PlanarMetrics> {
+        let mut file1 = File::open(format!("./testfiles/{}_input.y4m", name)).unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open(format!("./testfiles/{}_output.y4m", name)).unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        calculate_video_xpsnr(&mut dec1, &mut dec2, &VideoOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn xpsnr_identical_frames() {
+        let mut file = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec = Y4mDecoder::new(&mut file).unwrap();
+        let frame = dec.read_video_frame::<u8>().unwrap();
+        let result = calculate_frame_xpsnr(&frame, &frame).unwrap();
+        assert_metric_eq(100.0, result.y);
+        assert_metric_eq(100.0, result.avg);
+    }
+
+    #[test]
+    fn xpsnr_yuv420p8() {
+        let result = run("yuv420p8");
+        assert_eq!(3, result.frames.len());
+        assert_metric_eq(31.8333, result.aggregate.y);
+        assert_metric_eq(35.0249, result.aggregate.u);
+        assert_metric_eq(38.1638, result.aggregate.v);
+        assert_metric_eq(32.8824, result.aggregate.avg);
+    }
+
+    #[test]
+    fn xpsnr_yuv420p10() {
+        let result = run("yuv420p10");
+        assert_metric_eq(31.9822, result.aggregate.y);
+        assert_metric_eq(33.0462, result.aggregate.avg);
+    }
+
+    #[test]
+    fn xpsnr_yuv444p8() {
+        let result = run("yuv444p8");
+        assert_metric_eq(31.7931, result.aggregate.y);
+        assert_metric_eq(35.1402, result.aggregate.avg);
+    }
+
+    #[test]
+    fn xpsnr_yuv400p8() {
+        let result = run("yuv400p8");
+        assert_metric_eq(31.8333, result.aggregate.y);
+        assert!(result.aggregate.u.is_nan());
+        assert!(result.aggregate.v.is_nan());
+    }
+
+    #[test]
+    fn xpsnr_accumulator_yuv420p8() {
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let frame_rate = dec1.get_video_details().frame_rate;
+        let frame_rate = frame_rate.num as f64 / frame_rate.den as f64;
+        let mut accumulator = VideoMetricAccumulator::new(Xpsnr::with_frame_rate(frame_rate));
+        while let (Ok(frame1), Ok(frame2)) =
+            (dec1.read_video_frame::<u8>(), dec2.read_video_frame::<u8>())
+        {
+            accumulator.push(&frame1, &frame2).unwrap();
+        }
+        let result = accumulator.finish().unwrap();
+        assert_metric_eq(run("yuv420p8").aggregate.y, result.aggregate.y);
+    }
+
+    #[test]
+    fn xpsnr_temporal_activity() {
+        // The first frame of a video has no temporal activity,
+        // so it matches the score of the frame on its own
+        let result = run("yuv420p8");
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let frame1 = dec1.read_video_frame::<u8>().unwrap();
+        let frame2 = dec2.read_video_frame::<u8>().unwrap();
+        let first = calculate_frame_xpsnr(&frame1, &frame2).unwrap();
+        assert_metric_eq(first.y, result.frames[0].y);
+    }
+
+    #[test]
+    fn xpsnr_odd_dimensions_yuv420p8() {
+        use crate::video::{FrameInfo, PlaneRef};
+
+        // Crop the luma plane to an odd size, so the chroma planes are rounded up,
+        // and their block grid must still match the luma weights
+        fn cropped<T: Pixel>(frame: &FrameInfo<T>) -> FrameRef<'_, T> {
+            let plane_ref = |i: usize, crop: usize| {
+                let plane = &frame.planes[i];
+                let (width, height) = (plane.width - crop, plane.height - crop);
+                PlaneRef::new(&plane.data, width, height, plane.width).unwrap()
+            };
+            FrameRef {
+                planes: [plane_ref(0, 1), plane_ref(1, 0), plane_ref(2, 0)],
+                bit_depth: frame.bit_depth,
+                chroma_sampling: frame.chroma_sampling,
+            }
+        }
+
+        let mut file1 = File::open("./testfiles/yuv420p8_input.y4m").unwrap();
+        let mut dec1 = Y4mDecoder::new(&mut file1).unwrap();
+        let mut file2 = File::open("./testfiles/yuv420p8_output.y4m").unwrap();
+        let mut dec2 = Y4mDecoder::new(&mut file2).unwrap();
+        let frame1 = dec1.read_video_frame::<u8>().unwrap();
+        let frame2 = dec2.read_video_frame::<u8>().unwrap();
+        let whole = calculate_frame_xpsnr(&frame1, &frame2).unwrap();
+        let result = calculate_frame_xpsnr(cropped(&frame1), cropped(&frame2)).unwrap();
+        assert!(result.u.is_finite());
+        assert!(result.v.is_finite());
+        assert!((whole.y - result.y).abs() < 0.5);
+
+        let result = calculate_frame_xpsnr(cropped(&frame1), cropped(&frame1)).unwrap();
+        assert_metric_eq(100.0, result.avg);
+    }
+
+    /// Reads the per-frame XPSNR of each plane from an ffmpeg `xpsnr` stats file,
+    /// which has a line for each frame of the form
+    /// `n: <frame>  XPSNR y: <dB>  XPSNR u: <dB>  XPSNR v: <dB>`.
+    fn read_ffmpeg_stats(path: &str) -> Vec<[f64; 3]> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with("n:"))
+            .map(|line| {
+                let tokens = line.split_whitespace().collect::<Vec<_>>();
+                ["y:", "u:", "v:"].map(|plane| {
+                    let i = tokens.iter().position(|&token| token == plane).unwrap();
+                    tokens[i + 1].parse().unwrap()
+                })
+            })
+            .collect()
+    }
+
+    // This compares against ffmpeg's `xpsnr` filter, and needs its stats file in `testfiles`,
+    // written by `ffmpeg -i yuv420p8_output.y4m -i yuv420p8_input.y4m
+    // -lavfi xpsnr=stats_file=xpsnr_yuv420p8.log -f null -`.
+    #[test]
+    #[ignore]
+    fn xpsnr_ffmpeg_yuv420p8() {
+        let expected = read_ffmpeg_stats("./testfiles/xpsnr_yuv420p8.log");
+        let result = run("yuv420p8");
+        assert_eq!(expected.len(), result.frames.len());
+        for (expected, frame) in expected.iter().zip(&result.frames) {
+            assert_metric_eq(expected[0], frame.y);
+            assert_metric_eq(expected[1], frame.u);
+            assert_metric_eq(expected[2], frame.v);
+        }
+    }
+}
@@ -1,9 +1,17 @@
+use av_metrics::video::align::{find_alignment, Alignment, AlignmentOptions};
+use av_metrics::video::bit_depth::{normalize_decoders, BitDepthNormalization};
+use av_metrics::video::butteraugli::ButteraugliScore;
+use av_metrics::video::chroma::{resample_chroma_decoders, ChromaResampling};
+use av_metrics::video::scale::{scale_decoders, ScaleFilter, ScaleOptions, ScaleTarget};
+use av_metrics::video::suite::{calculate_video_suite, Metric, SuiteResults};
+use av_metrics::video::vif::VifMetrics;
+use av_metrics::video::vmaf::{calculate_video_vmaf, VmafModel};
+use av_metrics::video::xpsnr::calculate_video_xpsnr;
 use av_metrics::video::*;
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 use maplit::hashmap;
 use serde::Serialize;
 use std::collections::HashMap;
-use std::error::Error;
 use std::fs::File;
 use std::path::Path;
 use std::process::exit;
@@ -35,7 +43,13 @@ fn main() {
                 .possible_value("psnrhvs")
                 .possible_value("ssim")
                 .possible_value("msssim")
-                .possible_value("ciede2000"),
+                .possible_value("ciede2000")
+                .possible_value("deitp")
+                .possible_value("vif")
+                .possible_value("ssimulacra2")
+                .possible_value("butteraugli")
+                .possible_value("vmaf")
+                .possible_value("xpsnr"),
         )
         .arg(
             Arg::with_name("JSON")
@@ -43,20 +57,296 @@ fn main() {
                 .long("json")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("PER_FRAME")
+                .help("Also output the score of each frame, labelled with its timestamp")
+                .long("per-frame")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("START")
+                .help("The first frame to compare, after skipping any offset frames")
+                .long("start")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("END")
+                .help(
+                    "Stop comparing before this frame, after skipping any offset frames--\
+                     must not be less than --start",
+                )
+                .long("end")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("FRAMES")
+                .help("The maximum number of frames to compare")
+                .long("frames")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("OFFSET1")
+                .help("Number of frames to skip at the start of the first input")
+                .long("offset1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("OFFSET2")
+                .help("Number of frames to skip at the start of the second input")
+                .long("offset2")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("AUTO_ALIGN")
+                .help(
+                    "Detect frames which were added or dropped at the start of either input, \
+                     and skip them before comparing",
+                )
+                .long("auto-align")
+                .takes_value(false)
+                .conflicts_with_all(&["OFFSET1", "OFFSET2"]),
+        )
+        .arg(
+            Arg::with_name("ALIGN_MAX_OFFSET")
+                .help("The largest offset tried by --auto-align--defaults to 10 frames")
+                .long("align-max-offset")
+                .takes_value(true)
+                .requires("AUTO_ALIGN"),
+        )
+        .arg(
+            Arg::with_name("ALIGN_FRAMES")
+                .help("The number of frames compared by --auto-align at each offset--defaults to 5")
+                .long("align-frames")
+                .takes_value(true)
+                .requires("AUTO_ALIGN"),
+        )
+        .arg(
+            Arg::with_name("RESYNC")
+                .help(
+                    "Match each frame of the second input to the best frame of the first input, \
+                     and report frames which were dropped or duplicated",
+                )
+                .long("resync")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("RESYNC_LOOKAHEAD")
+                .help("The most consecutive dropped frames detected by --resync--defaults to 2")
+                .long("resync-lookahead")
+                .takes_value(true)
+                .requires("RESYNC"),
+        )
+        .arg(
+            Arg::with_name("SCALE_TO")
+                .help(
+                    "Rescale inputs with different resolutions before comparing--\
+                     `reference` scales the second input to the resolution of the first, \
+                     and `WIDTHxHEIGHT` scales both inputs to that resolution",
+                )
+                .long("scale-to")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("SCALE_FILTER")
+                .help("The filter used by --scale-to")
+                .long("scale-filter")
+                .takes_value(true)
+                .possible_value("bilinear")
+                .possible_value("bicubic")
+                .possible_value("lanczos")
+                .default_value("lanczos"),
+        )
+        .arg(
+            Arg::with_name("NORMALIZE_BIT_DEPTH")
+                .help(
+                    "Convert inputs with different bit depths to a common bit depth--\
+                     `shift-up` converts to the higher bit depth, \
+                     and `round-down` converts to the lower bit depth",
+                )
+                .long("normalize-bit-depth")
+                .takes_value(true)
+                .possible_value("shift-up")
+                .possible_value("round-down"),
+        )
+        .arg(
+            Arg::with_name("RESAMPLE_CHROMA")
+                .help(
+                    "Resample inputs with different chroma subsampling to a common subsampling--\
+                     `upsample` converts to the higher chroma resolution, \
+                     and `downsample` converts to the lower chroma resolution",
+                )
+                .long("resample-chroma")
+                .takes_value(true)
+                .possible_value("upsample")
+                .possible_value("downsample"),
+        )
+        .arg(
+            Arg::with_name("MATRIX")
+                .help(
+                    "The matrix coefficients used to convert the inputs to RGB \
+                     for CIEDE2000, SSIMULACRA2, and Butteraugli--\
+                     overrides the value from the input files, which defaults to `bt709`",
+                )
+                .long("matrix")
+                .takes_value(true)
+                .possible_value("bt601")
+                .possible_value("bt709")
+                .possible_value("bt2020"),
+        )
+        .arg(
+            Arg::with_name("RANGE")
+                .help(
+                    "The color range of the inputs for CIEDE2000, ΔE ITP, SSIMULACRA2, \
+                     and Butteraugli--\
+                     overrides the value from the input files, \
+                     which is `limited` unless a Y4M file has an `XCOLORRANGE=FULL` parameter",
+                )
+                .long("range")
+                .takes_value(true)
+                .possible_value("limited")
+                .possible_value("full"),
+        )
+        .arg(
+            Arg::with_name("TRANSFER")
+                .help(
+                    "The transfer function of the inputs for ΔE ITP--\
+                     defaults to `bt1886`, for standard dynamic range video",
+                )
+                .long("transfer")
+                .takes_value(true)
+                .possible_value("bt1886")
+                .possible_value("pq")
+                .possible_value("hlg"),
+        )
+        .arg(
+            Arg::with_name("VMAF_MODEL")
+                .help(
+                    "A libvmaf JSON model, such as `vmaf_v0.6.1.json`--\
+                     VMAF is only calculated if a model is given",
+                )
+                .long("vmaf-model")
+                .takes_value(true)
+                .required_if("METRIC", "vmaf"),
+        )
+        .arg(
+            Arg::with_name("STRICT_LENGTH")
+                .help(
+                    "What to do if the inputs have a different number of frames--\
+                     `truncate` compares up to the end of the shorter input, \
+                     `warn` also reports the length of each input, \
+                     and `error` fails",
+                )
+                .long("strict-length")
+                .takes_value(true)
+                .possible_value("truncate")
+                .possible_value("warn")
+                .possible_value("error")
+                .default_value("truncate"),
+        )
+        .arg(
+            Arg::with_name("THREADS")
+                .help("Number of threads used to calculate metrics--defaults to the number of CPUs")
+                .long("threads")
+                .takes_value(true),
+        )
         .get_matches();
+    if let Some(threads) = parse_count(&cli, "THREADS", "number of threads") {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("Failed to initialize thread pool");
+    }
+    let mut options = VideoOptions {
+        length_policy: match cli.value_of("STRICT_LENGTH") {
+            Some("warn") => LengthPolicy::Warn,
+            Some("error") => LengthPolicy::Error,
+            _ => LengthPolicy::Truncate,
+        },
+        offset1: parse_count(&cli, "OFFSET1", "offset").unwrap_or(0),
+        offset2: parse_count(&cli, "OFFSET2", "offset").unwrap_or(0),
+        start_frame: parse_count(&cli, "START", "start frame").unwrap_or(0),
+        end_frame: parse_count(&cli, "END", "end frame"),
+        frame_limit: parse_count(&cli, "FRAMES", "number of frames"),
+        resync_lookahead: if cli.is_present("RESYNC") {
+            Some(parse_count(&cli, "RESYNC_LOOKAHEAD", "lookahead").unwrap_or(2))
+        } else {
+            None
+        },
+        scale: cli.value_of("SCALE_TO").map(|target| ScaleOptions {
+            target: parse_scale_target(target),
+            filter: match cli.value_of("SCALE_FILTER") {
+                Some("bilinear") => ScaleFilter::Bilinear,
+                Some("bicubic") => ScaleFilter::Bicubic,
+                _ => ScaleFilter::Lanczos,
+            },
+        }),
+        bit_depth_normalization: match cli.value_of("NORMALIZE_BIT_DEPTH") {
+            Some("shift-up") => Some(BitDepthNormalization::ShiftUp),
+            Some("round-down") => Some(BitDepthNormalization::RoundDown),
+            _ => None,
+        },
+        chroma_resampling: match cli.value_of("RESAMPLE_CHROMA") {
+            Some("upsample") => Some(ChromaResampling::Upsample),
+            Some("downsample") => Some(ChromaResampling::Downsample),
+            _ => None,
+        },
+        matrix_coefficients: match cli.value_of("MATRIX") {
+            Some("bt601") => Some(MatrixCoefficients::BT601),
+            Some("bt709") => Some(MatrixCoefficients::BT709),
+            Some("bt2020") => Some(MatrixCoefficients::BT2020NCL),
+            _ => None,
+        },
+        color_range: match cli.value_of("RANGE") {
+            Some("limited") => Some(ColorRange::Limited),
+            Some("full") => Some(ColorRange::Full),
+            _ => None,
+        },
+        transfer_characteristics: match cli.value_of("TRANSFER") {
+            Some("bt1886") => Some(TransferCharacteristics::BT1886),
+            Some("pq") => Some(TransferCharacteristics::PQ),
+            Some("hlg") => Some(TransferCharacteristics::HLG),
+            _ => None,
+        },
+    };
+    let vmaf_model = cli.value_of("VMAF_MODEL").map(|path| {
+        VmafModel::from_file(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load VMAF model: {}", e);
+            exit(1);
+        })
+    });
     let input1 = cli.value_of("INPUT1").unwrap();
     let input2 = cli.value_of("INPUT2").unwrap();
     let input_type1 = InputType::detect(input1);
     let input_type2 = InputType::detect(input2);
     match (input_type1, input_type2) {
         (InputType::Video(c1), InputType::Video(c2)) => {
+            let alignment = if cli.is_present("AUTO_ALIGN") {
+                let defaults = AlignmentOptions::default();
+                let align_options = AlignmentOptions {
+                    max_offset: parse_count(&cli, "ALIGN_MAX_OFFSET", "maximum offset")
+                        .unwrap_or(defaults.max_offset),
+                    frames: parse_count(&cli, "ALIGN_FRAMES", "number of frames")
+                        .unwrap_or(defaults.frames),
+                };
+                let alignment = align_videos(input1, c1, input2, c2, &align_options, &options);
+                alignment.apply(&mut options);
+                Some(alignment)
+            } else {
+                None
+            };
             run_video_metrics(
                 input1,
                 c1,
                 input2,
                 c2,
-                cli.is_present("JSON"),
                 cli.value_of("METRIC"),
+                vmaf_model.as_ref(),
+                &options,
+                OutputOptions {
+                    serialize: cli.is_present("JSON"),
+                    show_frames: cli.is_present("PER_FRAME"),
+                    alignment,
+                },
             );
         }
         (InputType::Audio(_c1), InputType::Audio(_c2)) => {
@@ -74,6 +364,31 @@ fn main() {
     }
 }
 
+fn parse_count(cli: &ArgMatches, arg: &str, description: &str) -> Option<usize> {
+    cli.value_of(arg).map(|value| {
+        value.parse::<usize>().unwrap_or_else(|_| {
+            eprintln!("Invalid {}: {}", description, value);
+            exit(1);
+        })
+    })
+}
+
+fn parse_scale_target(target: &str) -> ScaleTarget {
+    if target == "reference" {
+        return ScaleTarget::Reference;
+    }
+    let size = target
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+    match size {
+        Some((width, height)) if width > 0 && height > 0 => ScaleTarget::Size { width, height },
+        _ => {
+            eprintln!("Invalid scale target: {}", target);
+            exit(1);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum InputType {
     Video(VideoContainer),
@@ -103,9 +418,9 @@ enum VideoContainer {
 
 impl VideoContainer {
     // TODO: Actually be generic and support more input types
-    pub fn get_decoder<'d>(&self, file: &'d mut File) -> y4m::Decoder<'d, File> {
+    pub fn get_decoder<'d>(&self, file: &'d mut File) -> Y4mDecoder<&'d mut File> {
         match *self {
-            VideoContainer::Y4M => y4m::Decoder::new(file).expect("Failed to read y4m file"),
+            VideoContainer::Y4M => Y4mDecoder::new(file).expect("Failed to read y4m file"),
         }
     }
 }
@@ -115,244 +430,408 @@ enum AudioContainer {
     // Coming soon
 }
 
+#[derive(Debug, Clone, Copy)]
+struct OutputOptions {
+    /// Output results as JSON
+    serialize: bool,
+    /// Output the score of each frame, as well as the aggregate score
+    show_frames: bool,
+    /// The alignment found by `--auto-align`, reported alongside the results
+    alignment: Option<Alignment>,
+}
+
+fn align_videos<P: AsRef<Path>>(
+    input1: P,
+    container1: VideoContainer,
+    input2: P,
+    container2: VideoContainer,
+    options: &AlignmentOptions,
+    video_options: &VideoOptions,
+) -> Alignment {
+    let mut file1 = File::open(input1).expect("Failed to open input file 1");
+    let mut file2 = File::open(input2).expect("Failed to open input file 2");
+    let mut dec1 = container1.get_decoder(&mut file1);
+    let mut dec2 = container2.get_decoder(&mut file2);
+    let (mut dec1, mut dec2) =
+        normalize_decoders(&mut dec1, &mut dec2, video_options.bit_depth_normalization);
+    let (mut dec1, mut dec2) =
+        resample_chroma_decoders(&mut dec1, &mut dec2, video_options.chroma_resampling);
+    let (mut dec1, mut dec2) = scale_decoders(&mut dec1, &mut dec2, video_options.scale.as_ref());
+    find_alignment(&mut dec1, &mut dec2, options).unwrap_or_else(|e| {
+        eprintln!("Failed to align inputs: {}", e);
+        exit(1);
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 fn run_video_metrics<P: AsRef<Path>>(
     input1: P,
     container1: VideoContainer,
     input2: P,
     container2: VideoContainer,
-    serialize: bool,
     metric: Option<&str>,
+    vmaf_model: Option<&VmafModel>,
+    options: &VideoOptions,
+    output: OutputOptions,
 ) {
-    let mut results = HashMap::new();
-
-    if metric.is_none() || metric == Some("psnr") {
-        let psnr = Psnr::run(
-            input1.as_ref(),
-            container1,
-            input2.as_ref(),
-            container2,
-            serialize,
-        );
-        if serialize {
-            results.insert("psnr", hashmap! {"result" => psnr});
+    let metrics = match metric {
+        Some("psnr") => vec![Metric::Psnr],
+        Some("apsnr") => vec![Metric::APsnr],
+        Some("psnrhvs") => vec![Metric::PsnrHvs],
+        Some("ssim") => vec![Metric::Ssim],
+        Some("msssim") => vec![Metric::MsSsim],
+        Some("ciede2000") => vec![Metric::Ciede2000],
+        Some("deitp") => vec![Metric::DeltaEItp],
+        Some("vif") => vec![Metric::Vif],
+        Some("ssimulacra2") => vec![Metric::Ssimulacra2],
+        Some("butteraugli") => vec![Metric::Butteraugli],
+        Some("vmaf") | Some("xpsnr") => Vec::new(),
+        // SSIMULACRA2 and Butteraugli do not support HDR video, so they are skipped for HDR inputs
+        _ => Metric::ALL
+            .iter()
+            .copied()
+            .filter(|&metric| {
+                !matches!(metric, Metric::Ssimulacra2 | Metric::Butteraugli)
+                    || matches!(
+                        options.transfer_characteristics,
+                        None | Some(TransferCharacteristics::BT1886)
+                    )
+            })
+            .collect(),
+    };
+
+    let mut file1 = File::open(&input1).expect("Failed to open input file 1");
+    let mut file2 = File::open(&input2).expect("Failed to open input file 2");
+    let mut dec1 = container1.get_decoder(&mut file1);
+    let mut dec2 = container2.get_decoder(&mut file2);
+    let details = dec1.get_video_details();
+    let results = if metrics.is_empty() {
+        SuiteResults::default()
+    } else {
+        calculate_video_suite(&mut dec1, &mut dec2, &metrics, options).unwrap_or_else(|e| {
+            eprintln!("Failed to calculate metrics: {}", e);
+            exit(1);
+        })
+    };
+    // VMAF processes frames in order, so it is calculated in a separate pass
+    let vmaf = vmaf_model.map(|model| {
+        let mut file1 = File::open(&input1).expect("Failed to open input file 1");
+        let mut file2 = File::open(&input2).expect("Failed to open input file 2");
+        let mut dec1 = container1.get_decoder(&mut file1);
+        let mut dec2 = container2.get_decoder(&mut file2);
+        calculate_video_vmaf(&mut dec1, &mut dec2, model, options).unwrap_or_else(|e| {
+            eprintln!("Failed to calculate VMAF: {}", e);
+            exit(1);
+        })
+    });
+    // XPSNR also processes frames in order, and is only calculated if it is requested
+    let xpsnr = if metric == Some("xpsnr") {
+        let mut file1 = File::open(&input1).expect("Failed to open input file 1");
+        let mut file2 = File::open(&input2).expect("Failed to open input file 2");
+        let mut dec1 = container1.get_decoder(&mut file1);
+        let mut dec2 = container2.get_decoder(&mut file2);
+        Some(
+            calculate_video_xpsnr(&mut dec1, &mut dec2, options).unwrap_or_else(|e| {
+                eprintln!("Failed to calculate XPSNR: {}", e);
+                exit(1);
+            }),
+        )
+    } else {
+        None
+    };
+    let resync = results
+        .resync
+        .as_ref()
+        .or_else(|| vmaf.as_ref().and_then(|vmaf| vmaf.resync.as_ref()))
+        .or_else(|| xpsnr.as_ref().and_then(|xpsnr| xpsnr.resync.as_ref()));
+    let frame_counts = results
+        .frame_counts
+        .or_else(|| vmaf.as_ref().and_then(|vmaf| vmaf.frame_counts))
+        .or_else(|| xpsnr.as_ref().and_then(|xpsnr| xpsnr.frame_counts));
+    let labels = FrameLabels {
+        details,
+        first_frame: options.offset1 + options.start_frame,
+        resync,
+    };
+    let frame_labels = if output.show_frames {
+        Some(&labels)
+    } else {
+        None
+    };
+
+    if let Some(counts) = frame_counts {
+        if counts.input1 != counts.input2 {
+            eprintln!(
+                "Warning: Input 1 has {} frames and input 2 has {} frames. \
+                 Only {} frames were compared.",
+                counts.input1,
+                counts.input2,
+                counts.input1.min(counts.input2)
+            );
         }
     }
 
-    if metric.is_none() || metric == Some("apsnr") {
-        let apsnr = APsnr::run(
-            input1.as_ref(),
-            container1,
-            input2.as_ref(),
-            container2,
-            serialize,
-        );
-        if serialize {
-            results.insert("apsnr", hashmap! {"result" => apsnr});
+    if output.serialize {
+        let alignment = output.alignment;
+        let mut output = HashMap::new();
+        if let Some(alignment) = alignment {
+            output.insert(
+                "alignment",
+                hashmap! {
+                    "offset1" => to_json(&alignment.offset1),
+                    "offset2" => to_json(&alignment.offset2),
+                    "psnr" => to_json(&alignment.psnr),
+                },
+            );
         }
-    }
-
-    if metric.is_none() || metric == Some("psnrhvs") {
-        let psnrhvs = PsnrHvs::run(
-            input1.as_ref(),
-            container1,
-            input2.as_ref(),
-            container2,
-            serialize,
-        );
-        if serialize {
-            results.insert("psnrhvs", hashmap! {"result" => psnrhvs});
+        if let Some(resync) = resync {
+            output.insert(
+                "resync",
+                hashmap! {
+                    "dropped" => to_json(&resync.dropped),
+                    "duplicated" => to_json(&resync.duplicated),
+                },
+            );
         }
-    }
-
-    if metric.is_none() || metric == Some("ssim") {
-        let ssim = Ssim::run(
-            input1.as_ref(),
-            container1,
-            input2.as_ref(),
-            container2,
-            serialize,
-        );
-        if serialize {
-            results.insert("ssim", hashmap! {"result" => ssim});
+        if let Some(counts) = frame_counts {
+            output.insert(
+                "frame_counts",
+                hashmap! {
+                    "input1" => to_json(&counts.input1),
+                    "input2" => to_json(&counts.input2),
+                },
+            );
         }
-    }
-
-    if metric.is_none() || metric == Some("msssim") {
-        let msssim = MsSsim::run(
-            input1.as_ref(),
-            container1,
-            input2.as_ref(),
-            container2,
-            serialize,
-        );
-        if serialize {
-            results.insert("msssim", hashmap! {"result" => msssim});
+        if let Some(psnr) = results.psnr {
+            output.insert("psnr", results_to_json(&psnr, frame_labels));
         }
-    }
-
-    if metric.is_none() || metric == Some("ciede2000") {
-        let ciede2000 = Ciede2000::run(
-            input1.as_ref(),
-            container1,
-            input2.as_ref(),
-            container2,
-            serialize,
-        );
-        if serialize {
-            results.insert("ciede2000", hashmap! {"result" => ciede2000});
+        if let Some(apsnr) = results.apsnr {
+            output.insert("apsnr", results_to_json(&apsnr, frame_labels));
         }
-    }
-
-    if serialize {
-        print!("{}", serde_json::to_string(&results).unwrap());
-    }
-}
-
-trait CliMetric {
-    type VideoResult: Serialize;
-
-    fn run<P: AsRef<Path>>(
-        input1: P,
-        container1: VideoContainer,
-        input2: P,
-        container2: VideoContainer,
-        serialize: bool,
-    ) -> Option<serde_json::Value> {
-        let mut file1 = File::open(input1).expect("Failed to open input file 1");
-        let mut file2 = File::open(input2).expect("Failed to open input file 2");
-        let mut dec1 = container1.get_decoder(&mut file1);
-        let mut dec2 = container2.get_decoder(&mut file2);
-        let result = Self::calculate_video_metric(&mut dec1, &mut dec2);
-        if let Ok(result) = result {
-            if serialize {
-                return Some(serde_json::to_value(result).unwrap());
-            } else {
-                Self::print_results(result);
+        if let Some(psnr_hvs) = results.psnr_hvs {
+            output.insert("psnrhvs", results_to_json(&psnr_hvs, frame_labels));
+        }
+        if let Some(ssim) = results.ssim {
+            output.insert("ssim", results_to_json(&ssim, frame_labels));
+        }
+        if let Some(msssim) = results.msssim {
+            output.insert("msssim", results_to_json(&msssim, frame_labels));
+        }
+        if let Some(ciede2000) = results.ciede2000 {
+            output.insert("ciede2000", results_to_json(&ciede2000, frame_labels));
+        }
+        if let Some(deitp) = results.deitp {
+            output.insert("deitp", results_to_json(&deitp, frame_labels));
+        }
+        if let Some(vif) = results.vif {
+            output.insert("vif", results_to_json(&vif, frame_labels));
+        }
+        if let Some(ssimulacra2) = results.ssimulacra2 {
+            output.insert("ssimulacra2", results_to_json(&ssimulacra2, frame_labels));
+        }
+        if let Some(butteraugli) = results.butteraugli {
+            output.insert("butteraugli", results_to_json(&butteraugli, frame_labels));
+        }
+        if let Some(vmaf) = vmaf.as_ref() {
+            output.insert("vmaf", results_to_json(vmaf, frame_labels));
+        }
+        if let Some(xpsnr) = xpsnr.as_ref() {
+            output.insert("xpsnr", results_to_json(xpsnr, frame_labels));
+        }
+        print!("{}", serde_json::to_string(&output).unwrap());
+    } else {
+        if let Some(alignment) = output.alignment {
+            println!(
+                "Alignment - Input 1 offset: {}  Input 2 offset: {}  PSNR: {:.4}",
+                alignment.offset1, alignment.offset2, alignment.psnr
+            );
+        }
+        if let Some(resync) = resync {
+            println!(
+                "Resync - Dropped frames: {}  Duplicated frames: {}",
+                format_frame_list(&resync.dropped),
+                format_frame_list(&resync.duplicated)
+            );
+        }
+        if let Some(psnr) = results.psnr {
+            print_planar_results("PSNR", &psnr, frame_labels);
+        }
+        if let Some(apsnr) = results.apsnr {
+            print_planar_results("APSNR", &apsnr, frame_labels);
+        }
+        if let Some(psnr_hvs) = results.psnr_hvs {
+            print_planar_results("PSNR HVS", &psnr_hvs, frame_labels);
+        }
+        if let Some(ssim) = results.ssim {
+            print_planar_results("SSIM", &ssim, frame_labels);
+        }
+        if let Some(msssim) = results.msssim {
+            print_planar_results("MSSSIM", &msssim, frame_labels);
+        }
+        if let Some(ciede2000) = results.ciede2000 {
+            println!("CIEDE2000 - {:.4}", ciede2000.aggregate);
+            if let Some(labels) = frame_labels {
+                for (i, frame) in ciede2000.frames.iter().enumerate() {
+                    println!("  {} - {:.4}", labels.label(i), frame);
+                }
             }
         }
-        None
+        if let Some(deitp) = results.deitp {
+            println!("DEITP - {:.4}", deitp.aggregate);
+            if let Some(labels) = frame_labels {
+                for (i, frame) in deitp.frames.iter().enumerate() {
+                    println!("  {} - {:.4}", labels.label(i), frame);
+                }
+            }
+        }
+        if let Some(vif) = results.vif {
+            println!("VIF - {}", format_vif_result(&vif.aggregate));
+            if let Some(labels) = frame_labels {
+                for (i, frame) in vif.frames.iter().enumerate() {
+                    println!("  {} - {}", labels.label(i), format_vif_result(frame));
+                }
+            }
+        }
+        if let Some(ssimulacra2) = results.ssimulacra2 {
+            println!("SSIMULACRA2 - {:.4}", ssimulacra2.aggregate);
+            if let Some(labels) = frame_labels {
+                for (i, frame) in ssimulacra2.frames.iter().enumerate() {
+                    println!("  {} - {:.4}", labels.label(i), frame);
+                }
+            }
+        }
+        if let Some(butteraugli) = results.butteraugli {
+            println!(
+                "BUTTERAUGLI - {}",
+                format_butteraugli_result(&butteraugli.aggregate)
+            );
+            if let Some(labels) = frame_labels {
+                for (i, frame) in butteraugli.frames.iter().enumerate() {
+                    println!(
+                        "  {} - {}",
+                        labels.label(i),
+                        format_butteraugli_result(frame)
+                    );
+                }
+            }
+        }
+        if let Some(vmaf) = vmaf.as_ref() {
+            println!("VMAF - {:.4}", vmaf.aggregate);
+            if let Some(labels) = frame_labels {
+                for (i, frame) in vmaf.frames.iter().enumerate() {
+                    println!("  {} - {:.4}", labels.label(i), frame);
+                }
+            }
+        }
+        if let Some(xpsnr) = xpsnr.as_ref() {
+            print_planar_results("XPSNR", xpsnr, frame_labels);
+        }
     }
-
-    fn calculate_video_metric<D: Decoder>(
-        dec1: &mut D,
-        dec2: &mut D,
-    ) -> Result<Self::VideoResult, Box<dyn Error>>;
-    fn print_results(result: Self::VideoResult);
 }
 
-struct Psnr;
-
-impl CliMetric for Psnr {
-    type VideoResult = PlanarMetrics;
-
-    fn calculate_video_metric<D: Decoder>(
-        dec1: &mut D,
-        dec2: &mut D,
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        psnr::calculate_video_psnr(dec1, dec2, None)
-    }
-
-    fn print_results(result: Self::VideoResult) {
-        println!(
-            "PSNR - Y: {:.4}  U: {:.4}  V: {:.4}  Avg: {:.4}",
-            result.y, result.u, result.v, result.avg
-        );
+fn format_frame_list(frames: &[usize]) -> String {
+    if frames.is_empty() {
+        return "none".to_string();
     }
+    frames
+        .iter()
+        .map(|frame| frame.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
-struct APsnr;
-
-impl CliMetric for APsnr {
-    type VideoResult = PlanarMetrics;
-
-    fn calculate_video_metric<D: Decoder>(
-        dec1: &mut D,
-        dec2: &mut D,
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        psnr::calculate_video_apsnr(dec1, dec2, None)
-    }
-
-    fn print_results(result: Self::VideoResult) {
-        println!(
-            "APSNR - Y: {:.4}  U: {:.4}  V: {:.4}  Avg: {:.4}",
-            result.y, result.u, result.v, result.avg
-        );
-    }
+fn to_json<T: Serialize>(result: &T) -> Option<serde_json::Value> {
+    Some(serde_json::to_value(result).unwrap())
 }
 
-struct PsnrHvs;
-
-impl CliMetric for PsnrHvs {
-    type VideoResult = PlanarMetrics;
-
-    fn calculate_video_metric<D: Decoder>(
-        dec1: &mut D,
-        dec2: &mut D,
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        psnr_hvs::calculate_video_psnr_hvs(dec1, dec2, None)
-    }
-
-    fn print_results(result: Self::VideoResult) {
-        println!(
-            "PSNR HVS - Y: {:.4}  U: {:.4}  V: {:.4}  Avg: {:.4}",
-            result.y, result.u, result.v, result.avg
-        );
+/// Converts the results of one metric to JSON, including the score of each frame
+/// if `frame_labels` is set.
+fn results_to_json<T: Serialize>(
+    results: &VideoMetricResults<T>,
+    frame_labels: Option<&FrameLabels<'_>>,
+) -> HashMap<&'static str, Option<serde_json::Value>> {
+    let mut output = hashmap! {"result" => to_json(&results.aggregate)};
+    if let Some(labels) = frame_labels {
+        let frames = results
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                hashmap! {
+                    "frame" => to_json(&labels.index(i)),
+                    "timestamp" => labels.timestamp(i).and_then(|t| to_json(&t)),
+                    "result" => to_json(frame),
+                }
+            })
+            .collect::<Vec<_>>();
+        output.insert("frames", to_json(&frames));
     }
+    output
 }
 
-struct Ssim;
+/// Labels compared frames by their index and timestamp in the first input.
+struct FrameLabels<'a> {
+    details: VideoDetails,
+    /// The index in the first input of the first frame which was compared.
+    first_frame: usize,
+    /// The frames which were compared, if the inputs were re-synced.
+    resync: Option<&'a ResyncReport>,
+}
 
-impl CliMetric for Ssim {
-    type VideoResult = PlanarMetrics;
+impl FrameLabels<'_> {
+    fn index(&self, compared_frame: usize) -> usize {
+        match self.resync {
+            Some(resync) => resync.pairs[compared_frame].0,
+            None => self.first_frame + compared_frame,
+        }
+    }
 
-    fn calculate_video_metric<D: Decoder>(
-        dec1: &mut D,
-        dec2: &mut D,
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        ssim::calculate_video_ssim(dec1, dec2, None)
+    fn timestamp(&self, compared_frame: usize) -> Option<f64> {
+        self.details.frame_timestamp(self.index(compared_frame))
     }
 
-    fn print_results(result: Self::VideoResult) {
-        println!(
-            "SSIM - Y: {:.4}  U: {:.4}  V: {:.4}  Avg: {:.4}",
-            result.y, result.u, result.v, result.avg
-        );
+    fn label(&self, compared_frame: usize) -> String {
+        match self.timestamp(compared_frame) {
+            Some(timestamp) => format!("Frame {} ({:.3}s)", self.index(compared_frame), timestamp),
+            None => format!("Frame {}", self.index(compared_frame)),
+        }
     }
 }
 
-struct MsSsim;
-
-impl CliMetric for MsSsim {
-    type VideoResult = PlanarMetrics;
-
-    fn calculate_video_metric<D: Decoder>(
-        dec1: &mut D,
-        dec2: &mut D,
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        ssim::calculate_video_msssim(dec1, dec2, None)
+fn print_planar_results(
+    name: &str,
+    results: &VideoMetricResults<PlanarMetrics>,
+    frame_labels: Option<&FrameLabels<'_>>,
+) {
+    println!("{} - {}", name, format_planar_result(&results.aggregate));
+    if let Some(labels) = frame_labels {
+        for (i, frame) in results.frames.iter().enumerate() {
+            println!("  {} - {}", labels.label(i), format_planar_result(frame));
+        }
     }
+}
 
-    fn print_results(result: Self::VideoResult) {
-        println!(
-            "MSSSIM - Y: {:.4}  U: {:.4}  V: {:.4}  Avg: {:.4}",
-            result.y, result.u, result.v, result.avg
-        );
+fn format_planar_result(result: &PlanarMetrics) -> String {
+    // 4:0:0 video has no chroma planes to report
+    if result.u.is_nan() && result.v.is_nan() {
+        return format!("Y: {:.4}  Avg: {:.4}", result.y, result.avg);
     }
+    format!(
+        "Y: {:.4}  U: {:.4}  V: {:.4}  Avg: {:.4}",
+        result.y, result.u, result.v, result.avg
+    )
 }
 
-struct Ciede2000;
-
-impl CliMetric for Ciede2000 {
-    type VideoResult = f64;
-
-    fn calculate_video_metric<D: Decoder>(
-        dec1: &mut D,
-        dec2: &mut D,
-    ) -> Result<Self::VideoResult, Box<dyn Error>> {
-        ciede::calculate_video_ciede(dec1, dec2, None)
-    }
+fn format_vif_result(result: &VifMetrics) -> String {
+    format!(
+        "Scale 0: {:.4}  Scale 1: {:.4}  Scale 2: {:.4}  Scale 3: {:.4}  Total: {:.4}",
+        result.scales[0], result.scales[1], result.scales[2], result.scales[3], result.total
+    )
+}
 
-    fn print_results(result: Self::VideoResult) {
-        println!("CIEDE2000 - {:.4}", result);
-    }
+fn format_butteraugli_result(result: &ButteraugliScore) -> String {
+    format!(
+        "Max: {:.4}  3-norm: {:.4}",
+        result.max_norm, result.three_norm
+    )
 }